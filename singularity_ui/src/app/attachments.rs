@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use slint::{
+    ComponentHandle, Image, ModelRc, Rgba8Pixel, SharedPixelBuffer, ToSharedString, VecModel,
+    winit_030::{EventResult, WinitWindowAccessor, winit::event::WindowEvent},
+};
+
+use crate::{
+    App, DraftImage,
+    core::conversations::attachments::{Attachment, IMAGE_EXTENSIONS},
+};
+
+/// Thumbnail of attachment, `None` if it can't be decoded
+fn thumbnail(attachment: &Attachment) -> Option<Image> {
+    let image = image::load_from_memory(&attachment.thumbnail)
+        .inspect_err(|e| tracing::error!("Failed to show {}. Reason: {e}", attachment.name))
+        .ok()?
+        .into_rgba8();
+
+    Some(Image::from_rgba8(
+        SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+        ),
+    ))
+}
+
+/// Thumbnails shown in chat message
+pub fn thumbnails(attachments: &[Attachment]) -> ModelRc<Image> {
+    ModelRc::from(std::rc::Rc::new(VecModel::from(
+        attachments.iter().filter_map(thumbnail).collect::<Vec<_>>(),
+    )))
+}
+
+/// Images attached to prompt not sent yet
+pub fn draft_images(attachments: &[Attachment]) -> ModelRc<DraftImage> {
+    ModelRc::from(std::rc::Rc::new(VecModel::from(
+        attachments
+            .iter()
+            .map(|attachment| DraftImage {
+                name: attachment.name.to_shared_string(),
+                thumbnail: thumbnail(attachment).unwrap_or_default(),
+            })
+            .collect::<Vec<_>>(),
+    )))
+}
+
+/// Asks which images to attach, empty if dialog was cancelled
+pub async fn pick_images() -> Vec<PathBuf> {
+    let dialog = rfd::AsyncFileDialog::new()
+        .set_title("Attach images")
+        .add_filter("Images", &IMAGE_EXTENSIONS);
+
+    async_compat::Compat::new(dialog.pick_files())
+        .await
+        .unwrap_or_default()
+        .iter()
+        .map(|file| file.path().to_owned())
+        .collect()
+}
+
+/// Files dropped on window are attached to prompt
+pub fn setup(ui: &App) {
+    let weak = ui.as_weak();
+
+    ui.window().on_winit_window_event(move |_, event| {
+        if let WindowEvent::DroppedFile(path) = event {
+            let path = path.to_string_lossy().to_shared_string();
+            let _ = weak
+                .upgrade_in_event_loop(move |ui| ui.invoke_image_dropped(path))
+                .inspect_err(|e| tracing::error!("Failed to attach dropped file. Reason: {e}"));
+        }
+
+        EventResult::Propagate
+    });
+}
@@ -0,0 +1,1583 @@
+use std::{
+    cell::{Cell, RefCell},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use slint::{ComponentHandle, Model, ModelRc, ToSharedString, VecModel};
+
+use crate::{
+    App, ChatMessage, ConversationEntry, ParameterField, SearchResult, SnippetPart,
+    app::{
+        attachments::{draft_images, pick_images, thumbnails},
+        markdown,
+    },
+    core::{
+        conversations::{
+            ConversationSettings, ConversationStore, Role, SearchHit, ThreadMessage,
+            attachments::{Attachment, load_image},
+            context::{self, ContextPlan, ContextSettings, ContextStrategy, SUMMARY_SYSTEM_PROMPT},
+            export::{self, ExportFormat},
+            queue::{Chat, PromptQueue, QueuedPrompt},
+            title::{self, TITLE_SYSTEM_PROMPT},
+            title_from_prompt,
+        },
+        llm::{
+            MODEL_NAME, llm_chat, llm_generate, llm_load, llm_pull_model, llm_supports_vision,
+            options::{GenerationOptions, OptionsError, PARAMETERS},
+        },
+        personas::{Persona, PersonaLibrary},
+    },
+};
+
+/// Conversation shown in chat, `None` until first message of a new one is sent
+type ActiveConversation = Rc<Cell<Option<i64>>>;
+/// Settings chosen before first message of a new conversation, saved once it is created
+type PendingSettings = Rc<RefCell<ConversationSettings>>;
+/// Prompts sent while their conversation waited for a reply
+type SendQueue = Rc<RefCell<PromptQueue>>;
+/// Images attached to prompt being written
+type DraftAttachments = Rc<RefCell<Vec<Attachment>>>;
+
+fn chat_message(entry: &ThreadMessage) -> Option<ChatMessage> {
+    let message = &entry.message;
+    let is_user = match message.role {
+        Role::User => true,
+        Role::Assistant => false,
+        Role::System => return None,
+    };
+
+    Some(ChatMessage {
+        id: message.id as i32,
+        text: message.content.to_shared_string(),
+        is_user,
+        blocks: markdown::message_blocks(&message.content, !is_user),
+        alternative: entry.position() as i32 + 1,
+        alternatives: entry.siblings.len() as i32,
+        previous: entry.previous().map(|id| id as i32).unwrap_or(-1),
+        next: entry.next().map(|id| id as i32).unwrap_or(-1),
+        ticket: -1,
+        queued: false,
+        images: thumbnails(&entry.attachments),
+    })
+}
+
+/// Message shown before it is saved, or if it could not be
+fn unsaved_message(is_user: bool, text: &str) -> ChatMessage {
+    ChatMessage {
+        id: -1,
+        text: text.to_shared_string(),
+        is_user,
+        blocks: markdown::message_blocks(text, !is_user),
+        alternative: 1,
+        alternatives: 1,
+        previous: -1,
+        next: -1,
+        ticket: -1,
+        queued: false,
+        images: ModelRc::default(),
+    }
+}
+
+/// Prompt waiting for reply to previous one
+fn queued_message(prompt: &QueuedPrompt) -> ChatMessage {
+    ChatMessage {
+        ticket: prompt.ticket as i32,
+        queued: true,
+        images: thumbnails(&prompt.attachments),
+        ..unsaved_message(true, &prompt.text)
+    }
+}
+
+/// Index of row showing prompt with `ticket`
+fn ticket_row(messages: &VecModel<ChatMessage>, ticket: u64) -> Option<usize> {
+    messages.iter().position(|row| row.ticket == ticket as i32)
+}
+
+/// Shows conversation `id`, or another new chat for `None`
+fn show_active(ui: &App, active: &ActiveConversation, queue: &SendQueue, id: Option<i64>) {
+    active.set(id);
+    if id.is_none() {
+        queue.borrow_mut().new_draft();
+        ui.set_context_usage(Default::default());
+        ui.set_context_fraction(0.0);
+    }
+    // Settings panel edits conversation it was opened for
+    ui.set_show_settings_panel(false);
+    ui.set_focused_message(-1);
+    ui.set_active_conversation(id.map(|id| id as i32).unwrap_or(-1));
+}
+
+/// Shows saved messages of conversation, scrolled to `focus` if it is given.
+/// Branch with `focus` is made active when it is not shown yet
+async fn open_conversation(
+    ui: &App,
+    store: &ConversationStore,
+    active: &ActiveConversation,
+    messages: &VecModel<ChatMessage>,
+    queue: &SendQueue,
+    id: i64,
+    focus: Option<i64>,
+) {
+    let mut thread = async_compat::Compat::new(store.thread(id)).await;
+
+    if let (Ok(saved), Some(focus)) = (&thread, focus)
+        && !saved.iter().any(|entry| entry.message.id == focus)
+    {
+        match async_compat::Compat::new(store.switch_branch(focus)).await {
+            Ok(()) => thread = async_compat::Compat::new(store.thread(id)).await,
+            Err(e) => tracing::error!("Failed to switch to branch of message {focus}. Reason: {e}"),
+        }
+    }
+
+    match thread {
+        Ok(saved) => {
+            let shown = saved
+                .iter()
+                .filter(|entry| chat_message(entry).is_some())
+                .collect::<Vec<_>>();
+            let focused = focus
+                .and_then(|focus| shown.iter().position(|entry| entry.message.id == focus))
+                .map(|index| index as i32)
+                .unwrap_or(-1);
+
+            show_active(ui, active, queue, Some(id));
+            ui.set_focused_message(-1);
+            messages.set_vec(
+                shown
+                    .into_iter()
+                    .filter_map(chat_message)
+                    .chain(
+                        queue
+                            .borrow()
+                            .waiting(Chat::Saved(id))
+                            .iter()
+                            .map(queued_message),
+                    )
+                    .collect::<Vec<_>>(),
+            );
+            ui.set_focused_message(focused);
+            show_context_usage(ui, store, Some(id)).await;
+        }
+        Err(e) => tracing::error!("Failed to open conversation {id}. Reason: {e}"),
+    }
+}
+
+fn search_result(hit: &SearchHit) -> SearchResult {
+    let snippet = hit
+        .snippet
+        .iter()
+        .map(|part| SnippetPart {
+            text: part.text.to_shared_string(),
+            matched: part.matched,
+        })
+        .collect::<Vec<_>>();
+
+    SearchResult {
+        conversation_id: hit.conversation_id as i32,
+        message_id: hit.message_id.map(|id| id as i32).unwrap_or(-1),
+        title: hit.conversation_title.to_shared_string(),
+        snippet: ModelRc::from(Rc::new(VecModel::from(snippet))),
+    }
+}
+
+/// Asks where to save conversation and writes it there
+async fn export_conversation(ui: &App, store: &ConversationStore, id: i64, format: ExportFormat) {
+    let conversation = match async_compat::Compat::new(store.conversation(id)).await {
+        Ok(Some(conversation)) => conversation,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Failed to export conversation {id}. Reason: {e}");
+            ui.set_conversation_status(format!("Export failed: {e}").to_shared_string());
+            return;
+        }
+    };
+
+    let dialog = rfd::AsyncFileDialog::new()
+        .set_title("Export conversation")
+        .set_file_name(export::file_name(&conversation, format))
+        .add_filter(format.label(), &[format.extension()]);
+
+    let Some(file) = async_compat::Compat::new(dialog.save_file()).await else {
+        return;
+    };
+
+    match async_compat::Compat::new(store.export(id, format, file.path())).await {
+        Ok(()) => ui.set_conversation_status(
+            format!("Exported to {}", file.path().display()).to_shared_string(),
+        ),
+        Err(e) => {
+            tracing::error!("Failed to export conversation {id}. Reason: {e}");
+            ui.set_conversation_status(format!("Export failed: {e}").to_shared_string());
+        }
+    }
+}
+
+/// Fills settings panel, empty parameters show defaults of model
+fn show_settings(
+    ui: &App,
+    conversation: &str,
+    settings: &ConversationSettings,
+    parameters: &VecModel<ParameterField>,
+) {
+    let defaults = GenerationOptions::for_model(settings.model());
+
+    ui.set_settings_conversation(conversation.to_shared_string());
+    ui.set_settings_model(
+        settings
+            .model
+            .as_deref()
+            .unwrap_or_default()
+            .to_shared_string(),
+    );
+    ui.set_settings_system_prompt(settings.system_prompt.to_shared_string());
+    ui.set_settings_stop(settings.options.stop.join("\n").to_shared_string());
+    ui.set_settings_context_strategy(match settings.context.strategy {
+        ContextStrategy::DropOldest => 0,
+        ContextStrategy::Summarize => 1,
+    });
+    ui.set_settings_context_threshold(settings.context.threshold.to_shared_string());
+    ui.set_settings_status(Default::default());
+    parameters.set_vec(
+        PARAMETERS
+            .iter()
+            .map(|name| {
+                let default = defaults.get(name);
+
+                ParameterField {
+                    name: name.to_shared_string(),
+                    value: settings.options.get(name).to_shared_string(),
+                    placeholder: if default.is_empty() {
+                        "model default".to_shared_string()
+                    } else {
+                        default.to_shared_string()
+                    },
+                }
+            })
+            .collect::<Vec<_>>(),
+    );
+}
+
+/// Settings as entered in settings panel
+fn edited_settings(
+    ui: &App,
+    parameters: &VecModel<ParameterField>,
+) -> Result<ConversationSettings, OptionsError> {
+    let mut options = GenerationOptions {
+        stop: ui
+            .get_settings_stop()
+            .lines()
+            .filter(|stop| !stop.is_empty())
+            .map(ToOwned::to_owned)
+            .collect(),
+        ..GenerationOptions::default()
+    };
+
+    for parameter in parameters.iter() {
+        options.set(&parameter.name, &parameter.value)?;
+    }
+
+    let model = ui.get_settings_model().trim().to_owned();
+
+    let threshold = ui.get_settings_context_threshold();
+    let threshold = match threshold.trim() {
+        "" => ContextSettings::default().threshold,
+        value => value
+            .parse::<u8>()
+            .map_err(|_| OptionsError::NotNumber {
+                name: "context threshold".to_owned(),
+                value: value.to_owned(),
+            })?
+            .clamp(1, 100),
+    };
+
+    Ok(ConversationSettings {
+        model: (!model.is_empty()).then_some(model),
+        system_prompt: ui.get_settings_system_prompt().trim().to_owned(),
+        options,
+        context: ContextSettings {
+            strategy: match ui.get_settings_context_strategy() {
+                1 => ContextStrategy::Summarize,
+                _ => ContextStrategy::DropOldest,
+            },
+            threshold,
+        },
+    })
+}
+
+/// Approximate number of tokens, e.g. "1.2k"
+fn tokens_label(tokens: usize) -> String {
+    if tokens < 1000 {
+        tokens.to_string()
+    } else {
+        format!("{:.1}k", tokens as f32 / 1000.0)
+    }
+}
+
+/// Shows how much of context window next request of conversation takes
+async fn show_context_usage(ui: &App, store: &ConversationStore, conversation_id: Option<i64>) {
+    let Some(conversation_id) = conversation_id else {
+        ui.set_context_usage(Default::default());
+        ui.set_context_fraction(0.0);
+        return;
+    };
+
+    let usage = async {
+        let settings = store.settings(conversation_id).await?;
+        let Some(leaf) = store.messages(conversation_id).await?.pop() else {
+            return Ok(None);
+        };
+        let branch = store.branch(leaf.id).await?;
+
+        Ok::<_, crate::core::conversations::ConversationError>(Some(branch.plan(&settings)))
+    };
+
+    match async_compat::Compat::new(usage).await {
+        Ok(Some(plan)) => {
+            let mut usage = format!(
+                "≈{} of {} context tokens",
+                tokens_label(plan.tokens),
+                tokens_label(plan.limit)
+            );
+            if plan.dropped > 0 {
+                usage.push_str(&format!(", {} older messages left out", plan.dropped));
+            }
+
+            ui.set_context_usage(usage.to_shared_string());
+            ui.set_context_fraction((plan.tokens as f32 / plan.limit.max(1) as f32).min(1.0));
+        }
+        Ok(None) => {
+            ui.set_context_usage(Default::default());
+            ui.set_context_fraction(0.0);
+        }
+        Err(e) => tracing::error!("Failed to estimate context usage. Reason: {e}"),
+    }
+}
+
+/// Messages sent to answer `prompt_id`, older ones are summarized first if strategy asks for it
+async fn context_plan(
+    ui: &App,
+    store: &ConversationStore,
+    settings: &ConversationSettings,
+    model: &str,
+    prompt_id: i64,
+) -> Option<ContextPlan> {
+    let res = async_compat::Compat::new(store.branch(prompt_id)).await;
+    let mut branch = match res {
+        Ok(branch) => branch,
+        Err(e) => {
+            tracing::error!("Failed to read conversation history. Reason: {e}");
+            return None;
+        }
+    };
+
+    let plan = branch.plan(settings);
+    let Some(until) = plan.to_summarize.last() else {
+        return Some(plan);
+    };
+
+    ui.set_conversation_status("Summarizing older messages…".to_shared_string());
+    let res = async_compat::Compat::new(llm_generate(
+        model,
+        context::summary_prompt(branch.memory.as_ref(), &plan.to_summarize),
+        SUMMARY_SYSTEM_PROMPT,
+        context::summary_options(&settings.options),
+    ))
+    .await;
+    ui.set_conversation_status(Default::default());
+
+    // Without summary older messages are left out
+    let summary = match res {
+        Ok(summary) => summary.trim().to_owned(),
+        Err(e) => {
+            tracing::error!("Failed to summarize older messages. Reason: {e}");
+            return Some(plan);
+        }
+    };
+
+    match async_compat::Compat::new(store.add_memory(until.id, summary)).await {
+        Ok(memory) => {
+            branch.memory = Some(memory);
+            Some(branch.plan(settings))
+        }
+        Err(e) => {
+            tracing::error!("Failed to save summary. Reason: {e}");
+            Some(plan)
+        }
+    }
+}
+
+/// Reply to `prompt_id` and messages before it, with settings of conversation and name of model which wrote it, `None` if it failed
+async fn generate_reply(
+    ui: &App,
+    store: &ConversationStore,
+    conversation_id: i64,
+    prompt_id: i64,
+) -> Option<(String, String)> {
+    let settings = async_compat::Compat::new(store.settings(conversation_id))
+        .await
+        .inspect_err(|e| tracing::error!("Failed to read conversation settings. Reason: {e}"))
+        .unwrap_or_default();
+    let model = settings.model().to_owned();
+
+    ui.set_generating(true);
+
+    if let Err(e) = async_compat::Compat::new(llm_load()).await {
+        tracing::error!("Failed to start ollama. Reason: {e}");
+        ui.set_conversation_status(format!("Failed to start ollama: {e}").to_shared_string());
+        ui.set_generating(false);
+        return None;
+    }
+
+    // Model chosen by persona may not be pulled yet
+    if model != MODEL_NAME {
+        ui.set_conversation_status(format!("Preparing {model}…").to_shared_string());
+
+        let res = async_compat::Compat::new(llm_pull_model(&model)).await;
+        ui.set_conversation_status(Default::default());
+
+        if let Err(e) = res {
+            tracing::error!("Failed to pull {model}. Reason: {e}");
+            ui.set_conversation_status(format!("Failed to get {model}: {e}").to_shared_string());
+            ui.set_generating(false);
+            return None;
+        }
+    }
+
+    let Some(mut plan) = context_plan(ui, store, &settings, &model, prompt_id).await else {
+        ui.set_generating(false);
+        return None;
+    };
+
+    // Models without vision would fail on images
+    let has_images = plan.messages.iter().any(|turn| !turn.images.is_empty());
+    if has_images
+        && matches!(
+            async_compat::Compat::new(llm_supports_vision(&model)).await,
+            Ok(false)
+        )
+    {
+        ui.set_conversation_status(
+            format!("{model} can't see images, only text was sent").to_shared_string(),
+        );
+        for turn in &mut plan.messages {
+            turn.images.clear();
+        }
+    }
+
+    let res = async_compat::Compat::new(llm_chat(&model, plan.messages, settings.options)).await;
+    ui.set_generating(false);
+
+    match res {
+        Ok(reply) => Some((reply, model)),
+        Err(e) => {
+            tracing::error!("Failed to generate msg. Reason: {e}");
+            ui.set_conversation_status(format!("Failed to generate reply: {e}").to_shared_string());
+            None
+        }
+    }
+}
+
+/// Shows conversation again after its branches changed, if it is still open
+async fn reload_conversation(
+    ui: &App,
+    store: &ConversationStore,
+    active: &ActiveConversation,
+    messages: &VecModel<ChatMessage>,
+    queue: &SendQueue,
+    id: i64,
+) {
+    if active.get() == Some(id) {
+        open_conversation(ui, store, active, messages, queue, id, None).await;
+    }
+}
+
+/// Saves another reply to prompt of assistant message
+async fn regenerate(
+    ui: &App,
+    store: &ConversationStore,
+    active: &ActiveConversation,
+    messages: &VecModel<ChatMessage>,
+    queue: &SendQueue,
+    message_id: i64,
+) {
+    let original = async_compat::Compat::new(store.message(message_id)).await;
+    let Ok(Some(original)) = original else {
+        tracing::error!("Failed to find message {message_id} to regenerate");
+        return;
+    };
+
+    let Some(prompt_id) = original.parent_id else {
+        tracing::error!("Failed to find prompt of message {message_id}");
+        return;
+    };
+
+    let Some((reply, model)) = generate_reply(ui, store, original.conversation_id, prompt_id).await
+    else {
+        return;
+    };
+
+    let res = async_compat::Compat::new(store.add_sibling(message_id, reply, Some(model))).await;
+
+    match res {
+        Ok(_) => {
+            reload_conversation(ui, store, active, messages, queue, original.conversation_id).await
+        }
+        Err(e) => tracing::error!("Failed to save reply. Reason: {e}"),
+    }
+}
+
+/// Saves edited prompt next to original one and answers it
+async fn edit_and_resend(
+    ui: &App,
+    store: &ConversationStore,
+    active: &ActiveConversation,
+    messages: &VecModel<ChatMessage>,
+    queue: &SendQueue,
+    message_id: i64,
+    text: String,
+) {
+    let res = async_compat::Compat::new(store.add_sibling(message_id, text, None)).await;
+
+    let edited = match res {
+        Ok(edited) => edited,
+        Err(e) => {
+            tracing::error!("Failed to save edited message. Reason: {e}");
+            return;
+        }
+    };
+    let id = edited.conversation_id;
+    reload_conversation(ui, store, active, messages, queue, id).await;
+
+    let Some((reply, model)) = generate_reply(ui, store, id, edited.id).await else {
+        return;
+    };
+
+    let res = async_compat::Compat::new(store.add_reply(id, edited.id, reply, model)).await;
+
+    match res {
+        Ok(_) => reload_conversation(ui, store, active, messages, queue, id).await,
+        Err(e) => tracing::error!("Failed to save reply. Reason: {e}"),
+    }
+}
+
+/// Replaces `initial` title of new conversation with one written by `model`, keeps it if that fails
+async fn name_conversation(
+    store: &ConversationStore,
+    entries: &VecModel<ConversationEntry>,
+    id: i64,
+    initial: String,
+    prompt: &str,
+    reply: &str,
+    model: &str,
+) {
+    let res = async_compat::Compat::new(llm_generate(
+        model,
+        title::title_prompt(prompt, reply),
+        TITLE_SYSTEM_PROMPT,
+        title::title_options(),
+    ))
+    .await;
+
+    let title = match res.map(|answer| title::clean_title(&answer)) {
+        Ok(Some(title)) => title,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Failed to generate title of conversation {id}. Reason: {e}");
+            return;
+        }
+    };
+
+    let res = async_compat::Compat::new(store.set_generated_title(id, initial, title)).await;
+
+    match res {
+        Ok(true) => refresh_conversations(store, entries).await,
+        Ok(false) => (),
+        Err(e) => tracing::error!("Failed to save title of conversation {id}. Reason: {e}"),
+    }
+}
+
+/// Saves prompt taken from queue and answers it, returns reply and model which wrote it
+async fn send_prompt(
+    ui: &App,
+    store: &ConversationStore,
+    entries: &VecModel<ConversationEntry>,
+    active: &ActiveConversation,
+    messages: &VecModel<ChatMessage>,
+    id: i64,
+    queued: &QueuedPrompt,
+) -> Option<(String, String)> {
+    let res =
+        async_compat::Compat::new(store.add_message(id, Role::User, queued.text.clone())).await;
+    refresh_conversations(store, entries).await;
+
+    let prompt = match res {
+        Ok(prompt) => prompt,
+        Err(e) => {
+            tracing::error!("Failed to save message. Reason: {e}");
+            return None;
+        }
+    };
+
+    if !queued.attachments.is_empty() {
+        let res =
+            async_compat::Compat::new(store.add_attachments(prompt.id, queued.attachments.clone()))
+                .await;
+
+        if let Err(e) = res {
+            tracing::error!("Failed to save images. Reason: {e}");
+            ui.set_conversation_status(format!("Failed to save images: {e}").to_shared_string());
+        }
+    }
+
+    // Saved prompt gets its actions
+    if active.get() == Some(id)
+        && let Some(index) = ticket_row(messages, queued.ticket)
+    {
+        let saved = ThreadMessage {
+            siblings: vec![prompt.id],
+            message: prompt.clone(),
+            attachments: queued.attachments.clone(),
+        };
+        if let Some(row) = chat_message(&saved) {
+            messages.set_row_data(index, row);
+        }
+    }
+
+    let (reply, model) = generate_reply(ui, store, id, prompt.id).await?;
+
+    let res =
+        async_compat::Compat::new(store.add_reply(id, prompt.id, reply.clone(), model.clone()))
+            .await;
+    refresh_conversations(store, entries).await;
+
+    let row = match res {
+        Ok(saved) => chat_message(&ThreadMessage {
+            siblings: vec![saved.id],
+            message: saved,
+            attachments: Vec::new(),
+        }),
+        Err(e) => {
+            tracing::error!("Failed to save reply. Reason: {e}");
+            Some(unsaved_message(false, &reply))
+        }
+    };
+
+    // User may have switched to another conversation while reply was generated
+    if active.get() == Some(id)
+        && let Some(row) = row
+    {
+        // Reply goes before prompts still waiting
+        let index = messages
+            .iter()
+            .position(|row| row.queued)
+            .unwrap_or(messages.row_count());
+        messages.insert(index, row);
+        show_context_usage(ui, store, Some(id)).await;
+    }
+
+    Some((reply, model))
+}
+
+/// Sends waiting prompts of `chat` one by one, until none is left or another task answers it
+#[allow(clippy::too_many_arguments)]
+async fn answer_queued(
+    ui: &App,
+    store: &ConversationStore,
+    entries: &Rc<VecModel<ConversationEntry>>,
+    active: &ActiveConversation,
+    messages: &VecModel<ChatMessage>,
+    pending: &PendingSettings,
+    queue: &SendQueue,
+    mut chat: Chat,
+) {
+    loop {
+        let Some(queued) = queue.borrow_mut().start(chat) else {
+            break;
+        };
+
+        // Prompt can't be removed anymore
+        if let Some(index) = ticket_row(messages, queued.ticket)
+            && let Some(mut row) = messages.row_data(index)
+        {
+            row.queued = false;
+            messages.set_row_data(index, row);
+        }
+
+        // Title of conversation created by this prompt, replaced after first reply
+        let (id, initial_title) = match chat {
+            Chat::Saved(id) => (id, None),
+            Chat::Draft(draft) => {
+                let res = async_compat::Compat::new(
+                    store.create_conversation(title_from_prompt(&queued.text)),
+                )
+                .await;
+
+                match res {
+                    Ok(created) => {
+                        // User may have started another new chat meanwhile
+                        let shown = active.get().is_none() && queue.borrow().chat(None) == chat;
+
+                        queue.borrow_mut().created(draft, created.id);
+                        chat = Chat::Saved(created.id);
+
+                        // Settings picked for another new chat stay with it
+                        let settings = match shown {
+                            true => {
+                                show_active(ui, active, queue, Some(created.id));
+                                pending.take()
+                            }
+                            false => ConversationSettings::default(),
+                        };
+                        let _ = async_compat::Compat::new(store.set_settings(created.id, settings))
+                            .await
+                            .inspect_err(|e| {
+                                tracing::error!("Failed to save conversation settings. Reason: {e}")
+                            });
+
+                        (created.id, Some(created.title))
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to create conversation. Reason: {e}");
+                        ui.set_conversation_status(
+                            format!("Failed to create conversation: {e}").to_shared_string(),
+                        );
+
+                        // Prompt was not sent, so it is not shown as sent either
+                        if let Some(index) = ticket_row(messages, queued.ticket) {
+                            messages.remove(index);
+                        }
+
+                        queue.borrow_mut().finish(chat);
+                        continue;
+                    }
+                }
+            }
+        };
+
+        let answer = send_prompt(ui, store, entries, active, messages, id, &queued).await;
+        queue.borrow_mut().finish(chat);
+
+        if let (Some(initial), Some((reply, model))) = (initial_title, answer) {
+            let _ = slint::spawn_local({
+                let store = store.clone();
+                let entries = entries.clone();
+
+                async move {
+                    name_conversation(&store, &entries, id, initial, &queued.text, &reply, &model)
+                        .await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to name conversation. Reason: {e}"));
+        }
+    }
+}
+
+/// Attaches images at `paths` to prompt being written, warns if model of conversation can't see them
+async fn attach_images(
+    ui: &App,
+    store: &ConversationStore,
+    active: &ActiveConversation,
+    pending: &PendingSettings,
+    draft: &DraftAttachments,
+    paths: Vec<PathBuf>,
+) {
+    for path in paths {
+        match async_compat::Compat::new(load_image(&path)).await {
+            Ok(attachment) => draft.borrow_mut().push(attachment),
+            Err(e) => {
+                tracing::error!("Failed to attach {}. Reason: {e}", path.display());
+                ui.set_conversation_status(format!("Can't attach image: {e}").to_shared_string());
+            }
+        }
+    }
+    ui.set_draft_images(draft_images(&draft.borrow()));
+
+    if draft.borrow().is_empty() {
+        return;
+    }
+
+    let model = match active.get() {
+        Some(id) => async_compat::Compat::new(store.settings(id))
+            .await
+            .unwrap_or_default()
+            .model()
+            .to_owned(),
+        None => pending.borrow().model().to_owned(),
+    };
+
+    // Capabilities are known only for pulled models
+    if let Ok(false) = async_compat::Compat::new(llm_supports_vision(&model)).await {
+        ui.set_conversation_status(
+            format!("{model} doesn't support images, they won't be sent").to_shared_string(),
+        );
+    }
+}
+
+/// Re-reads saved conversations into sidebar
+async fn refresh_conversations(store: &ConversationStore, entries: &VecModel<ConversationEntry>) {
+    match async_compat::Compat::new(store.conversations()).await {
+        Ok(conversations) => entries.set_vec(
+            conversations
+                .into_iter()
+                .map(|conversation| ConversationEntry {
+                    id: conversation.id as i32,
+                    title: conversation.title.to_shared_string(),
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Err(e) => tracing::error!("Failed to list conversations. Reason: {e}"),
+    }
+}
+
+pub fn setup(ui: &App) -> Result<(), Box<dyn std::error::Error>> {
+    let store = crate::TOKIO_RUNTIME.block_on(ConversationStore::open_default())?;
+    let active = ActiveConversation::default();
+
+    let messages = Rc::new(VecModel::<ChatMessage>::default());
+    ui.set_messages(ModelRc::from(messages.clone()));
+
+    let entries = Rc::new(VecModel::<ConversationEntry>::default());
+    ui.set_conversations(ModelRc::from(entries.clone()));
+
+    let results = Rc::new(VecModel::<SearchResult>::default());
+    ui.set_search_results(ModelRc::from(results.clone()));
+
+    let library = crate::TOKIO_RUNTIME.block_on(PersonaLibrary::open_default())?;
+    ui.set_default_model(MODEL_NAME.to_shared_string());
+
+    let pending = PendingSettings::default();
+    let queue = SendQueue::default();
+    let draft = DraftAttachments::default();
+    let parameters = Rc::new(VecModel::<ParameterField>::default());
+    ui.set_settings_parameters(ModelRc::from(parameters.clone()));
+
+    ui.on_conversations_changed({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let entries = entries.clone();
+        let active = active.clone();
+        let messages = messages.clone();
+        let queue = queue.clone();
+
+        move || {
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let entries = entries.clone();
+                let active = active.clone();
+                let messages = messages.clone();
+                let queue = queue.clone();
+
+                async move {
+                    // Active conversation may be gone with cleaned data
+                    if let Some(id) = active.get() {
+                        let exists = async_compat::Compat::new(store.conversation(id)).await;
+
+                        if !matches!(exists, Ok(Some(_))) {
+                            show_active(&ui, &active, &queue, None);
+                            messages.set_vec(Vec::new());
+                        }
+                    }
+
+                    refresh_conversations(&store, &entries).await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to list conversations. Reason: {e}"));
+        }
+    });
+    ui.invoke_conversations_changed();
+
+    ui.on_new_conversation_clicked({
+        let ui = ui.clone_strong();
+        let active = active.clone();
+        let messages = messages.clone();
+        let pending = pending.clone();
+        let queue = queue.clone();
+
+        move || {
+            show_active(&ui, &active, &queue, None);
+            messages.set_vec(Vec::new());
+            pending.take();
+            ui.set_selected_persona(0);
+        }
+    });
+
+    ui.on_open_conversation_clicked({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let active = active.clone();
+        let messages = messages.clone();
+        let queue = queue.clone();
+
+        move |id| {
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let active = active.clone();
+                let messages = messages.clone();
+                let queue = queue.clone();
+
+                async move {
+                    open_conversation(&ui, &store, &active, &messages, &queue, i64::from(id), None)
+                        .await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to open conversation. Reason: {e}"));
+        }
+    });
+
+    ui.on_search_edited({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let results = results.clone();
+
+        move |text| {
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let results = results.clone();
+
+                async move {
+                    let res = async_compat::Compat::new(store.search(&text)).await;
+
+                    // Results of outdated input arrive after newer ones
+                    if ui.get_search_text() != text {
+                        return;
+                    }
+
+                    match res {
+                        Ok(hits) => {
+                            results.set_vec(hits.iter().map(search_result).collect::<Vec<_>>())
+                        }
+                        Err(e) => tracing::error!("Failed to search conversations. Reason: {e}"),
+                    }
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to search conversations. Reason: {e}"));
+        }
+    });
+
+    ui.on_search_result_clicked({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let active = active.clone();
+        let messages = messages.clone();
+        let queue = queue.clone();
+
+        move |conversation, message| {
+            let message = (message >= 0).then_some(i64::from(message));
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let active = active.clone();
+                let messages = messages.clone();
+                let queue = queue.clone();
+
+                async move {
+                    open_conversation(
+                        &ui,
+                        &store,
+                        &active,
+                        &messages,
+                        &queue,
+                        i64::from(conversation),
+                        message,
+                    )
+                    .await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to open search result. Reason: {e}"));
+        }
+    });
+
+    ui.on_rename_conversation_clicked({
+        let store = store.clone();
+        let entries = entries.clone();
+
+        move |id, title| {
+            let title = title.trim().to_owned();
+            if title.is_empty() {
+                return;
+            }
+
+            let _ = slint::spawn_local({
+                let store = store.clone();
+                let entries = entries.clone();
+
+                async move {
+                    let res =
+                        async_compat::Compat::new(store.rename_conversation(i64::from(id), title))
+                            .await;
+
+                    if let Err(e) = res {
+                        tracing::error!("Failed to rename conversation {id}. Reason: {e}");
+                    }
+
+                    refresh_conversations(&store, &entries).await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to rename conversation. Reason: {e}"));
+        }
+    });
+
+    ui.on_delete_conversation_clicked({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let entries = entries.clone();
+        let active = active.clone();
+        let messages = messages.clone();
+        let queue = queue.clone();
+
+        move |id| {
+            let id = i64::from(id);
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let entries = entries.clone();
+                let active = active.clone();
+                let messages = messages.clone();
+                let queue = queue.clone();
+
+                async move {
+                    match async_compat::Compat::new(store.delete_conversation(id)).await {
+                        Ok(()) if active.get() == Some(id) => {
+                            show_active(&ui, &active, &queue, None);
+                            messages.set_vec(Vec::new());
+                        }
+                        Ok(()) => (),
+                        Err(e) => {
+                            tracing::error!("Failed to delete conversation {id}. Reason: {e}")
+                        }
+                    }
+
+                    refresh_conversations(&store, &entries).await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to delete conversation. Reason: {e}"));
+        }
+    });
+
+    ui.on_export_conversation_clicked({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+
+        move |id, format| {
+            let Some(format) = usize::try_from(format)
+                .ok()
+                .and_then(|format| ExportFormat::ALL.get(format).copied())
+            else {
+                return;
+            };
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+
+                async move {
+                    export_conversation(&ui, &store, i64::from(id), format).await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start export. Reason: {e}"));
+        }
+    });
+
+    ui.on_branch_clicked({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let active = active.clone();
+        let messages = messages.clone();
+        let queue = queue.clone();
+
+        move |message_id| {
+            let Some(id) = active.get() else {
+                return;
+            };
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let active = active.clone();
+                let messages = messages.clone();
+                let queue = queue.clone();
+
+                async move {
+                    let res =
+                        async_compat::Compat::new(store.switch_branch(i64::from(message_id))).await;
+
+                    match res {
+                        Ok(()) => {
+                            reload_conversation(&ui, &store, &active, &messages, &queue, id).await
+                        }
+                        Err(e) => tracing::error!("Failed to switch branch. Reason: {e}"),
+                    }
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to switch branch. Reason: {e}"));
+        }
+    });
+
+    ui.on_regenerate_clicked({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let entries = entries.clone();
+        let active = active.clone();
+        let messages = messages.clone();
+        let pending = pending.clone();
+        let queue = queue.clone();
+
+        move |message_id| {
+            let Some(id) = active.get() else {
+                return;
+            };
+
+            // Reply is generated in turn with sent prompts, which wait for it in queue
+            let chat = Chat::Saved(id);
+            if !queue.borrow_mut().reserve(chat) {
+                ui.set_conversation_status(
+                    "Wait for current reply before regenerating".to_shared_string(),
+                );
+                return;
+            }
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let entries = entries.clone();
+                let active = active.clone();
+                let messages = messages.clone();
+                let pending = pending.clone();
+                let queue = queue.clone();
+
+                async move {
+                    regenerate(
+                        &ui,
+                        &store,
+                        &active,
+                        &messages,
+                        &queue,
+                        i64::from(message_id),
+                    )
+                    .await;
+                    refresh_conversations(&store, &entries).await;
+
+                    queue.borrow_mut().finish(chat);
+                    answer_queued(
+                        &ui, &store, &entries, &active, &messages, &pending, &queue, chat,
+                    )
+                    .await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to regenerate reply. Reason: {e}"));
+        }
+    });
+
+    ui.on_edit_resend_clicked({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let entries = entries.clone();
+        let active = active.clone();
+        let messages = messages.clone();
+        let pending = pending.clone();
+        let queue = queue.clone();
+
+        move |message_id, text| {
+            if text.trim().is_empty() {
+                return;
+            }
+
+            let Some(id) = active.get() else {
+                return;
+            };
+
+            let chat = Chat::Saved(id);
+            if !queue.borrow_mut().reserve(chat) {
+                ui.set_conversation_status(
+                    "Wait for current reply before editing".to_shared_string(),
+                );
+                return;
+            }
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let entries = entries.clone();
+                let active = active.clone();
+                let messages = messages.clone();
+                let pending = pending.clone();
+                let queue = queue.clone();
+
+                async move {
+                    edit_and_resend(
+                        &ui,
+                        &store,
+                        &active,
+                        &messages,
+                        &queue,
+                        i64::from(message_id),
+                        text.to_string(),
+                    )
+                    .await;
+                    refresh_conversations(&store, &entries).await;
+
+                    queue.borrow_mut().finish(chat);
+                    answer_queued(
+                        &ui, &store, &entries, &active, &messages, &pending, &queue, chat,
+                    )
+                    .await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to resend message. Reason: {e}"));
+        }
+    });
+
+    ui.on_fork_clicked({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let entries = entries.clone();
+        let active = active.clone();
+        let messages = messages.clone();
+        let queue = queue.clone();
+
+        move |message_id| {
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let entries = entries.clone();
+                let active = active.clone();
+                let messages = messages.clone();
+                let queue = queue.clone();
+
+                async move {
+                    match async_compat::Compat::new(store.fork(i64::from(message_id))).await {
+                        Ok(fork) => {
+                            refresh_conversations(&store, &entries).await;
+                            open_conversation(
+                                &ui, &store, &active, &messages, &queue, fork.id, None,
+                            )
+                            .await;
+                        }
+                        Err(e) => tracing::error!("Failed to fork conversation. Reason: {e}"),
+                    }
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to fork conversation. Reason: {e}"));
+        }
+    });
+
+    ui.on_conversation_settings_clicked({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let active = active.clone();
+        let pending = pending.clone();
+        let parameters = parameters.clone();
+
+        move || {
+            let Some(id) = active.get() else {
+                show_settings(&ui, "", &pending.borrow(), &parameters);
+                return;
+            };
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let parameters = parameters.clone();
+
+                async move {
+                    let conversation = async_compat::Compat::new(store.conversation(id)).await;
+                    let settings = async_compat::Compat::new(store.settings(id)).await;
+
+                    match (conversation, settings) {
+                        (Ok(Some(conversation)), Ok(settings)) => {
+                            show_settings(&ui, &conversation.title, &settings, &parameters)
+                        }
+                        (Err(e), _) | (_, Err(e)) => {
+                            tracing::error!("Failed to read conversation settings. Reason: {e}");
+                            ui.set_settings_status(
+                                format!("Failed to read settings: {e}").to_shared_string(),
+                            );
+                        }
+                        (Ok(None), _) => ui.set_show_settings_panel(false),
+                    }
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to read conversation settings. Reason: {e}"));
+        }
+    });
+
+    ui.on_settings_parameter_edited({
+        let parameters = parameters.clone();
+
+        move |index, value| {
+            let index = index as usize;
+
+            if let Some(mut parameter) = parameters.row_data(index) {
+                parameter.value = value;
+                parameters.set_row_data(index, parameter);
+            }
+        }
+    });
+
+    ui.on_settings_reset({
+        let ui = ui.clone_strong();
+        let parameters = parameters.clone();
+
+        move || {
+            let conversation = ui.get_settings_conversation();
+            show_settings(
+                &ui,
+                &conversation,
+                &ConversationSettings {
+                    model: None,
+                    system_prompt: ui.get_settings_system_prompt().to_string(),
+                    options: GenerationOptions::default(),
+                    context: ContextSettings::default(),
+                },
+                &parameters,
+            );
+        }
+    });
+
+    ui.on_settings_saved({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let active = active.clone();
+        let pending = pending.clone();
+        let parameters = parameters.clone();
+
+        move || {
+            let settings = match edited_settings(&ui, &parameters) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    ui.set_settings_status(e.to_shared_string());
+                    return;
+                }
+            };
+
+            let Some(id) = active.get() else {
+                *pending.borrow_mut() = settings;
+                ui.set_settings_status("Saved, used from first message".to_shared_string());
+                return;
+            };
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let active = active.clone();
+
+                async move {
+                    match async_compat::Compat::new(store.set_settings(id, settings)).await {
+                        Ok(()) => {
+                            ui.set_settings_status("Saved".to_shared_string());
+                            if active.get() == Some(id) {
+                                show_context_usage(&ui, &store, Some(id)).await;
+                            }
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to save conversation settings. Reason: {e}");
+                            ui.set_settings_status(
+                                format!("Failed to save settings: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to save conversation settings. Reason: {e}"));
+        }
+    });
+
+    ui.on_persona_selected({
+        let ui = ui.clone_strong();
+        let pending = pending.clone();
+        let library = library.clone();
+
+        move |index| {
+            let Ok(index) = usize::try_from(index) else {
+                pending.take();
+                return;
+            };
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let pending = pending.clone();
+                let library = library.clone();
+
+                async move {
+                    match async_compat::Compat::new(library.personas()).await {
+                        Ok(personas) => match personas.get(index) {
+                            Some(persona) => *pending.borrow_mut() = persona.settings(),
+                            None => ui.invoke_personas_changed(),
+                        },
+                        Err(e) => tracing::error!("Failed to read personas. Reason: {e}"),
+                    }
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to choose persona. Reason: {e}"));
+        }
+    });
+
+    ui.on_save_as_persona({
+        let ui = ui.clone_strong();
+        let parameters = parameters.clone();
+
+        move |name| {
+            let settings = match edited_settings(&ui, &parameters) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    ui.set_settings_status(e.to_shared_string());
+                    return;
+                }
+            };
+            let persona = Persona::from_settings(name.trim().to_owned(), &settings);
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let library = library.clone();
+
+                async move {
+                    let name = persona.name.clone();
+
+                    match async_compat::Compat::new(library.save(vec![persona])).await {
+                        Ok(_) => {
+                            ui.set_settings_status(
+                                format!("Saved persona {name}").to_shared_string(),
+                            );
+                            ui.invoke_personas_changed();
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to save persona. Reason: {e}");
+                            ui.set_settings_status(
+                                format!("Failed to save persona: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to save persona. Reason: {e}"));
+        }
+    });
+
+    ui.on_remove_queued_clicked({
+        let queue = queue.clone();
+        let messages = messages.clone();
+
+        move |ticket| {
+            let Ok(ticket) = u64::try_from(ticket) else {
+                return;
+            };
+
+            if queue.borrow_mut().remove(ticket)
+                && let Some(index) = ticket_row(&messages, ticket)
+            {
+                messages.remove(index);
+            }
+        }
+    });
+
+    ui.on_attach_clicked({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let active = active.clone();
+        let pending = pending.clone();
+        let draft = draft.clone();
+
+        move || {
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let active = active.clone();
+                let pending = pending.clone();
+                let draft = draft.clone();
+
+                async move {
+                    let paths = pick_images().await;
+                    attach_images(&ui, &store, &active, &pending, &draft, paths).await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to attach images. Reason: {e}"));
+        }
+    });
+
+    ui.on_image_dropped({
+        let ui = ui.clone_strong();
+        let store = store.clone();
+        let active = active.clone();
+        let pending = pending.clone();
+        let draft = draft.clone();
+
+        move |path| {
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let active = active.clone();
+                let pending = pending.clone();
+                let draft = draft.clone();
+
+                async move {
+                    let paths = vec![PathBuf::from(path.as_str())];
+                    attach_images(&ui, &store, &active, &pending, &draft, paths).await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to attach dropped image. Reason: {e}"));
+        }
+    });
+
+    ui.on_draft_image_removed({
+        let ui = ui.clone_strong();
+        let draft = draft.clone();
+
+        move |index| {
+            let Ok(index) = usize::try_from(index) else {
+                return;
+            };
+
+            if index < draft.borrow().len() {
+                draft.borrow_mut().remove(index);
+                ui.set_draft_images(draft_images(&draft.borrow()));
+            }
+        }
+    });
+
+    ui.on_send_clicked({
+        let ui = ui.clone_strong();
+
+        move |text| {
+            let chat = queue.borrow().chat(active.get());
+            let attachments = draft.take();
+            ui.set_draft_images(ModelRc::default());
+
+            let prompt = QueuedPrompt {
+                ticket: queue
+                    .borrow_mut()
+                    .push(chat, text.to_string(), attachments.clone()),
+                conversation: chat,
+                text: text.to_string(),
+                attachments,
+            };
+            messages.push(queued_message(&prompt));
+
+            // Task answering earlier prompt of conversation takes this one as well
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+                let entries = entries.clone();
+                let active = active.clone();
+                let messages = messages.clone();
+                let pending = pending.clone();
+                let queue = queue.clone();
+
+                async move {
+                    answer_queued(
+                        &ui, &store, &entries, &active, &messages, &pending, &queue, chat,
+                    )
+                    .await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to generate msg. Reason: {e}"));
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use std::{cell::RefCell, rc::Rc};
+
+use slint::{ComponentHandle, ModelRc, ToSharedString, VecModel};
+
+use crate::{
+    App, ImportCandidate,
+    core::conversations::{
+        ConversationStore,
+        import::{self, ConversationImport, ImportPreview},
+    },
+};
+
+fn candidates(preview: &ImportPreview) -> Vec<ImportCandidate> {
+    let importable = preview.conversations.iter().map(|conversation| {
+        let mut detail = format!("{} messages", conversation.messages.len());
+        if conversation.skipped_messages > 0 {
+            detail.push_str(&format!(
+                ", {} unsupported left out",
+                conversation.skipped_messages
+            ));
+        }
+
+        ImportCandidate {
+            title: conversation.title.to_shared_string(),
+            detail: detail.to_shared_string(),
+            importable: true,
+        }
+    });
+
+    let skipped = preview
+        .skipped
+        .iter()
+        .map(|(title, reason)| ImportCandidate {
+            title: title.to_shared_string(),
+            detail: format!("Skipped: {reason}").to_shared_string(),
+            importable: false,
+        });
+
+    importable.chain(skipped).collect()
+}
+
+fn describe_report(report: &ConversationImport) -> String {
+    let mut text = format!(
+        "Imported {} conversations, skipped {}",
+        report.imported.len(),
+        report.skipped.len()
+    );
+
+    for (title, reason) in &report.skipped {
+        text.push_str(&format!("\n{title}: {reason}"));
+    }
+
+    text
+}
+
+/// Asks for file and shows conversations found in it
+async fn pick_file(ui: &App, preview: &RefCell<Option<ImportPreview>>) {
+    let dialog = rfd::AsyncFileDialog::new()
+        .set_title("Import conversations")
+        .add_filter("JSON", &["json"]);
+
+    let Some(file) = async_compat::Compat::new(dialog.pick_file()).await else {
+        return;
+    };
+
+    preview.borrow_mut().take();
+    ui.set_import_source(file.path().display().to_shared_string());
+    ui.set_import_report(Default::default());
+    ui.set_import_candidates(ModelRc::default());
+    ui.set_can_import(false);
+    ui.set_show_import_panel(true);
+
+    let content = match async_compat::Compat::new(tokio::fs::read(file.path())).await {
+        Ok(content) => content,
+        Err(e) => {
+            tracing::error!("Failed to read {}. Reason: {e}", file.path().display());
+            ui.set_import_report(format!("Failed to read file: {e}").to_shared_string());
+            return;
+        }
+    };
+
+    let parsed =
+        async_compat::Compat::new(tokio::task::spawn_blocking(move || import::parse(&content)))
+            .await;
+
+    match parsed {
+        Ok(Ok(parsed)) => {
+            ui.set_import_candidates(ModelRc::from(Rc::new(VecModel::from(candidates(&parsed)))));
+            ui.set_can_import(!parsed.conversations.is_empty());
+            *preview.borrow_mut() = Some(parsed);
+        }
+        Ok(Err(e)) => {
+            tracing::warn!("Failed to parse {}. Reason: {e}", file.path().display());
+            ui.set_import_report(e.to_shared_string());
+        }
+        Err(e) => tracing::error!("Failed to parse {}. Reason: {e}", file.path().display()),
+    }
+}
+
+pub fn setup(ui: &App) -> Result<(), Box<dyn std::error::Error>> {
+    let store = crate::TOKIO_RUNTIME.block_on(ConversationStore::open_default())?;
+    // Preview waiting for confirmation
+    let preview = Rc::new(RefCell::new(None::<ImportPreview>));
+
+    ui.on_import_conversations_clicked({
+        let ui = ui.clone_strong();
+        let preview = preview.clone();
+
+        move || {
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let preview = preview.clone();
+
+                async move {
+                    pick_file(&ui, &preview).await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start import. Reason: {e}"));
+        }
+    });
+
+    ui.on_import_confirmed({
+        let ui = ui.clone_strong();
+
+        move || {
+            let Some(pending) = preview.borrow_mut().take() else {
+                return;
+            };
+
+            ui.set_import_busy(true);
+            ui.set_can_import(false);
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let store = store.clone();
+
+                async move {
+                    match async_compat::Compat::new(store.import(pending)).await {
+                        Ok(report) => {
+                            ui.set_import_report(describe_report(&report).to_shared_string());
+                            ui.invoke_conversations_changed();
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to import conversations. Reason: {e}");
+                            ui.set_import_report(format!("Import failed: {e}").to_shared_string());
+                        }
+                    }
+
+                    ui.set_import_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start import. Reason: {e}"));
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use std::{cell::RefCell, rc::Rc};
+
+use slint::{ComponentHandle, ToSharedString};
+
+use crate::{
+    App,
+    core::llm::{
+        llm_remove_orphans, llm_repull_models, llm_verify_models,
+        store::{StoreProgress, StoreReport},
+    },
+};
+
+fn describe_report(report: &StoreReport) -> String {
+    if report.is_healthy() {
+        return format!("All {} models are intact", report.checked_models);
+    }
+
+    let mut text = format!(
+        "Checked {} models: {} missing, {} corrupt and {} orphaned blobs",
+        report.checked_models,
+        report.missing.len(),
+        report.corrupt.len(),
+        report.orphaned.len()
+    );
+
+    let broken = report.broken_models();
+    if !broken.is_empty() {
+        text.push_str(&format!(". Broken models: {}", broken.join(", ")));
+    }
+
+    text
+}
+
+fn show_report(ui: &App, report: &StoreReport) {
+    ui.set_integrity_report(describe_report(report).to_shared_string());
+    ui.set_has_broken_models(!report.broken_models().is_empty());
+    ui.set_has_orphans(!report.orphaned.is_empty());
+}
+
+pub fn setup(ui: &App) {
+    // Re-pull needs to know which blobs were found corrupt
+    let last_report = Rc::new(RefCell::new(None::<StoreReport>));
+
+    ui.on_verify_models_clicked({
+        let ui = ui.clone_strong();
+        let last_report = last_report.clone();
+
+        move || {
+            ui.set_storage_busy(true);
+            ui.set_migration_progress(0.0);
+
+            let progress = {
+                let ui = ui.as_weak();
+
+                move |progress: StoreProgress| {
+                    let _ = ui.upgrade_in_event_loop(move |ui| {
+                        ui.set_migration_progress(progress.fraction());
+                    });
+                }
+            };
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let last_report = last_report.clone();
+
+                async move {
+                    ui.set_integrity_report("Verifying models…".into());
+
+                    match async_compat::Compat::new(llm_verify_models(progress)).await {
+                        Ok(report) => {
+                            show_report(&ui, &report);
+                            *last_report.borrow_mut() = Some(report);
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to verify models. Reason: {e}");
+                            ui.set_integrity_report(
+                                format!("Failed to verify models: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+
+                    ui.set_migration_progress(-1.0);
+                    ui.set_storage_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start verification. Reason: {e}"));
+        }
+    });
+
+    ui.on_repull_models_clicked({
+        let ui = ui.clone_strong();
+        let last_report = last_report.clone();
+
+        move || {
+            let Some(report) = last_report.borrow_mut().take() else {
+                return;
+            };
+            let models = report.broken_models();
+
+            ui.set_storage_busy(true);
+            ui.set_has_broken_models(false);
+            ui.set_integrity_report(format!("Pulling {}…", models.join(", ")).to_shared_string());
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+
+                async move {
+                    match async_compat::Compat::new(llm_repull_models(report, models)).await {
+                        Ok(()) => ui.set_integrity_report(
+                            "Broken models are pulled again. Verify to check them".into(),
+                        ),
+                        Err(e) => {
+                            tracing::error!("Failed to pull models again. Reason: {e}");
+                            ui.set_integrity_report(
+                                format!("Failed to pull models: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+
+                    ui.set_storage_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start model pull. Reason: {e}"));
+        }
+    });
+
+    ui.on_remove_orphans_clicked({
+        let ui = ui.clone_strong();
+
+        move || {
+            ui.set_storage_busy(true);
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+
+                async move {
+                    match async_compat::Compat::new(llm_remove_orphans()).await {
+                        Ok(freed) => {
+                            ui.set_has_orphans(false);
+                            ui.set_integrity_report(
+                                format!(
+                                    "Removed orphaned blobs, freed {:.1} MB",
+                                    freed as f64 / 1024.0 / 1024.0
+                                )
+                                .to_shared_string(),
+                            );
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to remove orphaned blobs. Reason: {e}");
+                            ui.set_integrity_report(
+                                format!("Failed to remove orphans: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+
+                    ui.set_storage_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start orphan removal. Reason: {e}"));
+        }
+    });
+}
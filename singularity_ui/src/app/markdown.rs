@@ -0,0 +1,182 @@
+use std::{cell::RefCell, rc::Rc};
+
+use slint::{Color, ComponentHandle, ModelRc, SharedString, ToSharedString, VecModel};
+
+use crate::{
+    App, BlockKind, CodeLine, CodeSpan, ListEntry, MessageBlock, TableRow,
+    core::markdown::{
+        self, Block,
+        highlight::{self, Span},
+    },
+};
+
+fn model<T: Clone + 'static>(items: Vec<T>) -> ModelRc<T> {
+    ModelRc::from(Rc::new(VecModel::from(items)))
+}
+
+fn table_row(cells: &[String]) -> TableRow {
+    TableRow {
+        cells: model(cells.iter().map(ToSharedString::to_shared_string).collect()),
+    }
+}
+
+fn code_line(spans: Vec<Span>) -> CodeLine {
+    CodeLine {
+        spans: model(
+            spans
+                .into_iter()
+                .map(
+                    |Span {
+                         text,
+                         color: [r, g, b],
+                     }| CodeSpan {
+                        text: text.to_shared_string(),
+                        color: Color::from_rgb_u8(r, g, b),
+                    },
+                )
+                .collect(),
+        ),
+    }
+}
+
+fn message_block(block: &Block, highlighted: bool) -> MessageBlock {
+    let empty = |kind| MessageBlock {
+        kind,
+        text: SharedString::new(),
+        level: 0,
+        language: SharedString::new(),
+        lines: ModelRc::default(),
+        items: ModelRc::default(),
+        rows: ModelRc::default(),
+    };
+
+    match block {
+        Block::Paragraph(text) => MessageBlock {
+            text: text.to_shared_string(),
+            ..empty(BlockKind::Paragraph)
+        },
+        Block::Heading { level, text } => MessageBlock {
+            text: text.to_shared_string(),
+            level: i32::from(*level),
+            ..empty(BlockKind::Heading)
+        },
+        Block::List(items) => MessageBlock {
+            items: model(
+                items
+                    .iter()
+                    .map(|item| ListEntry {
+                        marker: item.marker.to_shared_string(),
+                        text: item.text.to_shared_string(),
+                        depth: item.depth as i32,
+                    })
+                    .collect(),
+            ),
+            ..empty(BlockKind::List)
+        },
+        Block::Code { language, code } => MessageBlock {
+            text: code.to_shared_string(),
+            language: language.as_deref().unwrap_or_default().to_shared_string(),
+            lines: model(
+                highlight::highlight(code, language.as_deref().filter(|_| highlighted))
+                    .into_iter()
+                    .map(code_line)
+                    .collect(),
+            ),
+            ..empty(BlockKind::Code)
+        },
+        Block::Quote(text) => MessageBlock {
+            text: text.to_shared_string(),
+            ..empty(BlockKind::Quote)
+        },
+        Block::Table { header, rows } => MessageBlock {
+            rows: model(
+                std::iter::once(header)
+                    .chain(rows)
+                    .map(|row| table_row(row))
+                    .collect(),
+            ),
+            ..empty(BlockKind::Table)
+        },
+        Block::Rule => empty(BlockKind::Rule),
+    }
+}
+
+/// Blocks of message Markdown, code is highlighted if `highlighted` is set
+pub fn message_blocks(text: &str, highlighted: bool) -> ModelRc<MessageBlock> {
+    model(
+        markdown::parse(text)
+            .iter()
+            .map(|block| message_block(block, highlighted))
+            .collect(),
+    )
+}
+
+async fn save_code(ui: &App, code: String, language: Option<String>) {
+    let dialog = rfd::AsyncFileDialog::new()
+        .set_title("Save code")
+        .set_file_name(highlight::file_name(language.as_deref()));
+
+    let Some(file) = async_compat::Compat::new(dialog.save_file()).await else {
+        return;
+    };
+
+    let status = match async_compat::Compat::new(tokio::fs::write(file.path(), code)).await {
+        Ok(()) => format!("Saved {}", file.path().display()),
+        Err(e) => {
+            tracing::error!(
+                "Failed to save code to {}. Reason: {e}",
+                file.path().display()
+            );
+            format!("Failed to save code: {e}")
+        }
+    };
+
+    ui.set_conversation_status(status.to_shared_string());
+}
+
+pub fn setup(ui: &App) {
+    // Kept open, on X11 copied text is gone once clipboard is dropped
+    let clipboard = Rc::new(RefCell::new(None::<arboard::Clipboard>));
+
+    ui.on_copy_code_clicked({
+        let ui = ui.as_weak();
+
+        move |code| {
+            let mut clipboard = clipboard.borrow_mut();
+            let copied = match clipboard.as_mut() {
+                Some(clipboard) => clipboard.set_text(code.as_str()),
+                None => arboard::Clipboard::new()
+                    .and_then(|opened| clipboard.insert(opened).set_text(code.as_str())),
+            };
+
+            let status = match copied {
+                Ok(()) => "Code copied to clipboard".to_owned(),
+                Err(e) => {
+                    tracing::error!("Failed to copy code. Reason: {e}");
+                    format!("Failed to copy code: {e}")
+                }
+            };
+
+            if let Some(ui) = ui.upgrade() {
+                ui.set_conversation_status(status.to_shared_string());
+            }
+        }
+    });
+
+    ui.on_save_code_clicked({
+        let ui = ui.clone_strong();
+
+        move |code, language| {
+            let language = Some(language.to_string()).filter(|language| !language.is_empty());
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+
+                async move {
+                    save_code(&ui, code.to_string(), language).await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to save code. Reason: {e}"));
+        }
+    });
+}
@@ -0,0 +1,10 @@
+//! Wiring of UI callbacks to core functionality, grouped by UI area
+
+pub mod attachments;
+pub mod chat;
+pub mod import;
+pub mod integrity;
+pub mod markdown;
+pub mod personas;
+pub mod runtime;
+pub mod storage;
@@ -0,0 +1,262 @@
+use std::rc::Rc;
+
+use slint::{ComponentHandle, Model, ModelRc, SharedString, ToSharedString, VecModel};
+
+use crate::{
+    App, PersonaEntry,
+    core::{
+        conversations::title_from_prompt,
+        llm::MODEL_NAME,
+        personas::{self, Persona, PersonaLibrary},
+    },
+};
+
+fn persona_entry(persona: &Persona) -> PersonaEntry {
+    let model = if persona.model.is_empty() {
+        MODEL_NAME
+    } else {
+        &persona.model
+    };
+
+    let prompt = match persona.system_prompt.trim() {
+        "" => "no system prompt".to_owned(),
+        prompt => title_from_prompt(prompt),
+    };
+
+    PersonaEntry {
+        name: persona.name.to_shared_string(),
+        detail: format!("{model} · {prompt}").to_shared_string(),
+    }
+}
+
+/// Re-reads library into panel and persona picker
+async fn refresh_personas(
+    ui: &App,
+    library: &PersonaLibrary,
+    entries: &VecModel<PersonaEntry>,
+    names: &VecModel<SharedString>,
+) {
+    match async_compat::Compat::new(library.personas()).await {
+        Ok(personas) => {
+            entries.set_vec(personas.iter().map(persona_entry).collect::<Vec<_>>());
+            names.set_vec(
+                std::iter::once("No persona".to_shared_string())
+                    .chain(
+                        personas
+                            .iter()
+                            .map(|persona| persona.name.to_shared_string()),
+                    )
+                    .collect::<Vec<_>>(),
+            );
+
+            if ui.get_selected_persona() > personas.len() as i32 {
+                ui.set_selected_persona(0);
+                ui.invoke_persona_selected(-1);
+            }
+        }
+        Err(e) => {
+            tracing::error!("Failed to read personas. Reason: {e}");
+            ui.set_personas_status(format!("Failed to read personas: {e}").to_shared_string());
+        }
+    }
+}
+
+/// Asks where to save `personas` and writes them there
+async fn export_personas(ui: &App, personas: Vec<Persona>) {
+    let file_name = match personas.as_slice() {
+        [persona] => personas::file_name(persona),
+        _ => "personas.json".to_owned(),
+    };
+
+    let dialog = rfd::AsyncFileDialog::new()
+        .set_title("Export personas")
+        .set_file_name(file_name)
+        .add_filter("JSON", &["json"]);
+
+    let Some(file) = async_compat::Compat::new(dialog.save_file()).await else {
+        return;
+    };
+
+    let content = personas::to_file(&personas);
+
+    let status = match async_compat::Compat::new(tokio::fs::write(file.path(), content)).await {
+        Ok(()) => format!("Exported to {}", file.path().display()),
+        Err(e) => {
+            tracing::error!("Failed to export personas. Reason: {e}");
+            format!("Export failed: {e}")
+        }
+    };
+
+    ui.set_personas_status(status.to_shared_string());
+}
+
+/// Asks for shared file and adds its personas to library
+async fn import_personas(ui: &App, library: &PersonaLibrary) {
+    let dialog = rfd::AsyncFileDialog::new()
+        .set_title("Import personas")
+        .add_filter("JSON", &["json"]);
+
+    let Some(file) = async_compat::Compat::new(dialog.pick_file()).await else {
+        return;
+    };
+
+    let content = match async_compat::Compat::new(tokio::fs::read(file.path())).await {
+        Ok(content) => content,
+        Err(e) => {
+            tracing::error!("Failed to read {}. Reason: {e}", file.path().display());
+            ui.set_personas_status(format!("Failed to read file: {e}").to_shared_string());
+            return;
+        }
+    };
+
+    let imported = match personas::from_file(&content) {
+        Ok(imported) => imported,
+        Err(e) => {
+            tracing::warn!("Failed to parse {}. Reason: {e}", file.path().display());
+            ui.set_personas_status(e.to_shared_string());
+            return;
+        }
+    };
+    let count = imported.len();
+
+    match async_compat::Compat::new(library.save(imported)).await {
+        Ok(_) => {
+            ui.set_personas_status(format!("Imported {count} personas").to_shared_string());
+            ui.invoke_personas_changed();
+        }
+        Err(e) => {
+            tracing::error!("Failed to import personas. Reason: {e}");
+            ui.set_personas_status(format!("Import failed: {e}").to_shared_string());
+        }
+    }
+}
+
+pub fn setup(ui: &App) -> Result<(), Box<dyn std::error::Error>> {
+    let library = crate::TOKIO_RUNTIME.block_on(PersonaLibrary::open_default())?;
+
+    let entries = Rc::new(VecModel::<PersonaEntry>::default());
+    ui.set_personas(ModelRc::from(entries.clone()));
+
+    let names = Rc::new(VecModel::<SharedString>::default());
+    ui.set_persona_names(ModelRc::from(names.clone()));
+
+    ui.on_personas_changed({
+        let ui = ui.clone_strong();
+        let library = library.clone();
+
+        move || {
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let library = library.clone();
+                let entries = entries.clone();
+                let names = names.clone();
+
+                async move {
+                    refresh_personas(&ui, &library, &entries, &names).await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to read personas. Reason: {e}"));
+        }
+    });
+    ui.invoke_personas_changed();
+
+    ui.on_persona_deleted({
+        let ui = ui.clone_strong();
+        let library = library.clone();
+
+        move |index| {
+            let Some(name) = ui
+                .get_personas()
+                .row_data(index as usize)
+                .map(|entry| entry.name.to_string())
+            else {
+                return;
+            };
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let library = library.clone();
+
+                async move {
+                    match async_compat::Compat::new(library.remove(name)).await {
+                        Ok(_) => {
+                            // Picker indices shift, so choice is cleared
+                            ui.set_selected_persona(0);
+                            ui.invoke_persona_selected(-1);
+                            ui.invoke_personas_changed();
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to delete persona. Reason: {e}");
+                            ui.set_personas_status(
+                                format!("Failed to delete persona: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to delete persona. Reason: {e}"));
+        }
+    });
+
+    ui.on_persona_exported({
+        let ui = ui.clone_strong();
+        let library = library.clone();
+
+        move |index| {
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let library = library.clone();
+
+                async move {
+                    match async_compat::Compat::new(library.personas()).await {
+                        Ok(personas) => {
+                            if let Some(persona) = personas.into_iter().nth(index as usize) {
+                                export_personas(&ui, vec![persona]).await;
+                            }
+                        }
+                        Err(e) => tracing::error!("Failed to read personas. Reason: {e}"),
+                    }
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to export persona. Reason: {e}"));
+        }
+    });
+
+    ui.on_personas_exported_all({
+        let ui = ui.clone_strong();
+        let library = library.clone();
+
+        move || {
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let library = library.clone();
+
+                async move {
+                    match async_compat::Compat::new(library.personas()).await {
+                        Ok(personas) => export_personas(&ui, personas).await,
+                        Err(e) => tracing::error!("Failed to read personas. Reason: {e}"),
+                    }
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to export personas. Reason: {e}"));
+        }
+    });
+
+    ui.on_personas_import_clicked({
+        let ui = ui.clone_strong();
+
+        move || {
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let library = library.clone();
+
+                async move {
+                    import_personas(&ui, &library).await;
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to import personas. Reason: {e}"));
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use slint::{ComponentHandle, ToSharedString};
+
+use crate::{
+    App,
+    core::{
+        self,
+        llm::{
+            download::{DownloadOptions, DownloadProgress, DownloadStage},
+            llm_can_rollback, llm_check_update, llm_pin_version, llm_rollback,
+            llm_set_manifest_url, llm_upgrade, llm_use_system, system_ollama,
+        },
+        settings::Settings,
+    },
+};
+
+/// Updates information about installed runtime shown in UI
+pub async fn refresh_runtime_info(ui: &App) {
+    let version = async_compat::Compat::new(async {
+        core::llm::version(&core::settings::load().await?).await
+    })
+    .await;
+
+    match version {
+        Ok(version) => ui.set_runtime_version(version.unwrap_or_default().to_shared_string()),
+        Err(e) => tracing::error!("Failed to check ollama version. Reason: {e}"),
+    }
+
+    match async_compat::Compat::new(llm_can_rollback()).await {
+        Ok(can_rollback) => ui.set_can_rollback(can_rollback),
+        Err(e) => tracing::error!("Failed to check previous ollama install. Reason: {e}"),
+    }
+}
+
+pub fn describe_progress(progress: DownloadProgress) -> String {
+    let stage = match progress.stage {
+        DownloadStage::Download => "Downloading",
+        DownloadStage::Unpack => "Unpacking",
+    };
+
+    format!(
+        "{stage} {} - {:.1} / {:.1} MB",
+        progress.archive,
+        progress.done as f64 / 1024.0 / 1024.0,
+        progress.total as f64 / 1024.0 / 1024.0
+    )
+}
+
+/// Download options which forward progress to UI thread
+pub fn download_options(
+    ui: &App,
+    keep_archive: bool,
+    show: fn(&App, DownloadProgress),
+) -> DownloadOptions {
+    let ui = ui.as_weak();
+
+    DownloadOptions {
+        keep_archive,
+        progress: Arc::new(move |progress| {
+            let _ = ui.upgrade_in_event_loop(move |ui| show(&ui, progress));
+        }),
+    }
+}
+
+/// Shows runtime related settings in UI
+pub fn apply_settings(ui: &App, settings: &Settings) {
+    ui.set_use_system_ollama(settings.runtime.use_system);
+    ui.set_manifest_url(settings.update.manifest_url.to_shared_string());
+    ui.set_pinned_version(
+        settings
+            .update
+            .pinned_version
+            .clone()
+            .unwrap_or_default()
+            .to_shared_string(),
+    );
+}
+
+pub fn setup(ui: &App, settings: &Settings) -> Result<(), Box<dyn std::error::Error>> {
+    let system = crate::TOKIO_RUNTIME.block_on(system_ollama());
+
+    ui.set_system_ollama(
+        system
+            .map(|system| format!("{} ({})", system.version, system.location.display()))
+            .unwrap_or_default()
+            .to_shared_string(),
+    );
+    apply_settings(ui, settings);
+
+    ui.on_rollback_clicked({
+        let ui = ui.clone_strong();
+
+        move || {
+            ui.set_runtime_busy(true);
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+
+                async move {
+                    let _ = async_compat::Compat::new(llm_rollback())
+                        .await
+                        .inspect_err(|e| tracing::error!("Failed to rollback ollama. Reason: {e}"));
+
+                    refresh_runtime_info(&ui).await;
+
+                    ui.set_runtime_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start rollback. Reason: {e}"));
+        }
+    });
+
+    ui.on_use_system_toggled({
+        let ui = ui.clone_strong();
+
+        move |use_system| {
+            ui.set_runtime_busy(true);
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+
+                async move {
+                    let _ = async_compat::Compat::new(llm_use_system(use_system))
+                        .await
+                        .inspect_err(|e| tracing::error!("Failed to switch ollama. Reason: {e}"));
+
+                    refresh_runtime_info(&ui).await;
+
+                    ui.set_runtime_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to switch ollama. Reason: {e}"));
+        }
+    });
+
+    ui.on_check_updates_clicked({
+        let ui = ui.clone_strong();
+
+        move || {
+            ui.set_runtime_busy(true);
+            ui.set_update_status("Checking for updates…".into());
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+
+                async move {
+                    match async_compat::Compat::new(llm_check_update()).await {
+                        Ok(Some(release)) => {
+                            ui.set_update_status(
+                                format!("Version {} is available", release.version_string())
+                                    .to_shared_string(),
+                            );
+                            ui.set_update_version(release.version_string().to_shared_string());
+                            ui.set_update_notes(release.notes().to_shared_string());
+                        }
+                        Ok(None) => {
+                            ui.set_update_status("Runtime is up to date".into());
+                            ui.set_update_version(Default::default());
+                            ui.set_update_notes(Default::default());
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to check for updates. Reason: {e}");
+                            ui.set_update_status(
+                                format!("Failed to check for updates: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+
+                    ui.set_runtime_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start update check. Reason: {e}"));
+        }
+    });
+
+    ui.on_upgrade_clicked({
+        let ui = ui.clone_strong();
+
+        move |version| {
+            ui.set_runtime_busy(true);
+
+            let options = download_options(&ui, ui.get_keep_archives(), |ui, progress| {
+                ui.set_update_status(describe_progress(progress).to_shared_string())
+            });
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+
+                async move {
+                    match async_compat::Compat::new(llm_upgrade(version.to_string(), options)).await
+                    {
+                        Ok(()) => {
+                            ui.set_update_status(
+                                format!("Installed version {version}").to_shared_string(),
+                            );
+                            ui.set_update_version(Default::default());
+                            ui.set_update_notes(Default::default());
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to upgrade ollama. Reason: {e}");
+                            ui.set_update_status(
+                                format!("Failed to upgrade: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+
+                    refresh_runtime_info(&ui).await;
+
+                    ui.set_runtime_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start upgrade. Reason: {e}"));
+        }
+    });
+
+    ui.on_pin_clicked({
+        let ui = ui.clone_strong();
+
+        move |version| {
+            let version = version.trim().to_owned();
+            let version = (!version.is_empty()).then_some(version);
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+
+                async move {
+                    let status = match version.as_deref() {
+                        Some(version) => format!("Runtime is pinned to {version}"),
+                        None => "Runtime follows latest release".to_owned(),
+                    };
+
+                    match async_compat::Compat::new(llm_pin_version(version)).await {
+                        Ok(()) => ui.set_update_status(status.to_shared_string()),
+                        Err(e) => tracing::error!("Failed to pin version. Reason: {e}"),
+                    }
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to pin version. Reason: {e}"));
+        }
+    });
+
+    ui.on_manifest_url_edited(move |url| {
+        let _ = slint::spawn_local(async move {
+            let _ = async_compat::Compat::new(llm_set_manifest_url(url.trim().to_owned()))
+                .await
+                .inspect_err(|e| tracing::error!("Failed to save manifest url. Reason: {e}"));
+        })
+        .inspect_err(|e| tracing::error!("Failed to save manifest url. Reason: {e}"));
+    });
+
+    Ok(())
+}
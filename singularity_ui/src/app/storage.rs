@@ -0,0 +1,427 @@
+use std::{path::PathBuf, rc::Rc};
+
+use slint::{ComponentHandle, Model, ModelRc, ToSharedString, VecModel};
+
+use crate::{
+    App, ImportableModel, StorageEntry,
+    app::runtime::{apply_settings, refresh_runtime_info},
+    core::{
+        self,
+        llm::{
+            llm_existing_store, llm_import_models, llm_migrate_models, llm_share_store, models_dir,
+            store::StoreProgress,
+        },
+        settings::Settings,
+        storage::{self, StorageCategory},
+    },
+};
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Re-reads disk usage and shows it in storage panel
+async fn refresh_usage(ui: &App, entries: &VecModel<StorageEntry>) {
+    match async_compat::Compat::new(storage::usage()).await {
+        Ok(usage) => {
+            let total: u64 = usage.iter().map(|usage| usage.bytes).sum();
+
+            entries.set_vec(
+                usage
+                    .into_iter()
+                    .map(|usage| StorageEntry {
+                        label: usage.category.label().into(),
+                        size: format_size(usage.bytes).to_shared_string(),
+                        location: usage
+                            .locations
+                            .iter()
+                            .map(|location| location.display().to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                            .to_shared_string(),
+                        shared: usage.shared,
+                    })
+                    .collect::<Vec<_>>(),
+            );
+
+            ui.set_storage_status(format!("Total: {}", format_size(total)).to_shared_string());
+        }
+        Err(e) => {
+            tracing::error!("Failed to measure storage. Reason: {e}");
+            ui.set_storage_status(format!("Failed to measure storage: {e}").to_shared_string());
+        }
+    }
+}
+
+/// Looks for models of ollama used before the app
+async fn refresh_existing_store(ui: &App, models: &VecModel<ImportableModel>) {
+    match async_compat::Compat::new(llm_existing_store()).await {
+        Ok(Some(store)) => {
+            ui.set_existing_store(store.location.display().to_shared_string());
+            models.set_vec(
+                store
+                    .models
+                    .iter()
+                    .map(|model| ImportableModel {
+                        name: model.name.to_shared_string(),
+                        size: format_size(model.manifest.size()).to_shared_string(),
+                        selected: false,
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+        Ok(None) => {
+            ui.set_existing_store(Default::default());
+            models.set_vec(Vec::new());
+        }
+        Err(e) => tracing::error!("Failed to look for existing model store. Reason: {e}"),
+    }
+}
+
+/// Runtime or model is gone, so download dialog is shown again
+async fn require_reinstall(ui: &App) {
+    ui.set_show_storage_panel(false);
+    ui.set_show_runtime_panel(false);
+    ui.set_finished_loading(false);
+    ui.set_show_download_warning(false);
+
+    refresh_runtime_info(ui).await;
+}
+
+pub fn setup(ui: &App, settings: &Settings) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = Rc::new(VecModel::<StorageEntry>::default());
+    ui.set_storage_entries(ModelRc::from(entries.clone()));
+
+    let existing_models = Rc::new(VecModel::<ImportableModel>::default());
+    ui.set_existing_models(ModelRc::from(existing_models.clone()));
+
+    ui.set_model_store(
+        settings
+            .models
+            .store
+            .as_ref()
+            .map(|store| store.display().to_string())
+            .unwrap_or_default()
+            .to_shared_string(),
+    );
+
+    ui.on_storage_refresh_clicked({
+        let ui = ui.clone_strong();
+        let entries = entries.clone();
+        let existing_models = existing_models.clone();
+
+        move || {
+            ui.set_storage_busy(true);
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let entries = entries.clone();
+                let existing_models = existing_models.clone();
+
+                async move {
+                    refresh_usage(&ui, &entries).await;
+                    refresh_existing_store(&ui, &existing_models).await;
+
+                    ui.set_storage_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to measure storage. Reason: {e}"));
+        }
+    });
+
+    ui.on_storage_clean_clicked({
+        let ui = ui.clone_strong();
+        let entries = entries.clone();
+
+        move |index| {
+            let Some(category) = usize::try_from(index)
+                .ok()
+                .and_then(|index| StorageCategory::ALL.get(index).copied())
+            else {
+                return;
+            };
+
+            ui.set_storage_busy(true);
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let entries = entries.clone();
+
+                async move {
+                    match async_compat::Compat::new(storage::clean(category)).await {
+                        Ok(()) => {
+                            refresh_usage(&ui, &entries).await;
+
+                            if category == StorageCategory::Conversations {
+                                ui.invoke_conversations_changed();
+                            }
+
+                            if category.needs_reinstall() {
+                                require_reinstall(&ui).await;
+                            }
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to clean {}. Reason: {e}", category.label());
+                            ui.set_storage_status(
+                                format!("Failed to clean {}: {e}", category.label())
+                                    .to_shared_string(),
+                            );
+                        }
+                    }
+
+                    ui.set_storage_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start cleanup. Reason: {e}"));
+        }
+    });
+
+    ui.on_storage_reset_clicked({
+        let ui = ui.clone_strong();
+        let entries = entries.clone();
+
+        move || {
+            ui.set_storage_busy(true);
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let entries = entries.clone();
+
+                async move {
+                    match async_compat::Compat::new(storage::reset_app_data()).await {
+                        Ok(()) => {
+                            ui.invoke_conversations_changed();
+
+                            match async_compat::Compat::new(core::settings::load()).await {
+                                Ok(settings) => {
+                                    apply_settings(&ui, &settings);
+                                    ui.set_model_store(Default::default());
+                                }
+                                Err(e) => tracing::error!("Failed to load settings. Reason: {e}"),
+                            }
+
+                            refresh_usage(&ui, &entries).await;
+                            require_reinstall(&ui).await;
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to reset app data. Reason: {e}");
+                            ui.set_storage_status(
+                                format!("Failed to reset app data: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+
+                    ui.set_storage_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start reset. Reason: {e}"));
+        }
+    });
+
+    ui.on_move_models_clicked({
+        let ui = ui.clone_strong();
+        let entries = entries.clone();
+        let existing_models = existing_models.clone();
+
+        move |location| {
+            let location = location.trim().to_owned();
+            let target = (!location.is_empty()).then(|| PathBuf::from(location));
+
+            ui.set_storage_busy(true);
+            ui.set_migration_progress(0.0);
+
+            let progress = {
+                let ui = ui.as_weak();
+
+                move |progress: StoreProgress| {
+                    let _ = ui.upgrade_in_event_loop(move |ui| {
+                        ui.set_migration_progress(progress.fraction());
+                        ui.set_storage_status(
+                            format!(
+                                "Moving models - {} / {}",
+                                format_size(progress.done / 2),
+                                format_size(progress.total / 2)
+                            )
+                            .to_shared_string(),
+                        );
+                    });
+                }
+            };
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let entries = entries.clone();
+                let existing_models = existing_models.clone();
+
+                async move {
+                    match async_compat::Compat::new(llm_migrate_models(target, progress)).await {
+                        Ok(()) => {
+                            refresh_usage(&ui, &entries).await;
+                            refresh_existing_store(&ui, &existing_models).await;
+
+                            if let Ok(store) = async_compat::Compat::new(models_dir()).await {
+                                ui.set_storage_status(
+                                    format!("Model store is {}", store.display())
+                                        .to_shared_string(),
+                                );
+                            }
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to move models. Reason: {e}");
+                            ui.set_storage_status(
+                                format!("Failed to move models: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+
+                    ui.set_migration_progress(-1.0);
+                    ui.set_storage_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start model migration. Reason: {e}"));
+        }
+    });
+
+    ui.on_existing_model_toggled({
+        let existing_models = existing_models.clone();
+
+        move |index, selected| {
+            let Ok(index) = usize::try_from(index) else {
+                return;
+            };
+
+            if let Some(mut model) = existing_models.row_data(index) {
+                model.selected = selected;
+                existing_models.set_row_data(index, model);
+            }
+        }
+    });
+
+    ui.on_share_store_clicked({
+        let ui = ui.clone_strong();
+        let entries = entries.clone();
+        let existing_models = existing_models.clone();
+
+        move || {
+            let location = PathBuf::from(ui.get_existing_store().as_str());
+
+            ui.set_storage_busy(true);
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let entries = entries.clone();
+                let existing_models = existing_models.clone();
+
+                async move {
+                    match async_compat::Compat::new(llm_share_store(location.clone())).await {
+                        Ok(()) => {
+                            ui.set_model_store(location.display().to_shared_string());
+                            refresh_usage(&ui, &entries).await;
+                            refresh_existing_store(&ui, &existing_models).await;
+                            ui.set_storage_status(
+                                format!("Model store is {}", location.display()).to_shared_string(),
+                            );
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to share model store. Reason: {e}");
+                            ui.set_storage_status(
+                                format!("Failed to use model store: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+
+                    ui.set_storage_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to share model store. Reason: {e}"));
+        }
+    });
+
+    ui.on_import_models_clicked({
+        let ui = ui.clone_strong();
+        let entries = entries.clone();
+        let existing_models = existing_models.clone();
+
+        move || {
+            let source = PathBuf::from(ui.get_existing_store().as_str());
+            let names = existing_models
+                .iter()
+                .filter(|model| model.selected)
+                .map(|model| model.name.to_string())
+                .collect::<Vec<_>>();
+
+            if names.is_empty() {
+                ui.set_storage_status("Select models to import".into());
+
+                return;
+            }
+
+            ui.set_storage_busy(true);
+            ui.set_migration_progress(0.0);
+
+            let progress = {
+                let ui = ui.as_weak();
+
+                move |progress: StoreProgress| {
+                    let _ = ui.upgrade_in_event_loop(move |ui| {
+                        ui.set_migration_progress(progress.fraction());
+                        ui.set_storage_status(
+                            format!(
+                                "Importing models - {} / {}",
+                                format_size(progress.done),
+                                format_size(progress.total)
+                            )
+                            .to_shared_string(),
+                        );
+                    });
+                }
+            };
+
+            let _ = slint::spawn_local({
+                let ui = ui.clone_strong();
+                let entries = entries.clone();
+                let existing_models = existing_models.clone();
+
+                async move {
+                    match async_compat::Compat::new(llm_import_models(source, names, progress))
+                        .await
+                    {
+                        Ok(report) => {
+                            refresh_usage(&ui, &entries).await;
+                            refresh_existing_store(&ui, &existing_models).await;
+                            ui.set_storage_status(
+                                format!(
+                                    "Imported {} models: {} files copied, {} reused",
+                                    report.models, report.copied_blobs, report.reused_blobs
+                                )
+                                .to_shared_string(),
+                            );
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to import models. Reason: {e}");
+                            ui.set_storage_status(
+                                format!("Failed to import models: {e}").to_shared_string(),
+                            );
+                        }
+                    }
+
+                    ui.set_migration_progress(-1.0);
+                    ui.set_storage_busy(false);
+                }
+            })
+            .inspect_err(|e| tracing::error!("Failed to start model import. Reason: {e}"));
+        }
+    });
+
+    Ok(())
+}
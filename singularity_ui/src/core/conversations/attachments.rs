@@ -0,0 +1,242 @@
+//! Images attached to prompts for models which can see
+
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use rusqlite::{Connection, params_from_iter};
+
+use super::{ConversationError, ConversationStore};
+use crate::error::BetterIoError;
+
+/// Extensions of images which can be attached
+pub const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "webp", "gif"];
+/// Larger images are rejected, models scale them down anyway
+const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+/// Longest side of thumbnail shown in chat, in pixels
+const THUMBNAIL_SIZE: u32 = 160;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AttachmentError {
+    #[error(transparent)]
+    Io(#[from] BetterIoError),
+    #[error("{0} is not a PNG, JPEG, WebP or GIF image")]
+    Unsupported(PathBuf),
+    #[error("{} is larger than {} MB", .0.display(), MAX_IMAGE_BYTES / 1024 / 1024)]
+    TooLarge(PathBuf),
+    #[error("Malformed image. Reason: {0}")]
+    Decode(#[from] image::ImageError),
+    #[error("Failed to wait for image to be read. Reason: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub name: String,
+    /// Content of image file
+    pub data: Vec<u8>,
+    /// PNG shown in chat
+    pub thumbnail: Vec<u8>,
+}
+
+/// Reads image file and makes its thumbnail
+pub async fn load_image(path: &Path) -> Result<Attachment, AttachmentError> {
+    let supported = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|known| extension.eq_ignore_ascii_case(known))
+        });
+    if !supported {
+        return Err(AttachmentError::Unsupported(path.to_owned()));
+    }
+
+    let metadata = tokio::fs::metadata(path)
+        .await
+        .map_err(|e| BetterIoError::new(path, "reading image metadata", e))?;
+    if metadata.len() > MAX_IMAGE_BYTES {
+        return Err(AttachmentError::TooLarge(path.to_owned()));
+    }
+
+    let data = tokio::fs::read(path)
+        .await
+        .map_err(|e| BetterIoError::new(path, "reading image", e))?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    tokio::task::spawn_blocking(move || {
+        let thumbnail = thumbnail(&data)?;
+
+        Ok(Attachment {
+            name,
+            data,
+            thumbnail,
+        })
+    })
+    .await?
+}
+
+/// PNG of image scaled down to fit thumbnail
+fn thumbnail(data: &[u8]) -> Result<Vec<u8>, image::ImageError> {
+    let image = image::load_from_memory(data)?.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+
+    Ok(png)
+}
+
+impl Attachment {
+    /// Attachment restored from image file content, e.g. from exported conversation
+    pub(super) fn from_data(name: String, data: Vec<u8>) -> Result<Self, image::ImageError> {
+        Ok(Self {
+            thumbnail: thumbnail(&data)?,
+            name,
+            data,
+        })
+    }
+}
+
+impl ConversationStore {
+    /// Attaches images to saved message
+    pub async fn add_attachments(
+        &self,
+        message_id: i64,
+        attachments: Vec<Attachment>,
+    ) -> Result<(), ConversationError> {
+        self.call(move |db| {
+            let transaction = db.transaction()?;
+
+            for attachment in attachments {
+                transaction.execute(
+                    "INSERT INTO attachments (message_id, name, data, thumbnail) VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![
+                        message_id,
+                        attachment.name,
+                        attachment.data,
+                        attachment.thumbnail
+                    ],
+                )?;
+            }
+            transaction.commit()?;
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Images of messages by message id, messages without images are left out
+pub(super) fn attachments_of(
+    db: &Connection,
+    message_ids: &[i64],
+) -> Result<HashMap<i64, Vec<Attachment>>, ConversationError> {
+    let mut attachments = HashMap::<i64, Vec<Attachment>>::new();
+    if message_ids.is_empty() {
+        return Ok(attachments);
+    }
+
+    let placeholders = vec!["?"; message_ids.len()].join(", ");
+    let mut statement = db.prepare(&format!(
+        "SELECT message_id, name, data, thumbnail FROM attachments
+        WHERE message_id IN ({placeholders}) ORDER BY id"
+    ))?;
+    let rows = statement.query_map(params_from_iter(message_ids), |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            Attachment {
+                name: row.get(1)?,
+                data: row.get(2)?,
+                thumbnail: row.get(3)?,
+            },
+        ))
+    })?;
+
+    for row in rows {
+        let (message_id, attachment) = row?;
+        attachments.entry(message_id).or_default().push(attachment);
+    }
+
+    Ok(attachments)
+}
+
+/// Gives images of `from` to `to`, e.g. to edited copy of prompt
+pub(super) fn copy_attachments(db: &Connection, from: i64, to: i64) -> rusqlite::Result<()> {
+    db.execute(
+        "INSERT INTO attachments (message_id, name, data, thumbnail)
+        SELECT ?2, name, data, thumbnail FROM attachments WHERE message_id = ?1 ORDER BY id",
+        [from, to],
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::conversations::{Role, tests::scratch_store};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Vec::new();
+        image::RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[tokio::test]
+    async fn loads_image_with_thumbnail() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = dir.path().join("photo.PNG");
+        tokio::fs::write(&path, png(640, 320)).await.unwrap();
+        let attachment = load_image(&path).await.unwrap();
+
+        assert_eq!(attachment.name, "photo.PNG");
+        let thumbnail = image::load_from_memory(&attachment.thumbnail).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (160, 80));
+
+        let text = dir.path().join("notes.txt");
+        tokio::fs::write(&text, "hello").await.unwrap();
+        assert!(matches!(
+            load_image(&text).await,
+            Err(AttachmentError::Unsupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn keeps_images_of_prompt_and_its_edit() {
+        let (_dir, store) = scratch_store();
+        let conversation = store.create_conversation("Chat".to_owned()).await.unwrap();
+        let prompt = store
+            .add_message(conversation.id, Role::User, "What is this?".to_owned())
+            .await
+            .unwrap();
+
+        let attachment = Attachment {
+            name: "dot.png".to_owned(),
+            data: png(1, 1),
+            thumbnail: png(1, 1),
+        };
+        store
+            .add_attachments(prompt.id, vec![attachment.clone()])
+            .await
+            .unwrap();
+
+        let edited = store
+            .add_sibling(prompt.id, "And this?".to_owned(), None)
+            .await
+            .unwrap();
+
+        let branch = store.branch(prompt.id).await.unwrap();
+        assert_eq!(branch.attachments[&prompt.id], vec![attachment.clone()]);
+        let branch = store.branch(edited.id).await.unwrap();
+        assert_eq!(branch.attachments[&edited.id], vec![attachment]);
+    }
+}
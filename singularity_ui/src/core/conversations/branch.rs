@@ -0,0 +1,316 @@
+//! Alternatives of messages, made by regenerating replies or editing prompts
+
+use rusqlite::{OptionalExtension, params};
+
+use super::{
+    Conversation, ConversationError, ConversationStore, Message, Role, active_path,
+    attachments::{Attachment, attachments_of, copy_attachments},
+    find_message, insert_message, now, path_to,
+};
+
+/// Message on active branch with its alternatives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadMessage {
+    pub message: Message,
+    /// Ids of messages sharing parent with this one, including it, oldest first
+    pub siblings: Vec<i64>,
+    /// Images attached to message
+    pub attachments: Vec<Attachment>,
+}
+
+impl ThreadMessage {
+    /// Index of message among its siblings
+    pub fn position(&self) -> usize {
+        self.siblings
+            .iter()
+            .position(|id| *id == self.message.id)
+            .unwrap_or_default()
+    }
+
+    pub fn previous(&self) -> Option<i64> {
+        let position = self.position();
+        (position > 0).then(|| self.siblings[position - 1])
+    }
+
+    pub fn next(&self) -> Option<i64> {
+        self.siblings.get(self.position() + 1).copied()
+    }
+}
+
+impl ConversationStore {
+    /// Messages on active branch of conversation with alternatives of each of them
+    pub async fn thread(
+        &self,
+        conversation_id: i64,
+    ) -> Result<Vec<ThreadMessage>, ConversationError> {
+        self.call(move |db| {
+            let mut statement = db.prepare(
+                "SELECT id FROM messages WHERE conversation_id = ?1 AND parent_id IS ?2 ORDER BY id",
+            )?;
+
+            let path = active_path(db, conversation_id)?;
+            let mut attachments = attachments_of(
+                db,
+                &path.iter().map(|message| message.id).collect::<Vec<_>>(),
+            )?;
+
+            path.into_iter()
+                .map(|message| {
+                    let siblings = statement
+                        .query_map(params![conversation_id, message.parent_id], |row| {
+                            row.get(0)
+                        })?
+                        .collect::<Result<Vec<i64>, _>>()?;
+
+                    Ok(ThreadMessage {
+                        attachments: attachments.remove(&message.id).unwrap_or_default(),
+                        message,
+                        siblings,
+                    })
+                })
+                .collect()
+        })
+        .await
+    }
+
+    /// Saves alternative of message, e.g. edited prompt or regenerated reply. It becomes active branch
+    pub async fn add_sibling(
+        &self,
+        message_id: i64,
+        content: String,
+        model: Option<String>,
+    ) -> Result<Message, ConversationError> {
+        self.call(move |db| {
+            let transaction = db.transaction()?;
+
+            let original = find_message(&transaction, message_id)?
+                .ok_or(ConversationError::MessageNotFound(message_id))?;
+            let message = insert_message(
+                &transaction,
+                original.conversation_id,
+                original.parent_id,
+                original.role,
+                content,
+                model,
+            )?;
+            // Edited prompt keeps its images
+            if original.role == Role::User {
+                copy_attachments(&transaction, original.id, message.id)?;
+            }
+            transaction.commit()?;
+
+            Ok(message)
+        })
+        .await
+    }
+
+    /// Makes branch with message active, below it latest alternatives are followed
+    pub async fn switch_branch(&self, message_id: i64) -> Result<(), ConversationError> {
+        self.call(move |db| {
+            let transaction = db.transaction()?;
+
+            let mut leaf = message_id;
+            while let Some(child) = transaction.query_row(
+                "SELECT MAX(id) FROM messages WHERE parent_id = ?1",
+                [leaf],
+                |row| row.get::<_, Option<i64>>(0),
+            )? {
+                leaf = child;
+            }
+
+            transaction.execute(
+                "UPDATE conversations SET active_leaf = ?1
+                WHERE id = (SELECT conversation_id FROM messages WHERE id = ?2)",
+                params![leaf, message_id],
+            )?;
+            transaction.commit()?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Copies messages up to `message_id` into new conversation
+    pub async fn fork(&self, message_id: i64) -> Result<Conversation, ConversationError> {
+        self.call(move |db| {
+            let transaction = db.transaction()?;
+            let now = now();
+
+            let path = path_to(&transaction, message_id)?;
+            let source = path
+                .first()
+                .ok_or(ConversationError::MessageNotFound(message_id))?
+                .conversation_id;
+
+            let title = transaction
+                .query_row(
+                    "SELECT title FROM conversations WHERE id = ?1",
+                    [source],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()?
+                .ok_or(ConversationError::NotFound(source))?;
+            let title = format!("{title} (fork)");
+
+            // Fork keeps settings of source
+            transaction.execute(
+                "INSERT INTO conversations
+                (title, created_at, updated_at, model, system_prompt, options, context)
+                SELECT ?1, ?2, ?2, model, system_prompt, options, context
+                FROM conversations WHERE id = ?3",
+                params![title, now, source],
+            )?;
+            let id = transaction.last_insert_rowid();
+
+            let mut parent_id = None;
+            for message in path {
+                transaction.execute(
+                    "INSERT INTO messages (conversation_id, role, content, created_at, model, parent_id)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        id,
+                        message.role.as_str(),
+                        message.content,
+                        message.created_at,
+                        message.model,
+                        parent_id
+                    ],
+                )?;
+                let copy = transaction.last_insert_rowid();
+                copy_attachments(&transaction, message.id, copy)?;
+                parent_id = Some(copy);
+            }
+
+            transaction.execute(
+                "UPDATE conversations SET active_leaf = ?2 WHERE id = ?1",
+                params![id, parent_id],
+            )?;
+            transaction.commit()?;
+
+            Ok(Conversation {
+                id,
+                title,
+                created_at: now,
+                updated_at: now,
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::conversations::{
+        ConversationSettings, Role,
+        context::{ContextSettings, ContextStrategy},
+        tests::scratch_store,
+    };
+
+    fn contents(messages: &[super::Message]) -> Vec<&str> {
+        messages
+            .iter()
+            .map(|message| message.content.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn switches_between_alternatives() {
+        let (_dir, store) = scratch_store();
+        let conversation = store.create_conversation("Chat".to_owned()).await.unwrap();
+
+        let prompt = store
+            .add_message(conversation.id, Role::User, "Hi".to_owned())
+            .await
+            .unwrap();
+        let first = store
+            .add_reply(
+                conversation.id,
+                prompt.id,
+                "A".to_owned(),
+                "model".to_owned(),
+            )
+            .await
+            .unwrap();
+        let second = store
+            .add_sibling(first.id, "B".to_owned(), Some("model".to_owned()))
+            .await
+            .unwrap();
+
+        let thread = store.thread(conversation.id).await.unwrap();
+        assert_eq!(thread[1].message.content, "B");
+        assert_eq!(thread[1].siblings, [first.id, second.id]);
+        assert_eq!(thread[1].previous(), Some(first.id));
+        assert_eq!(thread[1].next(), None);
+
+        store.switch_branch(first.id).await.unwrap();
+        assert_eq!(
+            contents(&store.messages(conversation.id).await.unwrap()),
+            ["Hi", "A"]
+        );
+
+        // Edited prompt starts branch without replies
+        store
+            .add_sibling(prompt.id, "Hello".to_owned(), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            contents(&store.messages(conversation.id).await.unwrap()),
+            ["Hello"]
+        );
+
+        store.switch_branch(prompt.id).await.unwrap();
+        assert_eq!(
+            contents(&store.messages(conversation.id).await.unwrap()),
+            ["Hi", "B"]
+        );
+    }
+
+    #[tokio::test]
+    async fn fork_copies_branch() {
+        let (_dir, store) = scratch_store();
+        let conversation = store.create_conversation("Chat".to_owned()).await.unwrap();
+
+        let prompt = store
+            .add_message(conversation.id, Role::User, "Hi".to_owned())
+            .await
+            .unwrap();
+        let reply = store
+            .add_reply(
+                conversation.id,
+                prompt.id,
+                "A".to_owned(),
+                "model".to_owned(),
+            )
+            .await
+            .unwrap();
+        store
+            .add_message(conversation.id, Role::User, "More".to_owned())
+            .await
+            .unwrap();
+
+        let settings = ConversationSettings {
+            model: Some("llama3.2".to_owned()),
+            system_prompt: "Answer briefly".to_owned(),
+            context: ContextSettings {
+                strategy: ContextStrategy::Summarize,
+                threshold: 60,
+            },
+            ..ConversationSettings::default()
+        };
+        store
+            .set_settings(conversation.id, settings.clone())
+            .await
+            .unwrap();
+
+        let fork = store.fork(reply.id).await.unwrap();
+        assert_eq!(fork.title, "Chat (fork)");
+        assert_eq!(store.settings(fork.id).await.unwrap(), settings);
+
+        let messages = store.messages(fork.id).await.unwrap();
+        assert_eq!(contents(&messages), ["Hi", "A"]);
+        assert_eq!(messages[1].model.as_deref(), Some("model"));
+        assert_eq!(messages[1].parent_id, Some(messages[0].id));
+
+        assert_eq!(store.messages(conversation.id).await.unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,480 @@
+//! History sent with request, kept within context window of model
+
+use rusqlite::{OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use super::{
+    ConversationError, ConversationSettings, ConversationStore, Message, Role,
+    attachments::{Attachment, attachments_of},
+    now, path_to,
+};
+use crate::core::llm::{ChatTurn, options::GenerationOptions};
+
+/// Context length assumed when neither conversation nor model sets one
+const DEFAULT_CONTEXT: u64 = 2048;
+/// Tokens taken by role and separators of every message
+const MESSAGE_OVERHEAD: usize = 4;
+/// Tokens taken by attached image, vision encoders use a fixed amount
+const IMAGE_TOKENS: usize = 256;
+
+/// Instruction for model which condenses older messages
+pub const SUMMARY_SYSTEM_PROMPT: &str = "Summarize the conversation below into a compact memory \
+for its later continuation. Keep facts, decisions, names, code identifiers and open questions. \
+Answer with the summary only.";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Oldest turns are left out of request
+    #[default]
+    DropOldest,
+    /// Oldest turns are replaced by summary written by model
+    Summarize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextSettings {
+    pub strategy: ContextStrategy,
+    /// Percent of context window history may fill before strategy applies
+    pub threshold: u8,
+}
+
+impl Default for ContextSettings {
+    fn default() -> Self {
+        Self {
+            strategy: ContextStrategy::default(),
+            threshold: 80,
+        }
+    }
+}
+
+/// Summary of messages up to `until_id`, sent instead of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Memory {
+    pub until_id: i64,
+    pub content: String,
+}
+
+/// Rough token count, about four characters per token for English text and code
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4) + MESSAGE_OVERHEAD
+}
+
+fn turn_tokens(turn: &ChatTurn) -> usize {
+    estimate_tokens(&turn.content) + turn.images.len() * IMAGE_TOKENS
+}
+
+/// Messages of request and how much of context they take
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextPlan {
+    /// Messages sent to model, system prompt and memory first
+    pub messages: Vec<ChatTurn>,
+    /// Estimated tokens of sent messages
+    pub tokens: usize,
+    /// Context length of model
+    pub limit: usize,
+    /// Messages left out of request, memory doesn't count
+    pub dropped: usize,
+    /// Older messages to be summarized into memory, set by [`ContextStrategy::Summarize`]
+    pub to_summarize: Vec<Message>,
+}
+
+/// Chooses history sent with request which ends with last message of `path`, with images of its messages
+pub fn plan(
+    path: &[Message],
+    memory: Option<&Memory>,
+    attachments: &HashMap<i64, Vec<Attachment>>,
+    settings: &ConversationSettings,
+) -> ContextPlan {
+    let limit = settings
+        .options
+        .clone()
+        .or(GenerationOptions::for_model(settings.model()))
+        .num_ctx
+        .unwrap_or(DEFAULT_CONTEXT) as usize;
+
+    // Memory applies to branch it was written on
+    let start = memory.and_then(|memory| {
+        path.iter()
+            .position(|message| message.id == memory.until_id)
+            .map(|index| index + 1)
+    });
+    let memory = memory.filter(|_| start.is_some());
+    let history = &path[start.unwrap_or_default()..];
+
+    let mut fixed = Vec::new();
+    if !settings.system_prompt.is_empty() {
+        fixed.push(ChatTurn::new(Role::System, settings.system_prompt.clone()));
+    }
+    if let Some(memory) = memory {
+        fixed.push(ChatTurn::new(
+            Role::System,
+            format!("Summary of earlier conversation:\n{}", memory.content),
+        ));
+    }
+
+    let history = history
+        .iter()
+        .map(|message| ChatTurn {
+            role: message.role,
+            content: message.content.clone(),
+            images: attachments
+                .get(&message.id)
+                .map(|images| images.iter().map(|image| image.data.clone()).collect())
+                .unwrap_or_default(),
+        })
+        .zip(history)
+        .collect::<Vec<_>>();
+
+    let fixed_tokens = fixed.iter().map(turn_tokens).sum::<usize>();
+    let tokens = history
+        .iter()
+        .map(|(turn, _)| turn_tokens(turn))
+        .collect::<Vec<_>>();
+
+    let budget = limit * usize::from(settings.context.threshold.min(100)) / 100;
+
+    let mut split = 0;
+    if fixed_tokens + tokens.iter().sum::<usize>() > budget {
+        // Summaries are costly, so they free half of budget at once
+        let target = match settings.context.strategy {
+            ContextStrategy::DropOldest => budget,
+            ContextStrategy::Summarize => budget / 2,
+        };
+
+        let mut kept = fixed_tokens;
+        split = history.len();
+        while split > 0 && kept + tokens[split - 1] <= target {
+            kept += tokens[split - 1];
+            split -= 1;
+        }
+
+        // Latest message is always sent, and history starts with whole turn
+        split = split.min(history.len().saturating_sub(1));
+        while split + 1 < history.len() && history[split].1.role != Role::User {
+            split += 1;
+        }
+    }
+
+    let mut history = history.into_iter();
+    let older = history
+        .by_ref()
+        .take(split)
+        .map(|(_, message)| message.clone())
+        .collect::<Vec<_>>();
+
+    let mut messages = fixed;
+    messages.extend(history.map(|(turn, _)| turn));
+
+    ContextPlan {
+        tokens: messages.iter().map(turn_tokens).sum(),
+        messages,
+        limit,
+        dropped: older.len(),
+        to_summarize: match settings.context.strategy {
+            ContextStrategy::Summarize => older,
+            ContextStrategy::DropOldest => Vec::new(),
+        },
+    }
+}
+
+/// Options of summary request. Only context window is taken from conversation, so the whole
+/// prompt fits, while its reply length and stop sequences never cut the saved memory short
+pub fn summary_options(options: &GenerationOptions) -> GenerationOptions {
+    GenerationOptions {
+        temperature: Some(0.2),
+        num_ctx: options.num_ctx,
+        ..GenerationOptions::default()
+    }
+}
+
+/// Prompt asking model to fold `messages` into `memory`
+pub fn summary_prompt(memory: Option<&Memory>, messages: &[Message]) -> String {
+    let mut prompt = String::new();
+
+    if let Some(memory) = memory {
+        prompt.push_str(&format!("Earlier summary:\n{}\n\n", memory.content));
+    }
+
+    for message in messages {
+        prompt.push_str(&format!(
+            "{}: {}\n\n",
+            message.role.as_str(),
+            message.content
+        ));
+    }
+
+    prompt
+}
+
+/// Messages from first one to some message, with what is sent along with them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    pub path: Vec<Message>,
+    /// Latest memory written on branch
+    pub memory: Option<Memory>,
+    /// Images of messages on path by message id
+    pub attachments: HashMap<i64, Vec<Attachment>>,
+}
+
+impl Branch {
+    pub fn plan(&self, settings: &ConversationSettings) -> ContextPlan {
+        plan(
+            &self.path,
+            self.memory.as_ref(),
+            &self.attachments,
+            settings,
+        )
+    }
+}
+
+impl ConversationStore {
+    /// Branch which ends with `message_id`
+    pub async fn branch(&self, message_id: i64) -> Result<Branch, ConversationError> {
+        self.call(move |db| {
+            let path = path_to(db, message_id)?;
+            let Some(conversation_id) = path.first().map(|message| message.conversation_id) else {
+                return Err(ConversationError::MessageNotFound(message_id));
+            };
+
+            let mut statement = db.prepare(
+                "SELECT until_id, content FROM memories WHERE conversation_id = ?1 ORDER BY id DESC",
+            )?;
+            let memories = statement
+                .query_map([conversation_id], |row| {
+                    Ok(Memory {
+                        until_id: row.get(0)?,
+                        content: row.get(1)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let memory = memories.into_iter().find(|memory| {
+                path.iter()
+                    .any(|message| message.id == memory.until_id)
+            });
+
+            let attachments = attachments_of(
+                db,
+                &path.iter().map(|message| message.id).collect::<Vec<_>>(),
+            )?;
+
+            Ok(Branch {
+                path,
+                memory,
+                attachments,
+            })
+        })
+        .await
+    }
+
+    /// Saves summary of branch up to `until_id`
+    pub async fn add_memory(
+        &self,
+        until_id: i64,
+        content: String,
+    ) -> Result<Memory, ConversationError> {
+        self.call(move |db| {
+            let conversation_id = db
+                .query_row(
+                    "SELECT conversation_id FROM messages WHERE id = ?1",
+                    [until_id],
+                    |row| row.get::<_, i64>(0),
+                )
+                .optional()?
+                .ok_or(ConversationError::MessageNotFound(until_id))?;
+
+            db.execute(
+                "INSERT INTO memories (conversation_id, until_id, content, created_at)
+                VALUES (?1, ?2, ?3, ?4)",
+                params![conversation_id, until_id, content, now()],
+            )?;
+
+            Ok(Memory { until_id, content })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::conversations::tests::scratch_store;
+
+    fn message(id: i64, role: Role, content: &str) -> Message {
+        Message {
+            id,
+            conversation_id: 1,
+            role,
+            content: content.to_owned(),
+            created_at: 0,
+            model: None,
+            parent_id: (id > 1).then_some(id - 1),
+        }
+    }
+
+    fn long_chat() -> Vec<Message> {
+        (1..=8)
+            .map(|id| {
+                let role = if id % 2 == 1 {
+                    Role::User
+                } else {
+                    Role::Assistant
+                };
+                message(id, role, &"word ".repeat(100))
+            })
+            .collect()
+    }
+
+    fn settings(strategy: ContextStrategy) -> ConversationSettings {
+        ConversationSettings {
+            system_prompt: "Be brief".to_owned(),
+            options: GenerationOptions {
+                num_ctx: Some(600),
+                ..GenerationOptions::default()
+            },
+            context: ContextSettings {
+                strategy,
+                threshold: 80,
+            },
+            ..ConversationSettings::default()
+        }
+    }
+
+    #[test]
+    fn summary_ignores_reply_limits() {
+        let options = GenerationOptions {
+            num_ctx: Some(8192),
+            num_predict: Some(16),
+            stop: vec!["\n".to_owned()],
+            ..GenerationOptions::default()
+        };
+
+        let summary = summary_options(&options);
+
+        assert_eq!(summary.num_ctx, Some(8192));
+        assert_eq!(summary.num_predict, None);
+        assert!(summary.stop.is_empty());
+    }
+
+    #[test]
+    fn sends_everything_that_fits() {
+        let path = long_chat();
+        let plan = plan(
+            &path[..2],
+            None,
+            &HashMap::new(),
+            &settings(ContextStrategy::DropOldest),
+        );
+
+        assert_eq!(plan.messages.len(), 3);
+        assert_eq!(plan.limit, 600);
+        assert_eq!(plan.dropped, 0);
+    }
+
+    #[test]
+    fn sends_images_of_messages() {
+        let path = long_chat();
+        let settings = settings(ContextStrategy::DropOldest);
+        let attachments = HashMap::from([(
+            1,
+            vec![Attachment {
+                name: "photo.png".to_owned(),
+                data: vec![1, 2, 3],
+                thumbnail: Vec::new(),
+            }],
+        )]);
+
+        let plain = plan(&path[..1], None, &HashMap::new(), &settings);
+        let with_image = plan(&path[..1], None, &attachments, &settings);
+
+        assert_eq!(with_image.messages[1].images, vec![vec![1, 2, 3]]);
+        assert_eq!(with_image.tokens, plain.tokens + IMAGE_TOKENS);
+    }
+
+    #[test]
+    fn drops_oldest_turns() {
+        let plan = plan(
+            &long_chat(),
+            None,
+            &HashMap::new(),
+            &settings(ContextStrategy::DropOldest),
+        );
+
+        assert!(plan.tokens <= 480);
+        assert_eq!(plan.messages[0].role, Role::System);
+        // History starts with a prompt
+        assert_eq!(plan.messages[1].role, Role::User);
+        assert_eq!(plan.dropped + plan.messages.len() - 1, 8);
+        assert!(plan.to_summarize.is_empty());
+    }
+
+    #[test]
+    fn summarizes_into_memory() {
+        let path = long_chat();
+        let settings = settings(ContextStrategy::Summarize);
+
+        let first = plan(&path, None, &HashMap::new(), &settings);
+        assert!(!first.to_summarize.is_empty());
+        assert!(first.tokens <= 240);
+
+        let memory = Memory {
+            until_id: first.to_summarize.last().unwrap().id,
+            content: "They talked about words".to_owned(),
+        };
+        let second = plan(&path, Some(&memory), &HashMap::new(), &settings);
+        assert!(second.to_summarize.is_empty());
+        assert!(
+            second.messages[1]
+                .content
+                .contains("They talked about words")
+        );
+        assert_eq!(second.dropped, 0);
+
+        // Memory of another branch is ignored
+        let other = Memory {
+            until_id: 99,
+            ..memory
+        };
+        assert_eq!(plan(&path, Some(&other), &HashMap::new(), &settings), first);
+    }
+
+    #[tokio::test]
+    async fn keeps_memory_of_branch() {
+        let (_dir, store) = scratch_store();
+        let conversation = store.create_conversation("Chat".to_owned()).await.unwrap();
+
+        let prompt = store
+            .add_message(conversation.id, Role::User, "Hi".to_owned())
+            .await
+            .unwrap();
+        let reply = store
+            .add_reply(
+                conversation.id,
+                prompt.id,
+                "Hello".to_owned(),
+                "model".to_owned(),
+            )
+            .await
+            .unwrap();
+        let other = store
+            .add_sibling(reply.id, "Hey".to_owned(), None)
+            .await
+            .unwrap();
+
+        store
+            .add_memory(reply.id, "Greeted".to_owned())
+            .await
+            .unwrap();
+
+        let branch = store.branch(reply.id).await.unwrap();
+        assert_eq!(branch.path.len(), 2);
+        assert_eq!(branch.memory.unwrap().content, "Greeted");
+
+        let branch = store.branch(other.id).await.unwrap();
+        assert_eq!(branch.memory, None);
+    }
+}
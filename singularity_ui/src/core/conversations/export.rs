@@ -0,0 +1,599 @@
+//! Conversation written into a file for reading or for import into the app
+
+use std::{collections::HashMap, path::Path};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::conversations::{
+        Conversation, ConversationError, ConversationSettings, ConversationStore, Message, Role,
+        attachments::{Attachment, attachments_of},
+        message_from_row,
+    },
+    error::BetterIoError,
+};
+
+/// Marks JSON written by [`to_json`], so importers can recognise it
+const JSON_FORMAT: &str = "singularity-conversation";
+/// Version 1 had only active branch and no settings, version 2 had no images
+const JSON_VERSION: u32 = 3;
+
+const HTML_STYLE: &str = "
+body { font-family: sans-serif; max-width: 860px; margin: 2em auto; padding: 0 1em; background: #1e1e1e; color: #eee; }
+header { border-bottom: 1px solid #444; margin-bottom: 1em; }
+.meta { color: #aaa; font-size: 0.85em; }
+.message { border-radius: 8px; padding: 0.5em 1em; margin: 1em 0; }
+.user { background: #2a6ef0; color: #fff; margin-left: 10%; }
+.assistant { background: #444; margin-right: 10%; }
+.system { background: #2b2b2b; border: 1px dashed #666; }
+pre { background: #111; padding: 0.75em; border-radius: 4px; overflow-x: auto; }
+code { font-family: monospace; }
+table { border-collapse: collapse; }
+td, th { border: 1px solid #666; padding: 0.25em 0.5em; }
+a { color: #9cf; }
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    pub const ALL: [Self; 3] = [Self::Markdown, Self::Json, Self::Html];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Markdown => "Markdown",
+            Self::Json => "JSON",
+            Self::Html => "HTML",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Html => "html",
+        }
+    }
+}
+
+/// Every branch of conversation with its settings, read for [`to_json`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversationTree {
+    /// Messages of all branches, parents before children
+    pub messages: Vec<Message>,
+    pub active_leaf: Option<i64>,
+    pub settings: ConversationSettings,
+    /// Images of messages by message id
+    pub attachments: HashMap<i64, Vec<Attachment>>,
+}
+
+/// Conversation as written by [`to_json`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedConversation {
+    pub format: String,
+    pub version: u32,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    /// Missing in version 1
+    #[serde(default)]
+    pub settings: Option<ConversationSettings>,
+    pub messages: Vec<ExportedMessage>,
+    /// Index of last message of branch shown in app. Version 1 has only this branch
+    #[serde(default)]
+    pub active_leaf: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedMessage {
+    /// Index of parent in [`ExportedConversation::messages`], always lower than index of the message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<usize>,
+    pub role: Role,
+    pub content: String,
+    pub created_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Missing before version 3
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<ExportedAttachment>,
+}
+
+/// Image attached to prompt. Thumbnail is made again on import
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedAttachment {
+    pub name: String,
+    /// Base64 of image file
+    pub data: String,
+}
+
+impl Role {
+    fn heading(self) -> &'static str {
+        match self {
+            Self::System => "System",
+            Self::User => "User",
+            Self::Assistant => "Assistant",
+        }
+    }
+}
+
+fn format_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
+/// Name of file for conversation, without characters forbidden on any platform
+pub fn file_name(conversation: &Conversation, format: ExportFormat) -> String {
+    let stem = conversation
+        .title
+        .chars()
+        .map(|char| match char {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            char if char.is_control() => '_',
+            char => char,
+        })
+        .collect::<String>();
+    let stem = stem.trim().trim_matches('.');
+
+    match stem.is_empty() {
+        true => format!("conversation.{}", format.extension()),
+        false => format!("{stem}.{}", format.extension()),
+    }
+}
+
+/// Headings per role, message text is kept as is since it is Markdown already
+pub fn to_markdown(conversation: &Conversation, messages: &[Message]) -> String {
+    let mut text = format!(
+        "# {}\n\n_Created {}_\n",
+        conversation.title,
+        format_time(conversation.created_at)
+    );
+
+    for message in messages {
+        text.push_str(&format!("\n## {}", message.role.heading()));
+        if let Some(model) = &message.model {
+            text.push_str(&format!(" ({model})"));
+        }
+        text.push_str(&format!("\n\n{}\n", message.content.trim_end()));
+    }
+
+    text
+}
+
+/// Lossless export, includes every branch, conversation settings, images, timestamps and models of messages
+pub fn to_json(conversation: &Conversation, tree: &ConversationTree) -> String {
+    // Message id to index in exported messages
+    let indices = tree
+        .messages
+        .iter()
+        .enumerate()
+        .map(|(index, message)| (message.id, index))
+        .collect::<HashMap<_, _>>();
+
+    let exported = ExportedConversation {
+        format: JSON_FORMAT.to_owned(),
+        version: JSON_VERSION,
+        title: conversation.title.clone(),
+        created_at: conversation.created_at,
+        updated_at: conversation.updated_at,
+        settings: Some(tree.settings.clone()),
+        messages: tree
+            .messages
+            .iter()
+            .map(|message| ExportedMessage {
+                parent: message
+                    .parent_id
+                    .and_then(|parent| indices.get(&parent).copied()),
+                role: message.role,
+                content: message.content.clone(),
+                created_at: message.created_at,
+                model: message.model.clone(),
+                attachments: tree
+                    .attachments
+                    .get(&message.id)
+                    .into_iter()
+                    .flatten()
+                    .map(|attachment| ExportedAttachment {
+                        name: attachment.name.clone(),
+                        data: BASE64_STANDARD.encode(&attachment.data),
+                    })
+                    .collect(),
+            })
+            .collect(),
+        active_leaf: tree
+            .active_leaf
+            .and_then(|leaf| indices.get(&leaf).copied()),
+    };
+
+    serde_json::to_string_pretty(&exported).expect("conversation is always serializable")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Only web and mail links are kept, e.g. `javascript:` would run when link is clicked
+fn safe_url(url: pulldown_cmark::CowStr<'_>) -> pulldown_cmark::CowStr<'_> {
+    const SAFE_PREFIXES: [&str; 3] = ["http://", "https://", "mailto:"];
+
+    let lowercase = url.trim_start().to_lowercase();
+
+    match SAFE_PREFIXES
+        .iter()
+        .any(|prefix| lowercase.starts_with(prefix))
+    {
+        true => url,
+        false => "#".into(),
+    }
+}
+
+/// Markdown of message as HTML. Raw HTML written by model is shown as text
+fn markdown_html(content: &str) -> String {
+    use pulldown_cmark::{Event, Options, Parser, Tag};
+
+    let events = Parser::new_ext(
+        content,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH,
+    )
+    .map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    });
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events);
+
+    html
+}
+
+/// Single page with inline styling and no external resources
+pub fn to_html(conversation: &Conversation, messages: &[Message]) -> String {
+    let title = escape_html(&conversation.title);
+
+    let mut body = String::new();
+    for message in messages {
+        let meta = match &message.model {
+            Some(model) => format!(
+                "{} · {} · {}",
+                message.role.heading(),
+                escape_html(model),
+                format_time(message.created_at)
+            ),
+            None => format!(
+                "{} · {}",
+                message.role.heading(),
+                format_time(message.created_at)
+            ),
+        };
+
+        body.push_str(&format!(
+            "<section class=\"message {}\">\n<div class=\"meta\">{meta}</div>\n{}</section>\n",
+            message.role.as_str(),
+            markdown_html(&message.content)
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n<header>\n<h1>{title}</h1>\n<p class=\"meta\">Created {}</p>\n</header>\n{body}</body>\n</html>\n",
+        format_time(conversation.created_at)
+    )
+}
+
+impl ConversationStore {
+    /// Messages of every branch of conversation, with active leaf and settings
+    pub async fn tree(&self, conversation_id: i64) -> Result<ConversationTree, ConversationError> {
+        let settings = self.settings(conversation_id).await?;
+
+        self.call(move |db| {
+            let active_leaf = db
+                .query_row(
+                    "SELECT active_leaf FROM conversations WHERE id = ?1",
+                    [conversation_id],
+                    |row| row.get::<_, Option<i64>>(0),
+                )
+                .optional()?
+                .ok_or(ConversationError::NotFound(conversation_id))?;
+
+            // Message is always saved after its parent, so ids order parents first
+            let mut statement = db.prepare(
+                "SELECT id, conversation_id, role, content, created_at, model, parent_id
+                FROM messages WHERE conversation_id = ?1 ORDER BY id",
+            )?;
+            let mut rows = statement.query([conversation_id])?;
+
+            let mut messages = Vec::new();
+            while let Some(row) = rows.next()? {
+                messages.push(message_from_row(row)?);
+            }
+
+            let ids = messages
+                .iter()
+                .map(|message| message.id)
+                .collect::<Vec<_>>();
+            let attachments = attachments_of(db, &ids)?;
+
+            Ok(ConversationTree {
+                messages,
+                active_leaf,
+                settings,
+                attachments,
+            })
+        })
+        .await
+    }
+
+    /// Writes conversation `id` into file at `location`
+    pub async fn export(
+        &self,
+        id: i64,
+        format: ExportFormat,
+        location: &Path,
+    ) -> Result<(), ConversationError> {
+        let conversation = self
+            .conversation(id)
+            .await?
+            .ok_or(ConversationError::NotFound(id))?;
+
+        // Markdown and HTML are for reading, so they show only active branch
+        let content = match format {
+            ExportFormat::Markdown => to_markdown(&conversation, &self.messages(id).await?),
+            ExportFormat::Json => to_json(&conversation, &self.tree(id).await?),
+            ExportFormat::Html => to_html(&conversation, &self.messages(id).await?),
+        };
+
+        tokio::fs::write(location, content)
+            .await
+            .map_err(|e| BetterIoError::new(location, "writing exported conversation", e))?;
+
+        tracing::info!(
+            "Exported conversation {id} as {} into {}",
+            format.label(),
+            location.display()
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::conversations::{
+        context::{ContextSettings, ContextStrategy},
+        import::parse,
+        tests::scratch_store,
+    };
+
+    fn sample() -> (Conversation, Vec<Message>) {
+        let conversation = Conversation {
+            id: 1,
+            title: "Tokio: <cancel>?".to_owned(),
+            created_at: 0,
+            updated_at: 60,
+        };
+        let message = |id, role, content: &str, model: Option<&str>| Message {
+            id,
+            conversation_id: 1,
+            role,
+            content: content.to_owned(),
+            created_at: 60,
+            model: model.map(ToOwned::to_owned),
+            parent_id: None,
+        };
+
+        let messages = vec![
+            message(1, Role::User, "How to cancel a task?", None),
+            message(
+                2,
+                Role::Assistant,
+                "Drop it <b>now</b>\n\n```rust\ndrop(task);\n```",
+                Some("gemma3:1b"),
+            ),
+        ];
+
+        (conversation, messages)
+    }
+
+    #[test]
+    fn markdown_has_role_headings() {
+        let (conversation, messages) = sample();
+
+        let markdown = to_markdown(&conversation, &messages);
+
+        assert!(markdown.starts_with("# Tokio: <cancel>?\n\n_Created 1970-01-01 00:00 UTC_\n"));
+        assert!(markdown.contains("\n## User\n\nHow to cancel a task?\n"));
+        assert!(markdown.contains("\n## Assistant (gemma3:1b)\n\nDrop it"));
+    }
+
+    #[test]
+    fn json_keeps_branches_and_settings() {
+        let (conversation, mut messages) = sample();
+        messages[1].parent_id = Some(1);
+        messages.push(Message {
+            id: 3,
+            parent_id: Some(1),
+            ..messages[1].clone()
+        });
+        let tree = ConversationTree {
+            messages,
+            active_leaf: Some(2),
+            settings: ConversationSettings {
+                system_prompt: "Answer briefly".to_owned(),
+                ..ConversationSettings::default()
+            },
+            attachments: HashMap::from([(
+                1,
+                vec![Attachment {
+                    name: "cat.png".to_owned(),
+                    data: b"png".to_vec(),
+                    thumbnail: Vec::new(),
+                }],
+            )]),
+        };
+
+        let exported =
+            serde_json::from_str::<ExportedConversation>(&to_json(&conversation, &tree)).unwrap();
+
+        assert_eq!(exported.format, JSON_FORMAT);
+        assert_eq!(exported.version, JSON_VERSION);
+        assert_eq!(exported.title, conversation.title);
+        assert_eq!(
+            exported
+                .messages
+                .iter()
+                .map(|message| message.parent)
+                .collect::<Vec<_>>(),
+            [None, Some(0), Some(0)]
+        );
+        assert_eq!(exported.messages[1].role, Role::Assistant);
+        assert_eq!(exported.messages[1].content, tree.messages[1].content);
+        assert_eq!(exported.messages[1].model.as_deref(), Some("gemma3:1b"));
+        assert_eq!(
+            exported.messages[0].attachments,
+            [ExportedAttachment {
+                name: "cat.png".to_owned(),
+                data: BASE64_STANDARD.encode(b"png"),
+            }]
+        );
+        assert!(exported.messages[1].attachments.is_empty());
+        assert_eq!(exported.active_leaf, Some(1));
+        assert_eq!(exported.settings, Some(tree.settings));
+    }
+
+    #[tokio::test]
+    async fn json_import_restores_conversation() {
+        let (dir, store) = scratch_store();
+        let conversation = store.create_conversation("Chat".to_owned()).await.unwrap();
+        let prompt = store
+            .add_message(conversation.id, Role::User, "Hi".to_owned())
+            .await
+            .unwrap();
+        let mut png = Vec::new();
+        image::RgbImage::new(4, 4)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let image = Attachment::from_data("cat.png".to_owned(), png).unwrap();
+        store
+            .add_attachments(prompt.id, vec![image.clone()])
+            .await
+            .unwrap();
+        let reply = store
+            .add_reply(
+                conversation.id,
+                prompt.id,
+                "A".to_owned(),
+                "model".to_owned(),
+            )
+            .await
+            .unwrap();
+        store
+            .add_sibling(reply.id, "B".to_owned(), Some("model".to_owned()))
+            .await
+            .unwrap();
+        store.switch_branch(reply.id).await.unwrap();
+        let settings = ConversationSettings {
+            model: Some("llama3.2".to_owned()),
+            system_prompt: "Answer briefly".to_owned(),
+            context: ContextSettings {
+                strategy: ContextStrategy::Summarize,
+                threshold: 60,
+            },
+            ..ConversationSettings::default()
+        };
+        store
+            .set_settings(conversation.id, settings.clone())
+            .await
+            .unwrap();
+
+        let location = dir.path().join("chat.json");
+        store
+            .export(conversation.id, ExportFormat::Json, &location)
+            .await
+            .unwrap();
+
+        let (_other_dir, other) = scratch_store();
+        let preview = parse(&std::fs::read(&location).unwrap()).unwrap();
+        other.import(preview).await.unwrap();
+
+        let imported = other.conversations().await.unwrap().remove(0);
+        let tree = other.tree(imported.id).await.unwrap();
+        assert_eq!(
+            tree.messages
+                .iter()
+                .map(|message| message.content.as_str())
+                .collect::<Vec<_>>(),
+            ["Hi", "A", "B"]
+        );
+        assert_eq!(tree.messages[1].parent_id, Some(tree.messages[0].id));
+        assert_eq!(tree.messages[2].parent_id, Some(tree.messages[0].id));
+        assert_eq!(tree.active_leaf, Some(tree.messages[1].id));
+        assert_eq!(tree.settings, settings);
+        assert_eq!(
+            tree.attachments.get(&tree.messages[0].id),
+            Some(&vec![image])
+        );
+        assert_eq!(tree.attachments.len(), 1);
+    }
+
+    #[test]
+    fn html_keeps_only_web_and_mail_links() {
+        let html = markdown_html(
+            "[a](javascript:alert(1)) [b]( JavaScript:alert(1)) ![c](data:image/png;base64,AA) \
+            [d](https://example.com) [e](mailto:me@example.com)",
+        );
+
+        assert!(!html.to_lowercase().contains("javascript:"));
+        assert!(!html.contains("data:"));
+        assert!(html.contains("<a href=\"#\">a</a>"));
+        assert!(html.contains("<img src=\"#\" alt=\"c\" />"));
+        assert!(html.contains("<a href=\"https://example.com\">d</a>"));
+        assert!(html.contains("<a href=\"mailto:me@example.com\">e</a>"));
+    }
+
+    #[test]
+    fn html_escapes_raw_html() {
+        let (conversation, messages) = sample();
+
+        let html = to_html(&conversation, &messages);
+
+        assert!(html.contains("<title>Tokio: &lt;cancel&gt;?</title>"));
+        assert!(html.contains("&lt;b&gt;now&lt;/b&gt;"));
+        assert!(html.contains("<code class=\"language-rust\">drop(task);"));
+        assert_eq!(
+            file_name(&conversation, ExportFormat::Html),
+            "Tokio_ _cancel__.html"
+        );
+    }
+}
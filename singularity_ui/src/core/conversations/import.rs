@@ -0,0 +1,812 @@
+use std::collections::{HashMap, HashSet};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use rusqlite::{OptionalExtension, params};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::core::conversations::{
+    ConversationError, ConversationSettings, ConversationStore, Role,
+    attachments::Attachment,
+    export::{ExportedAttachment, ExportedConversation},
+    now, title_from_prompt,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("File is not valid JSON. Reason: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(
+        "Unknown format. Expected ChatGPT conversations.json, OpenAI-style messages or exported conversation"
+    )]
+    UnknownFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedMessage {
+    /// Index of parent in [`ImportedConversation::messages`], always lower than index of the message
+    pub parent: Option<usize>,
+    pub role: Role,
+    pub content: String,
+    pub created_at: i64,
+    pub model: Option<String>,
+    /// Images of prompt, only files exported by the app have them
+    pub attachments: Vec<Attachment>,
+}
+
+/// Conversation read from file, not saved yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedConversation {
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub messages: Vec<ImportedMessage>,
+    /// Index of message shown as latest, e.g. branch picked last in ChatGPT
+    pub active_leaf: Option<usize>,
+    /// Messages left out, e.g. tool calls which have no place in the app
+    pub skipped_messages: usize,
+    /// Settings of conversation exported by the app, `None` keeps defaults
+    pub settings: Option<ConversationSettings>,
+    /// Identifies conversation without timestamps of its own, e.g. OpenAI-style messages.
+    /// Others are recognised by title and creation time
+    pub import_key: Option<String>,
+}
+
+impl ImportedConversation {
+    /// Reason why conversation can't be imported
+    fn problem(&self) -> Option<&'static str> {
+        self.messages.is_empty().then_some("has no messages")
+    }
+}
+
+/// Conversations found in file, with ones which can't be imported
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportPreview {
+    pub conversations: Vec<ImportedConversation>,
+    /// Title and reason
+    pub skipped: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversationImport {
+    /// Titles of imported conversations
+    pub imported: Vec<String>,
+    /// Title and reason
+    pub skipped: Vec<(String, String)>,
+}
+
+fn seconds(time: Option<f64>) -> Option<i64> {
+    time.map(|time| time as i64)
+}
+
+fn parse_role(role: &str) -> Option<Role> {
+    match role {
+        "system" | "developer" => Some(Role::System),
+        "user" => Some(Role::User),
+        "assistant" => Some(Role::Assistant),
+        _ => None,
+    }
+}
+
+/// Text of message content, which is either a string or list of parts. Non text parts are left out
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                Value::String(text) => Some(text.as_str()),
+                part => part.get("text").and_then(Value::as_str),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Object(object) => object
+            .get("parts")
+            .map(content_text)
+            .or_else(|| object.get("text").map(content_text))
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptConversation {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    create_time: Option<f64>,
+    #[serde(default)]
+    update_time: Option<f64>,
+    mapping: HashMap<String, ChatGptNode>,
+    #[serde(default)]
+    current_node: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptNode {
+    #[serde(default)]
+    message: Option<ChatGptMessage>,
+    #[serde(default)]
+    parent: Option<String>,
+    #[serde(default)]
+    children: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptMessage {
+    author: ChatGptAuthor,
+    #[serde(default)]
+    content: Value,
+    #[serde(default)]
+    create_time: Option<f64>,
+    #[serde(default)]
+    metadata: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptAuthor {
+    role: String,
+}
+
+/// ChatGPT keeps every edit and regeneration in `mapping` tree. Nodes without text, like hidden system
+/// prompt, are dropped and their children attached to nearest kept ancestor. Nodes reached again
+/// through a broken, cyclic mapping are skipped.
+fn from_chatgpt(export: ChatGptConversation) -> ImportedConversation {
+    let created_at = seconds(export.create_time).unwrap_or_else(now);
+
+    let mut conversation = ImportedConversation {
+        title: export.title.unwrap_or_default(),
+        created_at,
+        updated_at: seconds(export.update_time).unwrap_or(created_at),
+        messages: Vec::new(),
+        active_leaf: None,
+        skipped_messages: 0,
+        settings: None,
+        import_key: None,
+    };
+
+    let mut roots = export
+        .mapping
+        .iter()
+        .filter(|(_, node)| {
+            node.parent
+                .as_ref()
+                .is_none_or(|parent| !export.mapping.contains_key(parent))
+        })
+        .map(|(id, _)| id.as_str())
+        .collect::<Vec<_>>();
+    roots.sort();
+
+    // Node id to index of imported message
+    let mut imported = HashMap::<&str, usize>::new();
+    let mut visited = HashSet::<&str>::new();
+    let mut stack = roots
+        .into_iter()
+        .rev()
+        .map(|id| (id, None))
+        .collect::<Vec<_>>();
+
+    while let Some((id, parent)) = stack.pop() {
+        let Some(node) = export.mapping.get(id) else {
+            continue;
+        };
+
+        if !visited.insert(id) {
+            conversation.skipped_messages += 1;
+            continue;
+        }
+
+        let mut kept = parent;
+
+        if let Some(message) = &node.message {
+            let content = content_text(&message.content);
+
+            match parse_role(&message.author.role) {
+                _ if content.trim().is_empty() => (),
+                Some(role) => {
+                    conversation.messages.push(ImportedMessage {
+                        parent,
+                        role,
+                        content,
+                        created_at: seconds(message.create_time).unwrap_or(created_at),
+                        model: message
+                            .metadata
+                            .get("model_slug")
+                            .and_then(Value::as_str)
+                            .map(ToOwned::to_owned),
+                        attachments: Vec::new(),
+                    });
+
+                    kept = Some(conversation.messages.len() - 1);
+                    imported.insert(id, conversation.messages.len() - 1);
+                }
+                None => conversation.skipped_messages += 1,
+            }
+        }
+
+        stack.extend(
+            node.children
+                .iter()
+                .rev()
+                .map(|child| (child.as_str(), kept)),
+        );
+    }
+
+    // Latest kept message on branch picked in ChatGPT
+    let mut current = export.current_node.as_deref();
+    let mut walked = HashSet::<&str>::new();
+    while let Some(id) = current.filter(|id| walked.insert(id)) {
+        if let Some(index) = imported.get(id) {
+            conversation.active_leaf = Some(*index);
+            break;
+        }
+
+        current = export
+            .mapping
+            .get(id)
+            .and_then(|node| node.parent.as_deref());
+    }
+
+    if conversation.active_leaf.is_none() {
+        conversation.active_leaf = conversation.messages.len().checked_sub(1);
+    }
+
+    conversation
+}
+
+/// Linear list of `{ "role": ..., "content": ... }` messages
+fn from_messages(title: Option<String>, messages: &[Value]) -> ImportedConversation {
+    let created_at = now();
+
+    let mut conversation = ImportedConversation {
+        title: title.unwrap_or_default(),
+        created_at,
+        updated_at: created_at,
+        messages: Vec::new(),
+        active_leaf: None,
+        skipped_messages: 0,
+        settings: None,
+        import_key: None,
+    };
+
+    for message in messages {
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .and_then(parse_role);
+        let content = message.get("content").map(content_text).unwrap_or_default();
+
+        let Some(role) = role.filter(|_| !content.trim().is_empty()) else {
+            conversation.skipped_messages += 1;
+            continue;
+        };
+
+        conversation.messages.push(ImportedMessage {
+            parent: conversation.messages.len().checked_sub(1),
+            role,
+            content,
+            created_at,
+            model: None,
+            attachments: Vec::new(),
+        });
+    }
+
+    conversation.active_leaf = conversation.messages.len().checked_sub(1);
+    conversation.import_key = Some(content_key(&conversation));
+
+    conversation
+}
+
+/// Hex encoded SHA-256 of title and messages. Import time is left out, so same file gives same key
+fn content_key(conversation: &ImportedConversation) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(conversation.title.as_bytes());
+
+    for message in &conversation.messages {
+        hasher.update([0]);
+        hasher.update(message.role.as_str().as_bytes());
+        hasher.update([0]);
+        hasher.update(message.content.as_bytes());
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Image of exported message, left out with a warning if it can't be read
+fn imported_attachment(attachment: ExportedAttachment) -> Option<Attachment> {
+    let res = BASE64_STANDARD
+        .decode(&attachment.data)
+        .map_err(|e| e.to_string())
+        .and_then(|data| {
+            Attachment::from_data(attachment.name.clone(), data).map_err(|e| e.to_string())
+        });
+
+    res.inspect_err(|e| tracing::warn!("Skipping image {}. Reason: {e}", attachment.name))
+        .ok()
+}
+
+/// Conversation written by [`super::export::to_json`]. Version 1 holds only active branch,
+/// where every message follows previous one
+fn from_exported(exported: ExportedConversation) -> ImportedConversation {
+    let linear = exported.version < 2;
+
+    let messages = exported
+        .messages
+        .into_iter()
+        .enumerate()
+        .map(|(index, message)| ImportedMessage {
+            parent: match linear {
+                true => index.checked_sub(1),
+                false => message.parent.filter(|parent| *parent < index),
+            },
+            role: message.role,
+            content: message.content,
+            created_at: message.created_at,
+            model: message.model,
+            attachments: message
+                .attachments
+                .into_iter()
+                .filter_map(imported_attachment)
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+
+    ImportedConversation {
+        title: exported.title,
+        created_at: exported.created_at,
+        updated_at: exported.updated_at,
+        active_leaf: exported
+            .active_leaf
+            .filter(|leaf| !linear && *leaf < messages.len())
+            .or(messages.len().checked_sub(1)),
+        messages,
+        skipped_messages: 0,
+        settings: exported.settings,
+        import_key: None,
+    }
+}
+
+fn is_message_list(items: &[Value]) -> bool {
+    items.iter().all(|item| item.get("role").is_some())
+}
+
+/// Reads conversations from ChatGPT `conversations.json`, OpenAI-style messages JSON
+/// or file exported by the app
+pub fn parse(content: &[u8]) -> Result<ImportPreview, ImportError> {
+    let value = serde_json::from_slice::<Value>(content)?;
+
+    let conversations = match value {
+        Value::Array(items) if !items.is_empty() && is_message_list(&items) => {
+            vec![from_messages(None, &items)]
+        }
+        Value::Array(items) => {
+            let mut preview = ImportPreview::default();
+
+            for (index, item) in items.into_iter().enumerate() {
+                let title = item
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .map(ToOwned::to_owned)
+                    .unwrap_or_else(|| format!("Conversation {}", index + 1));
+
+                match serde_json::from_value::<ChatGptConversation>(item) {
+                    Ok(export) => preview.conversations.push(from_chatgpt(export)),
+                    Err(e) => preview
+                        .skipped
+                        .push((title, format!("unsupported structure: {e}"))),
+                }
+            }
+
+            return Ok(finish(preview));
+        }
+        Value::Object(object) if object.get("format").and_then(Value::as_str).is_some() => {
+            vec![from_exported(serde_json::from_value(Value::Object(
+                object,
+            ))?)]
+        }
+        Value::Object(object) if object.contains_key("mapping") => {
+            vec![from_chatgpt(serde_json::from_value(Value::Object(object))?)]
+        }
+        Value::Object(object) => match object.get("messages") {
+            Some(Value::Array(messages)) if is_message_list(messages) => {
+                let title = object
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .map(ToOwned::to_owned);
+
+                vec![from_messages(title, messages)]
+            }
+            _ => return Err(ImportError::UnknownFormat),
+        },
+        _ => return Err(ImportError::UnknownFormat),
+    };
+
+    Ok(finish(ImportPreview {
+        conversations,
+        skipped: Vec::new(),
+    }))
+}
+
+/// Moves conversations which can't be imported into skipped ones and fills missing titles
+fn finish(mut preview: ImportPreview) -> ImportPreview {
+    let mut conversations = Vec::with_capacity(preview.conversations.len());
+
+    for mut conversation in preview.conversations {
+        if conversation.title.trim().is_empty() {
+            conversation.title = conversation
+                .messages
+                .iter()
+                .find(|message| message.role == Role::User)
+                .map(|message| title_from_prompt(&message.content))
+                .unwrap_or_else(|| "Imported chat".to_owned());
+        }
+
+        match conversation.problem() {
+            Some(problem) => preview
+                .skipped
+                .push((conversation.title, problem.to_owned())),
+            None => conversations.push(conversation),
+        }
+    }
+
+    preview.conversations = conversations;
+    preview
+}
+
+impl ConversationStore {
+    /// Saves conversations of preview. Conversations with same title and creation time as saved ones
+    /// are treated as imported before and skipped.
+    pub async fn import(
+        &self,
+        preview: ImportPreview,
+    ) -> Result<ConversationImport, ConversationError> {
+        self.call(move |db| {
+            let mut report = ConversationImport {
+                imported: Vec::new(),
+                skipped: preview.skipped,
+            };
+
+            let transaction = db.transaction()?;
+
+            for conversation in preview.conversations {
+                let exists = match &conversation.import_key {
+                    Some(key) => transaction
+                        .query_row(
+                            "SELECT 1 FROM conversations WHERE import_key = ?1",
+                            [key],
+                            |_| Ok(()),
+                        )
+                        .optional()?,
+                    None => transaction
+                        .query_row(
+                            "SELECT 1 FROM conversations WHERE title = ?1 AND created_at = ?2",
+                            params![conversation.title, conversation.created_at],
+                            |_| Ok(()),
+                        )
+                        .optional()?,
+                }
+                .is_some();
+
+                if exists {
+                    report
+                        .skipped
+                        .push((conversation.title, "already imported".to_owned()));
+                    continue;
+                }
+
+                transaction.execute(
+                    "INSERT INTO conversations (title, created_at, updated_at, import_key)
+                    VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        conversation.title,
+                        conversation.created_at,
+                        conversation.updated_at,
+                        conversation.import_key
+                    ],
+                )?;
+                let conversation_id = transaction.last_insert_rowid();
+
+                if let Some(settings) = &conversation.settings {
+                    transaction.execute(
+                        "UPDATE conversations SET model = ?2, system_prompt = ?3, options = ?4, context = ?5
+                        WHERE id = ?1",
+                        params![
+                            conversation_id,
+                            settings.model,
+                            settings.system_prompt,
+                            serde_json::to_string(&settings.options)?,
+                            serde_json::to_string(&settings.context)?
+                        ],
+                    )?;
+                }
+
+                let mut ids = Vec::<i64>::with_capacity(conversation.messages.len());
+                for message in &conversation.messages {
+                    transaction.execute(
+                        "INSERT INTO messages
+                        (conversation_id, role, content, created_at, model, parent_id)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                        params![
+                            conversation_id,
+                            message.role.as_str(),
+                            message.content,
+                            message.created_at,
+                            message.model,
+                            message.parent.map(|parent| ids[parent])
+                        ],
+                    )?;
+                    let message_id = transaction.last_insert_rowid();
+                    ids.push(message_id);
+
+                    for attachment in &message.attachments {
+                        transaction.execute(
+                            "INSERT INTO attachments (message_id, name, data, thumbnail) VALUES (?1, ?2, ?3, ?4)",
+                            params![
+                                message_id,
+                                attachment.name,
+                                attachment.data,
+                                attachment.thumbnail
+                            ],
+                        )?;
+                    }
+                }
+
+                transaction.execute(
+                    "UPDATE conversations SET active_leaf = ?2 WHERE id = ?1",
+                    params![
+                        conversation_id,
+                        conversation.active_leaf.map(|leaf| ids[leaf])
+                    ],
+                )?;
+
+                report.imported.push(conversation.title);
+            }
+
+            transaction.commit()?;
+
+            tracing::info!(
+                "Imported {} conversations, skipped {}",
+                report.imported.len(),
+                report.skipped.len()
+            );
+
+            Ok(report)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::conversations::tests::scratch_store;
+
+    const CHATGPT: &str = r#"[
+        {
+            "title": "Borrow checker",
+            "create_time": 1700000000.5,
+            "update_time": 1700000100.0,
+            "current_node": "edited-reply",
+            "mapping": {
+                "root": { "message": null, "parent": null, "children": ["system"] },
+                "system": {
+                    "message": { "author": { "role": "system" }, "content": { "content_type": "text", "parts": [""] } },
+                    "parent": "root",
+                    "children": ["question", "edited"]
+                },
+                "question": {
+                    "message": { "author": { "role": "user" }, "create_time": 1700000010, "content": { "content_type": "text", "parts": ["Why?"] } },
+                    "parent": "system",
+                    "children": ["reply"]
+                },
+                "reply": {
+                    "message": { "author": { "role": "assistant" }, "content": { "content_type": "text", "parts": ["Because"] }, "metadata": { "model_slug": "gpt-4o" } },
+                    "parent": "question",
+                    "children": []
+                },
+                "edited": {
+                    "message": { "author": { "role": "user" }, "content": { "content_type": "text", "parts": ["Why not?"] } },
+                    "parent": "system",
+                    "children": ["tool"]
+                },
+                "tool": {
+                    "message": { "author": { "role": "tool" }, "content": { "content_type": "text", "parts": ["search results"] } },
+                    "parent": "edited",
+                    "children": ["edited-reply"]
+                },
+                "edited-reply": {
+                    "message": { "author": { "role": "assistant" }, "content": { "content_type": "text", "parts": ["It is fine"] } },
+                    "parent": "tool",
+                    "children": []
+                }
+            }
+        },
+        { "title": "Empty", "mapping": { "root": { "message": null, "parent": null, "children": [] } } },
+        { "title": "Broken" }
+    ]"#;
+
+    #[test]
+    fn chatgpt_keeps_branches() {
+        let preview = parse(CHATGPT.as_bytes()).unwrap();
+
+        assert_eq!(
+            preview
+                .skipped
+                .iter()
+                .map(|(title, _)| title.as_str())
+                .collect::<Vec<_>>(),
+            ["Broken", "Empty"]
+        );
+
+        let [conversation] = &preview.conversations[..] else {
+            panic!("expected one conversation");
+        };
+
+        assert_eq!(conversation.created_at, 1700000000);
+        assert_eq!(conversation.skipped_messages, 1);
+        assert_eq!(
+            conversation
+                .messages
+                .iter()
+                .map(|message| (message.parent, message.content.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (None, "Why?"),
+                (Some(0), "Because"),
+                (None, "Why not?"),
+                (Some(2), "It is fine")
+            ]
+        );
+        assert_eq!(conversation.messages[0].created_at, 1700000010);
+        assert_eq!(conversation.messages[1].model.as_deref(), Some("gpt-4o"));
+        assert_eq!(conversation.active_leaf, Some(3));
+    }
+
+    #[test]
+    fn chatgpt_cycles_are_skipped() {
+        let preview = parse(
+            br#"[{
+                "title": "Looped",
+                "current_node": "loop-a",
+                "mapping": {
+                    "root": { "message": null, "parent": null, "children": ["question"] },
+                    "question": {
+                        "message": { "author": { "role": "user" }, "content": { "content_type": "text", "parts": ["Again?"] } },
+                        "parent": "root",
+                        "children": ["reply"]
+                    },
+                    "reply": {
+                        "message": { "author": { "role": "assistant" }, "content": { "content_type": "text", "parts": ["Again"] } },
+                        "parent": "question",
+                        "children": ["question"]
+                    },
+                    "loop-a": { "message": null, "parent": "loop-b", "children": [] },
+                    "loop-b": { "message": null, "parent": "loop-a", "children": [] }
+                }
+            }]"#,
+        )
+        .unwrap();
+
+        let [conversation] = &preview.conversations[..] else {
+            panic!("expected one conversation");
+        };
+
+        assert_eq!(conversation.skipped_messages, 1);
+        assert_eq!(
+            conversation
+                .messages
+                .iter()
+                .map(|message| (message.parent, message.content.as_str()))
+                .collect::<Vec<_>>(),
+            [(None, "Again?"), (Some(0), "Again")]
+        );
+        assert_eq!(conversation.active_leaf, Some(1));
+    }
+
+    #[test]
+    fn reads_version_1_export_as_one_branch() {
+        let preview = parse(
+            br#"{
+                "format": "singularity-conversation",
+                "version": 1,
+                "title": "Old",
+                "created_at": 10,
+                "updated_at": 20,
+                "messages": [
+                    { "role": "user", "content": "Hi", "created_at": 10 },
+                    { "role": "assistant", "content": "Hello", "created_at": 20, "model": "gemma3:1b" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let [conversation] = &preview.conversations[..] else {
+            panic!("expected one conversation");
+        };
+
+        assert_eq!(conversation.messages[1].parent, Some(0));
+        assert_eq!(conversation.active_leaf, Some(1));
+        assert_eq!(conversation.settings, None);
+    }
+
+    #[test]
+    fn reads_openai_messages() {
+        let preview = parse(
+            br#"{ "messages": [
+                { "role": "system", "content": "Be brief" },
+                { "role": "user", "content": [{ "type": "text", "text": "Hello there" }] },
+                { "role": "function", "content": "{}" }
+            ] }"#,
+        )
+        .unwrap();
+
+        let conversation = &preview.conversations[0];
+
+        assert_eq!(conversation.title, "Hello there");
+        assert_eq!(conversation.messages.len(), 2);
+        assert_eq!(conversation.messages[1].parent, Some(0));
+        assert_eq!(conversation.skipped_messages, 1);
+
+        assert!(matches!(
+            parse(br#"{ "unknown": 1 }"#),
+            Err(ImportError::UnknownFormat)
+        ));
+    }
+
+    #[tokio::test]
+    async fn saves_active_branch_once() {
+        let (_dir, store) = scratch_store();
+
+        let report = store
+            .import(parse(CHATGPT.as_bytes()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(report.imported, ["Borrow checker"]);
+
+        let conversation = store.conversations().await.unwrap().remove(0);
+        let messages = store.messages(conversation.id).await.unwrap();
+        assert_eq!(
+            messages
+                .iter()
+                .map(|message| message.content.as_str())
+                .collect::<Vec<_>>(),
+            ["Why not?", "It is fine"]
+        );
+
+        let report = store
+            .import(parse(CHATGPT.as_bytes()).unwrap())
+            .await
+            .unwrap();
+        assert!(report.imported.is_empty());
+        assert!(
+            report
+                .skipped
+                .contains(&("Borrow checker".to_owned(), "already imported".to_owned()))
+        );
+    }
+
+    #[tokio::test]
+    async fn saves_openai_messages_once() {
+        let (_dir, store) = scratch_store();
+        let content = br#"{ "title": "Greeting", "messages": [
+            { "role": "user", "content": "Hello there" },
+            { "role": "assistant", "content": "Hi" }
+        ] }"#;
+
+        let report = store.import(parse(content).unwrap()).await.unwrap();
+        assert_eq!(report.imported, ["Greeting"]);
+
+        let report = store.import(parse(content).unwrap()).await.unwrap();
+        assert!(report.imported.is_empty());
+        assert_eq!(
+            report.skipped,
+            [("Greeting".to_owned(), "already imported".to_owned())]
+        );
+        assert_eq!(store.conversations().await.unwrap().len(), 1);
+    }
+}
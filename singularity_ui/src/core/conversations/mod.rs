@@ -0,0 +1,499 @@
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::{
+    core::{llm::get_or_create_app_dir, storage::CONVERSATIONS_DIR},
+    error::BetterIoError,
+};
+
+pub mod attachments;
+mod branch;
+pub mod context;
+pub mod export;
+pub mod import;
+pub mod queue;
+mod schema;
+mod search;
+mod settings;
+pub mod title;
+
+pub use branch::ThreadMessage;
+pub use search::SearchHit;
+pub use settings::ConversationSettings;
+
+const DATABASE_FILENAME: &str = "conversations.sqlite";
+/// Longest title taken from first message of conversation, in characters
+const TITLE_LENGTH: usize = 40;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConversationError {
+    #[error(transparent)]
+    Io(#[from] BetterIoError),
+    #[error("Conversation database error. Reason: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("Conversation database task failed. Reason: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    #[error("Unknown message role {0}")]
+    UnknownRole(String),
+    #[error("Conversation {0} does not exist")]
+    NotFound(i64),
+    #[error("Message {0} does not exist")]
+    MessageNotFound(i64),
+    #[error("Malformed conversation settings. Reason: {0}")]
+    Settings(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::User => "user",
+            Self::Assistant => "assistant",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, ConversationError> {
+        match value {
+            "system" => Ok(Self::System),
+            "user" => Ok(Self::User),
+            "assistant" => Ok(Self::Assistant),
+            other => Err(ConversationError::UnknownRole(other.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversation {
+    pub id: i64,
+    pub title: String,
+    /// Unix time in seconds
+    pub created_at: i64,
+    /// Unix time in seconds, bumped by every new message
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub id: i64,
+    pub conversation_id: i64,
+    pub role: Role,
+    pub content: String,
+    /// Unix time in seconds
+    pub created_at: i64,
+    /// Model which wrote the message, set for assistant messages
+    pub model: Option<String>,
+    /// Message this one answers or follows, `None` for first message
+    pub parent_id: Option<i64>,
+}
+
+pub(crate) fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Title of new conversation made of first line of its first message
+pub fn title_from_prompt(prompt: &str) -> String {
+    let line = prompt.trim().lines().next().unwrap_or_default().trim();
+
+    match line.char_indices().nth(TITLE_LENGTH) {
+        Some((end, _)) => format!("{}…", line[..end].trim_end()),
+        None if line.is_empty() => "New chat".to_owned(),
+        None => line.to_owned(),
+    }
+}
+
+fn conversation_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Conversation> {
+    Ok(Conversation {
+        id: row.get("id")?,
+        title: row.get("title")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn message_from_row(row: &rusqlite::Row<'_>) -> Result<Message, ConversationError> {
+    Ok(Message {
+        id: row.get("id")?,
+        conversation_id: row.get("conversation_id")?,
+        role: Role::parse(&row.get::<_, String>("role")?)?,
+        content: row.get("content")?,
+        created_at: row.get("created_at")?,
+        model: row.get("model")?,
+        parent_id: row.get("parent_id")?,
+    })
+}
+
+/// Saved conversations in SQLite database under application data dir.
+///
+/// Every call opens its own connection on blocking thread, so the database file can be removed by storage
+/// cleanup at any time and is simply created again.
+#[derive(Debug, Clone)]
+pub struct ConversationStore {
+    location: PathBuf,
+}
+
+impl ConversationStore {
+    pub fn new(location: impl Into<PathBuf>) -> Self {
+        Self {
+            location: location.into(),
+        }
+    }
+
+    /// Store in application data dir
+    pub async fn open_default() -> Result<Self, ConversationError> {
+        let dir = get_or_create_app_dir(None).await?.join(CONVERSATIONS_DIR);
+
+        Ok(Self::new(dir.join(DATABASE_FILENAME)))
+    }
+
+    fn connect(location: &Path) -> Result<Connection, ConversationError> {
+        if let Some(parent) = location.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| BetterIoError::new(parent, "creating conversations dir", e))?;
+        }
+
+        let mut connection = Connection::open(location)?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+        schema::migrate(&mut connection)?;
+
+        Ok(connection)
+    }
+
+    /// Runs `query` with fresh connection on blocking thread
+    async fn call<T: Send + 'static>(
+        &self,
+        query: impl FnOnce(&mut Connection) -> Result<T, ConversationError> + Send + 'static,
+    ) -> Result<T, ConversationError> {
+        let location = self.location.clone();
+
+        tokio::task::spawn_blocking(move || query(&mut Self::connect(&location)?)).await?
+    }
+
+    pub async fn create_conversation(
+        &self,
+        title: String,
+    ) -> Result<Conversation, ConversationError> {
+        self.call(move |db| {
+            let now = now();
+
+            db.execute(
+                "INSERT INTO conversations (title, created_at, updated_at) VALUES (?1, ?2, ?2)",
+                params![title, now],
+            )?;
+
+            Ok(Conversation {
+                id: db.last_insert_rowid(),
+                title,
+                created_at: now,
+                updated_at: now,
+            })
+        })
+        .await
+    }
+
+    /// Most recently updated first
+    pub async fn conversations(&self) -> Result<Vec<Conversation>, ConversationError> {
+        self.call(|db| {
+            let mut statement = db.prepare(
+                "SELECT id, title, created_at, updated_at FROM conversations
+                ORDER BY updated_at DESC, id DESC",
+            )?;
+
+            let conversations = statement
+                .query_map([], conversation_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(conversations)
+        })
+        .await
+    }
+
+    pub async fn conversation(&self, id: i64) -> Result<Option<Conversation>, ConversationError> {
+        self.call(move |db| {
+            Ok(db
+                .query_row(
+                    "SELECT id, title, created_at, updated_at FROM conversations WHERE id = ?1",
+                    [id],
+                    conversation_from_row,
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    pub async fn rename_conversation(
+        &self,
+        id: i64,
+        title: String,
+    ) -> Result<(), ConversationError> {
+        self.call(move |db| {
+            db.execute(
+                "UPDATE conversations SET title = ?2 WHERE id = ?1",
+                params![id, title],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Removes conversation with all of its messages
+    pub async fn delete_conversation(&self, id: i64) -> Result<(), ConversationError> {
+        self.call(move |db| {
+            db.execute("DELETE FROM conversations WHERE id = ?1", [id])?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Messages on active branch of conversation, from first to latest
+    pub async fn messages(&self, conversation_id: i64) -> Result<Vec<Message>, ConversationError> {
+        self.call(move |db| active_path(db, conversation_id)).await
+    }
+
+    pub async fn message(&self, id: i64) -> Result<Option<Message>, ConversationError> {
+        self.call(move |db| find_message(db, id)).await
+    }
+
+    /// Appends message to active branch of conversation
+    pub async fn add_message(
+        &self,
+        conversation_id: i64,
+        role: Role,
+        content: String,
+    ) -> Result<Message, ConversationError> {
+        self.call(move |db| {
+            let transaction = db.transaction()?;
+
+            let parent_id = transaction
+                .query_row(
+                    "SELECT active_leaf FROM conversations WHERE id = ?1",
+                    [conversation_id],
+                    |row| row.get::<_, Option<i64>>(0),
+                )
+                .optional()?
+                .ok_or(ConversationError::NotFound(conversation_id))?;
+
+            let message = insert_message(
+                &transaction,
+                conversation_id,
+                parent_id,
+                role,
+                content,
+                None,
+            )?;
+            transaction.commit()?;
+
+            Ok(message)
+        })
+        .await
+    }
+
+    /// Saves reply of `model` to message `parent_id`, reply becomes active branch
+    pub async fn add_reply(
+        &self,
+        conversation_id: i64,
+        parent_id: i64,
+        content: String,
+        model: String,
+    ) -> Result<Message, ConversationError> {
+        self.call(move |db| {
+            let transaction = db.transaction()?;
+            let message = insert_message(
+                &transaction,
+                conversation_id,
+                Some(parent_id),
+                Role::Assistant,
+                content,
+                Some(model),
+            )?;
+            transaction.commit()?;
+
+            Ok(message)
+        })
+        .await
+    }
+}
+
+fn find_message(db: &Connection, id: i64) -> Result<Option<Message>, ConversationError> {
+    let mut statement = db.prepare(
+        "SELECT id, conversation_id, role, content, created_at, model, parent_id
+        FROM messages WHERE id = ?1",
+    )?;
+    let mut rows = statement.query([id])?;
+
+    rows.next()?.map(message_from_row).transpose()
+}
+
+/// Path from first message of conversation to its active leaf
+fn active_path(db: &Connection, conversation_id: i64) -> Result<Vec<Message>, ConversationError> {
+    let leaf = db
+        .query_row(
+            "SELECT active_leaf FROM conversations WHERE id = ?1",
+            [conversation_id],
+            |row| row.get::<_, Option<i64>>(0),
+        )
+        .optional()?
+        .flatten();
+
+    match leaf {
+        Some(leaf) => path_to(db, leaf),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Messages from first one of conversation to `leaf`
+fn path_to(db: &Connection, leaf: i64) -> Result<Vec<Message>, ConversationError> {
+    let mut statement = db.prepare(
+        "WITH RECURSIVE path (id, depth) AS (
+            SELECT ?1, 0
+            UNION ALL
+            SELECT messages.parent_id, path.depth + 1 FROM messages
+            JOIN path ON messages.id = path.id
+            WHERE messages.parent_id IS NOT NULL
+        )
+        SELECT messages.id, conversation_id, role, content, created_at, model, parent_id
+        FROM messages JOIN path ON messages.id = path.id
+        ORDER BY path.depth DESC",
+    )?;
+    let mut rows = statement.query([leaf])?;
+
+    let mut messages = Vec::new();
+    while let Some(row) = rows.next()? {
+        messages.push(message_from_row(row)?);
+    }
+
+    Ok(messages)
+}
+
+/// Saves message under `parent_id` and makes it active leaf of conversation
+fn insert_message(
+    db: &Connection,
+    conversation_id: i64,
+    parent_id: Option<i64>,
+    role: Role,
+    content: String,
+    model: Option<String>,
+) -> Result<Message, ConversationError> {
+    let now = now();
+
+    db.execute(
+        "INSERT INTO messages (conversation_id, role, content, created_at, model, parent_id)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            conversation_id,
+            role.as_str(),
+            content,
+            now,
+            model,
+            parent_id
+        ],
+    )?;
+    let id = db.last_insert_rowid();
+
+    db.execute(
+        "UPDATE conversations SET updated_at = ?2, active_leaf = ?3 WHERE id = ?1",
+        params![conversation_id, now, id],
+    )?;
+
+    Ok(Message {
+        id,
+        conversation_id,
+        role,
+        content,
+        created_at: now,
+        model,
+        parent_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    pub(super) fn scratch_store() -> (TempDir, ConversationStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ConversationStore::new(dir.path().join(DATABASE_FILENAME));
+
+        (dir, store)
+    }
+
+    #[test]
+    fn title_is_first_line_of_prompt() {
+        assert_eq!(title_from_prompt("  Hello\nworld"), "Hello");
+        assert_eq!(title_from_prompt(" \n"), "New chat");
+        assert_eq!(
+            title_from_prompt(&"a".repeat(TITLE_LENGTH + 5)),
+            format!("{}…", "a".repeat(TITLE_LENGTH))
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_messages_of_conversation() {
+        let (_dir, store) = scratch_store();
+
+        let first = store.create_conversation("First".to_owned()).await.unwrap();
+        let second = store
+            .create_conversation("Second".to_owned())
+            .await
+            .unwrap();
+
+        store
+            .add_message(first.id, Role::User, "Hello".to_owned())
+            .await
+            .unwrap();
+        store
+            .add_message(first.id, Role::Assistant, "Hi".to_owned())
+            .await
+            .unwrap();
+
+        let messages = store.messages(first.id).await.unwrap();
+        assert_eq!(
+            messages
+                .iter()
+                .map(|message| (message.role, message.content.as_str()))
+                .collect::<Vec<_>>(),
+            [(Role::User, "Hello"), (Role::Assistant, "Hi")]
+        );
+        assert!(store.messages(second.id).await.unwrap().is_empty());
+
+        store
+            .rename_conversation(second.id, "Renamed".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(
+            store.conversation(second.id).await.unwrap().unwrap().title,
+            "Renamed"
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_removes_messages() {
+        let (_dir, store) = scratch_store();
+
+        let conversation = store.create_conversation("Chat".to_owned()).await.unwrap();
+        store
+            .add_message(conversation.id, Role::User, "Hello".to_owned())
+            .await
+            .unwrap();
+
+        store.delete_conversation(conversation.id).await.unwrap();
+
+        assert!(store.conversations().await.unwrap().is_empty());
+        assert!(store.messages(conversation.id).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+//! Prompts waiting for reply to previous one of their conversation
+
+use std::collections::{HashSet, VecDeque};
+
+use super::attachments::Attachment;
+
+/// Conversation prompts are sent to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Chat {
+    Saved(i64),
+    /// New conversation, created once its first prompt starts. Every new chat gets its own draft
+    Draft(u64),
+}
+
+/// Prompt sent while conversation was busy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedPrompt {
+    /// Identifies prompt until it is saved
+    pub ticket: u64,
+    pub conversation: Chat,
+    pub text: String,
+    pub attachments: Vec<Attachment>,
+}
+
+/// Sends of each conversation in order they were made, one at a time
+#[derive(Debug, Default)]
+pub struct PromptQueue {
+    waiting: VecDeque<QueuedPrompt>,
+    /// Conversations which prompt is being answered
+    running: HashSet<Chat>,
+    next_ticket: u64,
+    /// New chat shown while no saved conversation is open
+    draft: u64,
+}
+
+impl PromptQueue {
+    /// Conversation with `id`, or current new chat for `None`
+    pub fn chat(&self, id: Option<i64>) -> Chat {
+        id.map_or(Chat::Draft(self.draft), Chat::Saved)
+    }
+
+    /// Starts another new chat, so prompts of previous one don't mix with its prompts
+    pub fn new_draft(&mut self) {
+        self.draft += 1;
+    }
+
+    /// Adds prompt after earlier ones of conversation and returns its ticket
+    pub fn push(&mut self, conversation: Chat, text: String, attachments: Vec<Attachment>) -> u64 {
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+
+        self.waiting.push_back(QueuedPrompt {
+            ticket,
+            conversation,
+            text,
+            attachments,
+        });
+
+        ticket
+    }
+
+    /// Next prompt of conversation, `None` if there is none or previous one is still answered
+    pub fn start(&mut self, conversation: Chat) -> Option<QueuedPrompt> {
+        if self.running.contains(&conversation) {
+            return None;
+        }
+
+        let index = self
+            .waiting
+            .iter()
+            .position(|prompt| prompt.conversation == conversation)?;
+        self.running.insert(conversation);
+
+        self.waiting.remove(index)
+    }
+
+    /// Marks conversation busy with answer not taken from queue, like regenerated reply.
+    /// `false` if conversation is answering already
+    pub fn reserve(&mut self, conversation: Chat) -> bool {
+        self.running.insert(conversation)
+    }
+
+    /// Marks answer of conversation finished, so its next prompt can start
+    pub fn finish(&mut self, conversation: Chat) {
+        self.running.remove(&conversation);
+    }
+
+    /// Moves prompts of new chat `draft` to conversation created for it
+    pub fn created(&mut self, draft: u64, id: i64) {
+        let draft = Chat::Draft(draft);
+
+        if self.running.remove(&draft) {
+            self.running.insert(Chat::Saved(id));
+        }
+
+        for prompt in &mut self.waiting {
+            if prompt.conversation == draft {
+                prompt.conversation = Chat::Saved(id);
+            }
+        }
+    }
+
+    /// Takes waiting prompt out of queue, `false` if it already started
+    pub fn remove(&mut self, ticket: u64) -> bool {
+        let before = self.waiting.len();
+        self.waiting.retain(|prompt| prompt.ticket != ticket);
+
+        self.waiting.len() != before
+    }
+
+    /// Waiting prompts of conversation, oldest first
+    pub fn waiting(&self, conversation: Chat) -> Vec<QueuedPrompt> {
+        self.waiting
+            .iter()
+            .filter(|prompt| prompt.conversation == conversation)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_one_prompt_of_conversation_at_a_time() {
+        let mut queue = PromptQueue::default();
+        let first = queue.push(Chat::Saved(1), "first".to_owned(), Vec::new());
+        let second = queue.push(Chat::Saved(1), "second".to_owned(), Vec::new());
+        let other = queue.push(Chat::Saved(2), "other".to_owned(), Vec::new());
+
+        assert_eq!(queue.start(Chat::Saved(1)).unwrap().ticket, first);
+        assert_eq!(queue.start(Chat::Saved(1)), None);
+        // Other conversations don't wait
+        assert_eq!(queue.start(Chat::Saved(2)).unwrap().ticket, other);
+
+        assert_eq!(queue.waiting(Chat::Saved(1)).len(), 1);
+        queue.finish(Chat::Saved(1));
+        assert_eq!(queue.start(Chat::Saved(1)).unwrap().ticket, second);
+        queue.finish(Chat::Saved(1));
+        assert_eq!(queue.start(Chat::Saved(1)), None);
+    }
+
+    #[test]
+    fn removes_waiting_prompt() {
+        let mut queue = PromptQueue::default();
+        let first = queue.push(Chat::Saved(1), "first".to_owned(), Vec::new());
+        let second = queue.push(Chat::Saved(1), "second".to_owned(), Vec::new());
+        let third = queue.push(Chat::Saved(1), "third".to_owned(), Vec::new());
+
+        queue.start(Chat::Saved(1));
+        assert!(!queue.remove(first));
+        assert!(queue.remove(second));
+        queue.finish(Chat::Saved(1));
+
+        assert_eq!(queue.start(Chat::Saved(1)).unwrap().ticket, third);
+    }
+
+    #[test]
+    fn follows_created_conversation() {
+        let mut queue = PromptQueue::default();
+        let draft = queue.chat(None);
+        queue.push(draft, "first".to_owned(), Vec::new());
+        let second = queue.push(draft, "second".to_owned(), Vec::new());
+
+        // Prompt of another new chat stays with it
+        queue.new_draft();
+        let other = queue.push(queue.chat(None), "other".to_owned(), Vec::new());
+
+        queue.start(draft);
+        queue.created(0, 7);
+        assert_eq!(queue.waiting(draft), Vec::new());
+        assert_eq!(queue.start(Chat::Saved(7)), None);
+
+        queue.finish(Chat::Saved(7));
+        assert_eq!(queue.start(Chat::Saved(7)).unwrap().ticket, second);
+        assert_eq!(queue.waiting(Chat::Draft(1))[0].ticket, other);
+    }
+
+    #[test]
+    fn reserved_conversation_queues_prompts() {
+        let mut queue = PromptQueue::default();
+        assert!(queue.reserve(Chat::Saved(1)));
+        assert!(!queue.reserve(Chat::Saved(1)));
+
+        let ticket = queue.push(Chat::Saved(1), "later".to_owned(), Vec::new());
+        assert_eq!(queue.start(Chat::Saved(1)), None);
+
+        queue.finish(Chat::Saved(1));
+        assert_eq!(queue.start(Chat::Saved(1)).unwrap().ticket, ticket);
+    }
+}
@@ -0,0 +1,129 @@
+use rusqlite::Connection;
+
+/// Applied in order, database keeps amount of applied migrations in `user_version`
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE conversations (
+        id INTEGER PRIMARY KEY,
+        title TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE messages (
+        id INTEGER PRIMARY KEY,
+        conversation_id INTEGER NOT NULL REFERENCES conversations (id) ON DELETE CASCADE,
+        role TEXT NOT NULL,
+        content TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+
+    CREATE INDEX messages_conversation ON messages (conversation_id);",
+    // Full-text index over message text and titles, kept in sync by triggers
+    "CREATE VIRTUAL TABLE messages_fts USING fts5 (
+        content,
+        content = 'messages',
+        content_rowid = 'id',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+
+    CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+        INSERT INTO messages_fts (rowid, content) VALUES (new.id, new.content);
+    END;
+
+    CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+        INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.id, old.content);
+    END;
+
+    CREATE TRIGGER messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+        INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.id, old.content);
+        INSERT INTO messages_fts (rowid, content) VALUES (new.id, new.content);
+    END;
+
+    CREATE VIRTUAL TABLE conversations_fts USING fts5 (
+        title,
+        content = 'conversations',
+        content_rowid = 'id',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+
+    CREATE TRIGGER conversations_fts_insert AFTER INSERT ON conversations BEGIN
+        INSERT INTO conversations_fts (rowid, title) VALUES (new.id, new.title);
+    END;
+
+    CREATE TRIGGER conversations_fts_delete AFTER DELETE ON conversations BEGIN
+        INSERT INTO conversations_fts (conversations_fts, rowid, title) VALUES ('delete', old.id, old.title);
+    END;
+
+    CREATE TRIGGER conversations_fts_update AFTER UPDATE OF title ON conversations BEGIN
+        INSERT INTO conversations_fts (conversations_fts, rowid, title) VALUES ('delete', old.id, old.title);
+        INSERT INTO conversations_fts (rowid, title) VALUES (new.id, new.title);
+    END;
+
+    INSERT INTO messages_fts (messages_fts) VALUES ('rebuild');
+    INSERT INTO conversations_fts (conversations_fts) VALUES ('rebuild');",
+    // Model which wrote assistant message
+    "ALTER TABLE messages ADD COLUMN model TEXT;",
+    // Messages form a tree, alternative replies share a parent. Conversation shows path to its active leaf
+    "ALTER TABLE messages ADD COLUMN parent_id INTEGER REFERENCES messages (id) ON DELETE CASCADE;
+    ALTER TABLE conversations ADD COLUMN active_leaf INTEGER;
+
+    UPDATE messages SET parent_id = (
+        SELECT MAX(previous.id) FROM messages AS previous
+        WHERE previous.conversation_id = messages.conversation_id AND previous.id < messages.id
+    );
+    UPDATE conversations SET active_leaf = (
+        SELECT MAX(id) FROM messages WHERE messages.conversation_id = conversations.id
+    );
+
+    CREATE INDEX messages_parent ON messages (parent_id);",
+    // System prompt and generation parameters of conversation, parameters are JSON
+    "ALTER TABLE conversations ADD COLUMN system_prompt TEXT NOT NULL DEFAULT '';
+    ALTER TABLE conversations ADD COLUMN options TEXT NOT NULL DEFAULT '{}';",
+    // Model answering in conversation, NULL for default one
+    "ALTER TABLE conversations ADD COLUMN model TEXT;",
+    // Context strategy of conversation as JSON, and summaries which replace older messages in requests
+    "ALTER TABLE conversations ADD COLUMN context TEXT NOT NULL DEFAULT '{}';
+
+    CREATE TABLE memories (
+        id INTEGER PRIMARY KEY,
+        conversation_id INTEGER NOT NULL REFERENCES conversations (id) ON DELETE CASCADE,
+        until_id INTEGER NOT NULL REFERENCES messages (id) ON DELETE CASCADE,
+        content TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+
+    CREATE INDEX memories_conversation ON memories (conversation_id);",
+    // Images sent with prompts, thumbnail is PNG shown in chat
+    "CREATE TABLE attachments (
+        id INTEGER PRIMARY KEY,
+        message_id INTEGER NOT NULL REFERENCES messages (id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        data BLOB NOT NULL,
+        thumbnail BLOB NOT NULL
+    );
+
+    CREATE INDEX attachments_message ON attachments (message_id);",
+    // Content hash of imported conversation which has no timestamps of its own, so it is imported once
+    "ALTER TABLE conversations ADD COLUMN import_key TEXT;
+
+    CREATE INDEX conversations_import_key ON conversations (import_key);",
+];
+
+/// Brings database schema up to date
+pub(super) fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+    let applied =
+        connection.pragma_query_value(None, "user_version", |row| row.get::<_, usize>(0))?;
+
+    if applied >= MIGRATIONS.len() {
+        return Ok(());
+    }
+
+    let transaction = connection.transaction()?;
+
+    for migration in &MIGRATIONS[applied..] {
+        transaction.execute_batch(migration)?;
+    }
+    transaction.pragma_update(None, "user_version", MIGRATIONS.len())?;
+
+    transaction.commit()
+}
@@ -0,0 +1,221 @@
+use rusqlite::params;
+
+use crate::core::conversations::{ConversationError, ConversationStore};
+
+/// Most hits returned by one search
+const SEARCH_LIMIT: usize = 50;
+/// Tokens of message text around match shown in snippet
+const SNIPPET_TOKENS: usize = 12;
+/// Control characters never typed by user, used to mark matches in snippets
+const MATCH_START: char = '\u{1}';
+const MATCH_END: char = '\u{2}';
+
+/// Piece of snippet, matched pieces are shown highlighted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    pub text: String,
+    pub matched: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    pub conversation_id: i64,
+    pub conversation_title: String,
+    /// `None` when only title of conversation matched
+    pub message_id: Option<i64>,
+    pub snippet: Vec<Highlight>,
+}
+
+/// Turns user input into FTS5 query. Every word must be present, the last one may be typed partially.
+/// Words are quoted, so FTS5 operators and punctuation in input are matched literally.
+fn fts_query(input: &str) -> Option<String> {
+    let words = input
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+
+    if words.is_empty() {
+        return None;
+    }
+
+    Some(format!("{}*", words.join(" ")))
+}
+
+/// Splits snippet with match markers into highlighted and plain pieces
+fn highlights(snippet: &str) -> Vec<Highlight> {
+    let mut parts = Vec::new();
+    let mut rest = snippet;
+
+    while let Some(start) = rest.find(MATCH_START) {
+        if start > 0 {
+            parts.push(Highlight {
+                text: rest[..start].to_owned(),
+                matched: false,
+            });
+        }
+
+        let matched = &rest[start + MATCH_START.len_utf8()..];
+        let end = matched.find(MATCH_END).unwrap_or(matched.len());
+
+        parts.push(Highlight {
+            text: matched[..end].to_owned(),
+            matched: true,
+        });
+
+        rest = matched
+            .get(end + MATCH_END.len_utf8()..)
+            .unwrap_or_default();
+    }
+
+    if !rest.is_empty() {
+        parts.push(Highlight {
+            text: rest.to_owned(),
+            matched: false,
+        });
+    }
+
+    parts
+}
+
+impl ConversationStore {
+    /// Finds conversations which title or messages contain every word of `input`.
+    /// Title matches come first, then messages ordered by relevance.
+    pub async fn search(&self, input: &str) -> Result<Vec<SearchHit>, ConversationError> {
+        let Some(query) = fts_query(input) else {
+            return Ok(Vec::new());
+        };
+
+        self.call(move |db| {
+            let mut hits = Vec::new();
+
+            let mut titles = db.prepare(
+                "SELECT conversations.id, conversations.title,
+                    highlight(conversations_fts, 0, ?2, ?3)
+                FROM conversations_fts
+                JOIN conversations ON conversations.id = conversations_fts.rowid
+                WHERE conversations_fts MATCH ?1
+                ORDER BY rank LIMIT ?4",
+            )?;
+            let mut rows = titles.query(params![
+                query,
+                MATCH_START.to_string(),
+                MATCH_END.to_string(),
+                SEARCH_LIMIT
+            ])?;
+
+            while let Some(row) = rows.next()? {
+                hits.push(SearchHit {
+                    conversation_id: row.get(0)?,
+                    conversation_title: row.get(1)?,
+                    message_id: None,
+                    snippet: highlights(&row.get::<_, String>(2)?),
+                });
+            }
+
+            let mut messages = db.prepare(
+                "SELECT conversations.id, conversations.title, messages.id,
+                    snippet(messages_fts, 0, ?2, ?3, '…', ?5)
+                FROM messages_fts
+                JOIN messages ON messages.id = messages_fts.rowid
+                JOIN conversations ON conversations.id = messages.conversation_id
+                WHERE messages_fts MATCH ?1
+                ORDER BY rank LIMIT ?4",
+            )?;
+            let mut rows = messages.query(params![
+                query,
+                MATCH_START.to_string(),
+                MATCH_END.to_string(),
+                SEARCH_LIMIT - hits.len(),
+                SNIPPET_TOKENS
+            ])?;
+
+            while let Some(row) = rows.next()? {
+                hits.push(SearchHit {
+                    conversation_id: row.get(0)?,
+                    conversation_title: row.get(1)?,
+                    message_id: Some(row.get(2)?),
+                    snippet: highlights(&row.get::<_, String>(3)?),
+                });
+            }
+
+            Ok(hits)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::conversations::{Role, tests::scratch_store};
+
+    #[test]
+    fn query_quotes_words() {
+        assert_eq!(
+            fts_query("tokio cancel").as_deref(),
+            Some(r#""tokio" "cancel"*"#)
+        );
+        assert_eq!(
+            fts_query(r#"say "hi" OR"#).as_deref(),
+            Some(r#""say" """hi""" "OR"*"#)
+        );
+        assert_eq!(fts_query("  "), None);
+    }
+
+    #[tokio::test]
+    async fn finds_titles_and_messages() {
+        let (_dir, store) = scratch_store();
+
+        let tokio = store
+            .create_conversation("Tokio questions".to_owned())
+            .await
+            .unwrap();
+        let other = store
+            .create_conversation("Translation".to_owned())
+            .await
+            .unwrap();
+
+        let answer = store
+            .add_message(
+                tokio.id,
+                Role::Assistant,
+                "Dropping a future is how cancellation works in tokio".to_owned(),
+            )
+            .await
+            .unwrap();
+        store
+            .add_message(other.id, Role::User, "Translate cancellation".to_owned())
+            .await
+            .unwrap();
+
+        let hits = store.search("tokio cancel").await.unwrap();
+
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].message_id, Some(answer.id));
+        assert!(
+            hits[0]
+                .snippet
+                .iter()
+                .any(|part| part.matched && part.text == "cancellation")
+        );
+
+        let hits = store.search("tokio").await.unwrap();
+        assert_eq!(hits[0].message_id, None);
+        assert_eq!(
+            hits[0].snippet,
+            [
+                Highlight {
+                    text: "Tokio".to_owned(),
+                    matched: true
+                },
+                Highlight {
+                    text: " questions".to_owned(),
+                    matched: false
+                }
+            ]
+        );
+
+        store.delete_conversation(tokio.id).await.unwrap();
+        assert!(store.search("tokio").await.unwrap().is_empty());
+    }
+}
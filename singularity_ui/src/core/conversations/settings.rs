@@ -0,0 +1,122 @@
+use rusqlite::{OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+
+use super::{ConversationError, ConversationStore, context::ContextSettings};
+use crate::core::llm::{MODEL_NAME, options::GenerationOptions};
+
+/// Model, system prompt and parameters used by every request of conversation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversationSettings {
+    /// `None` for default model
+    pub model: Option<String>,
+    /// Empty for none
+    pub system_prompt: String,
+    pub options: GenerationOptions,
+    /// What happens once history nears end of context window
+    pub context: ContextSettings,
+}
+
+impl ConversationSettings {
+    /// Model answering in conversation
+    pub fn model(&self) -> &str {
+        self.model.as_deref().unwrap_or(MODEL_NAME)
+    }
+}
+
+impl ConversationStore {
+    pub async fn settings(
+        &self,
+        conversation_id: i64,
+    ) -> Result<ConversationSettings, ConversationError> {
+        self.call(move |db| {
+            let (model, system_prompt, options, context) = db
+                .query_row(
+                    "SELECT model, system_prompt, options, context FROM conversations WHERE id = ?1",
+                    [conversation_id],
+                    |row| {
+                        Ok((
+                            row.get::<_, Option<String>>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, String>(2)?,
+                            row.get::<_, String>(3)?,
+                        ))
+                    },
+                )
+                .optional()?
+                .ok_or(ConversationError::NotFound(conversation_id))?;
+
+            Ok(ConversationSettings {
+                model,
+                system_prompt,
+                options: serde_json::from_str(&options)?,
+                context: serde_json::from_str(&context)?,
+            })
+        })
+        .await
+    }
+
+    pub async fn set_settings(
+        &self,
+        conversation_id: i64,
+        settings: ConversationSettings,
+    ) -> Result<(), ConversationError> {
+        self.call(move |db| {
+            let options = serde_json::to_string(&settings.options)?;
+            let context = serde_json::to_string(&settings.context)?;
+
+            db.execute(
+                "UPDATE conversations SET model = ?2, system_prompt = ?3, options = ?4, context = ?5
+                WHERE id = ?1",
+                params![
+                    conversation_id,
+                    settings.model,
+                    settings.system_prompt,
+                    options,
+                    context
+                ],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::conversations::{context::ContextStrategy, tests::scratch_store};
+
+    #[tokio::test]
+    async fn keeps_settings_of_conversation() {
+        let (_dir, store) = scratch_store();
+        let conversation = store.create_conversation("Chat".to_owned()).await.unwrap();
+
+        assert_eq!(
+            store.settings(conversation.id).await.unwrap(),
+            ConversationSettings::default()
+        );
+
+        let settings = ConversationSettings {
+            model: Some("llama3.2".to_owned()),
+            system_prompt: "Answer briefly".to_owned(),
+            options: GenerationOptions {
+                temperature: Some(0.3),
+                stop: vec!["###".to_owned()],
+                ..GenerationOptions::default()
+            },
+            context: ContextSettings {
+                strategy: ContextStrategy::Summarize,
+                threshold: 60,
+            },
+        };
+        store
+            .set_settings(conversation.id, settings.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(store.settings(conversation.id).await.unwrap(), settings);
+        assert!(store.settings(conversation.id + 1).await.is_err());
+    }
+}
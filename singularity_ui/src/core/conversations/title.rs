@@ -0,0 +1,123 @@
+//! Titles written by model after first exchange of conversation
+
+use rusqlite::params;
+
+use super::{ConversationError, ConversationStore, title_from_prompt};
+use crate::core::llm::options::GenerationOptions;
+
+/// Characters of each message shown to model, enough to tell topic
+const EXCERPT_LENGTH: usize = 1000;
+
+pub const TITLE_SYSTEM_PROMPT: &str = "You name chat conversations. Reply with a title of at most \
+six words describing the topic of the conversation. Use the language of the user. No quotes, no \
+punctuation at the end, no explanations, only the title.";
+
+/// Prompt asking for title of conversation which starts with `prompt` and `reply`
+pub fn title_prompt(prompt: &str, reply: &str) -> String {
+    let excerpt = |text: &str| text.trim().chars().take(EXCERPT_LENGTH).collect::<String>();
+
+    format!(
+        "User: {}\n\nAssistant: {}\n\nTitle:",
+        excerpt(prompt),
+        excerpt(reply)
+    )
+}
+
+/// Options keeping title short and stable, the rest is left to model defaults
+pub fn title_options() -> GenerationOptions {
+    GenerationOptions {
+        temperature: Some(0.2),
+        num_predict: Some(24),
+        ..GenerationOptions::default()
+    }
+}
+
+/// Title from answer of model, `None` if nothing usable is left
+pub fn clean_title(answer: &str) -> Option<String> {
+    let line = answer.trim().lines().find(|line| !line.trim().is_empty())?;
+
+    let line = line.trim();
+    let line = match line.get(..6) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("title:") => &line[6..],
+        _ => line,
+    };
+    let line = line
+        .trim_matches(|c: char| {
+            matches!(c, '"' | '\'' | '*' | '#' | '`' | '“' | '”') || c.is_whitespace()
+        })
+        .trim_end_matches(['.', '!', ':', ';', ','])
+        .trim();
+
+    (!line.is_empty()).then(|| title_from_prompt(line))
+}
+
+impl ConversationStore {
+    /// Replaces title set when conversation started, kept if it was renamed meanwhile
+    pub async fn set_generated_title(
+        &self,
+        id: i64,
+        initial: String,
+        title: String,
+    ) -> Result<bool, ConversationError> {
+        self.call(move |db| {
+            let changed = db.execute(
+                "UPDATE conversations SET title = ?3 WHERE id = ?1 AND title = ?2",
+                params![id, initial, title],
+            )?;
+
+            Ok(changed > 0)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::conversations::{TITLE_LENGTH, tests::scratch_store};
+
+    #[test]
+    fn cleans_answer_of_model() {
+        assert_eq!(
+            clean_title("\n  Title: \"Rust lifetimes explained.\"\nSure!"),
+            Some("Rust lifetimes explained".to_owned())
+        );
+        assert_eq!(
+            clean_title("**Sourdough starter tips**"),
+            Some("Sourdough starter tips".to_owned())
+        );
+        assert_eq!(clean_title(" \"\" "), None);
+        assert!(clean_title(&"word ".repeat(20)).unwrap().chars().count() <= TITLE_LENGTH + 1);
+    }
+
+    #[tokio::test]
+    async fn keeps_manual_rename() {
+        let (_dir, store) = scratch_store();
+        let conversation = store.create_conversation("Hello".to_owned()).await.unwrap();
+
+        store
+            .rename_conversation(conversation.id, "Mine".to_owned())
+            .await
+            .unwrap();
+        let replaced = store
+            .set_generated_title(conversation.id, "Hello".to_owned(), "Greeting".to_owned())
+            .await
+            .unwrap();
+        assert!(!replaced);
+
+        let replaced = store
+            .set_generated_title(conversation.id, "Mine".to_owned(), "Greeting".to_owned())
+            .await
+            .unwrap();
+        assert!(replaced);
+        assert_eq!(
+            store
+                .conversation(conversation.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .title,
+            "Greeting"
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use crate::{core::llm::store::store_files, error::BetterIoError};
+
+/// Subdirectory of bundle with ollama model store, `blobs` and `manifests` inside
+pub const BUNDLE_MODELS_DIR: &str = "models";
+
+/// Copies model store from `<bundle>/models` into `models_dir`.
+/// Files which already exist with the same size are skipped. Returns amount of copied files.
+///
+/// Blocking, run it on blocking thread.
+pub fn import_bundle_models(bundle: &Path, models_dir: &Path) -> Result<usize, BetterIoError> {
+    let source = bundle.join(BUNDLE_MODELS_DIR);
+
+    let files = store_files(&source)?;
+
+    let mut copied = 0;
+
+    for file in files {
+        let relative = file
+            .strip_prefix(&source)
+            .expect("collected from inside of source");
+        let target = models_dir.join(relative);
+
+        let size = std::fs::metadata(&file)
+            .map_err(|e| BetterIoError::new(&file, "reading bundled model file", e))?
+            .len();
+
+        if std::fs::metadata(&target).is_ok_and(|meta| meta.len() == size) {
+            continue;
+        }
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| BetterIoError::new(parent, "creating model store dir", e))?;
+        }
+
+        // Copy under temporary name, so interrupted import never leaves truncated blob
+        let mut partial = target.clone().into_os_string();
+        partial.push(".part");
+
+        std::fs::copy(&file, &partial)
+            .map_err(|e| BetterIoError::new(&file, "copying bundled model file", e))?;
+        std::fs::rename(&partial, &target)
+            .map_err(|e| BetterIoError::new(&target, "moving bundled model file", e))?;
+
+        copied += 1;
+    }
+
+    tracing::info!(
+        "Imported {copied} model files from bundle {}",
+        bundle.display()
+    );
+
+    Ok(copied)
+}
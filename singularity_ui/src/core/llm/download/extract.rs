@@ -0,0 +1,668 @@
+// Nothing is unpacked for apple silicon as ollama is shipped as disk image there
+#![cfg_attr(all(target_os = "macos", target_arch = "aarch64"), allow(dead_code))]
+
+use std::{
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{core::llm::download::ArchiveDownloadError, error::BetterIoError};
+
+/// Upper bound for everything a single archive may unpack. Ollama with the GPU addons is
+/// a few GB, so anything above this is either broken or hostile.
+pub const MAX_UNPACKED_SIZE: u64 = 32 * 1024 * 1024 * 1024;
+
+/// Entries which must stay executable after unpack even if the archive lost the mode bits
+#[cfg(target_family = "unix")]
+const EXECUTABLE_ENTRIES: &[&str] = &["bin/ollama", "ollama"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnsafeEntryReason {
+    AbsolutePath,
+    ParentTraversal,
+    LinkEscape,
+    ThroughSymlink,
+    UnsupportedType,
+}
+
+impl std::fmt::Display for UnsafeEntryReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            Self::AbsolutePath => "absolute paths are not allowed",
+            Self::ParentTraversal => "path points outside of target dir",
+            Self::LinkEscape => "link target points outside of target dir",
+            Self::ThroughSymlink => "path goes through a symlink",
+            Self::UnsupportedType => "unsupported entry type",
+        };
+
+        f.write_str(reason)
+    }
+}
+
+/// Turns path from archive into relative path which is guaranteed to stay inside of target dir.
+/// `.` components are dropped, everything else which could escape is refused.
+fn sanitize_entry_path(path: &Path) -> Result<PathBuf, UnsafeEntryReason> {
+    let mut sanitized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => sanitized.push(part),
+            Component::CurDir => (),
+            Component::ParentDir => return Err(UnsafeEntryReason::ParentTraversal),
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(UnsafeEntryReason::AbsolutePath);
+            }
+        }
+    }
+
+    Ok(sanitized)
+}
+
+/// Checks that symlink placed at `entry` (relative to target dir) and pointing to `link` resolves inside target dir.
+/// Resolution is purely lexical, so chains of links are checked again by [`check_resolved_symlink`] once unpack is done.
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+fn check_symlink_target(entry: &Path, link: &Path) -> Result<(), UnsafeEntryReason> {
+    let mut depth = entry
+        .parent()
+        .map(|this| this.components().count())
+        .unwrap_or(0);
+
+    for component in link.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => (),
+            Component::ParentDir => {
+                depth = depth.checked_sub(1).ok_or(UnsafeEntryReason::LinkEscape)?;
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(UnsafeEntryReason::LinkEscape);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Upper bound for links followed while resolving one path, same as linux uses
+const MAX_LINK_HOPS: usize = 40;
+
+/// Resolves symlink at `entry` (relative to target dir) through every link already unpacked, the way
+/// the OS does once ollama is started. Catches chains like `sub/d -> ..` plus `e -> sub/d/..`,
+/// where each link stays inside target dir on its own.
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+fn check_resolved_symlink(target_dir: &Path, entry: &Path) -> Result<(), ArchiveDownloadError> {
+    let escape = || ArchiveDownloadError::UnsafeEntry {
+        entry: entry.to_path_buf(),
+        reason: UnsafeEntryReason::LinkEscape,
+    };
+
+    let mut pending: std::collections::VecDeque<_> = entry
+        .components()
+        .map(|component| component.as_os_str().to_os_string())
+        .collect();
+    let mut resolved = PathBuf::new();
+    let mut hops = 0;
+
+    while let Some(part) = pending.pop_front() {
+        match Path::new(&part).components().next() {
+            Some(Component::Normal(_)) => (),
+            Some(Component::ParentDir) => {
+                if !resolved.pop() {
+                    return Err(escape());
+                }
+
+                continue;
+            }
+            Some(Component::CurDir) | None => continue,
+            Some(Component::RootDir | Component::Prefix(_)) => return Err(escape()),
+        }
+
+        let location = target_dir.join(&resolved).join(&part);
+
+        match std::fs::symlink_metadata(&location) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                hops += 1;
+
+                if hops > MAX_LINK_HOPS {
+                    return Err(escape());
+                }
+
+                let link = std::fs::read_link(&location)
+                    .map_err(|e| BetterIoError::new(&location, "reading unpacked symlink", e))?;
+
+                // Link is relative to its own dir, which is what `resolved` holds at this point
+                for component in link.components().rev() {
+                    pending.push_front(component.as_os_str().to_os_string());
+                }
+            }
+            Ok(_) => resolved.push(&part),
+            // Rest of the path doesn't exist yet, so there are no more links to follow
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => resolved.push(&part),
+            Err(error) => {
+                return Err(BetterIoError::new(location, "checking unpacked path", error).into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Refuses to write through any symlink which is already present in target dir.
+/// Otherwise an archive could first plant a valid link and then use it as a directory to escape.
+fn check_no_symlink_in_path(
+    target_dir: &Path,
+    relative: &Path,
+) -> Result<(), ArchiveDownloadError> {
+    let mut current = target_dir.to_path_buf();
+
+    for component in relative.components() {
+        current.push(component);
+
+        match std::fs::symlink_metadata(&current) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(ArchiveDownloadError::UnsafeEntry {
+                    entry: relative.to_path_buf(),
+                    reason: UnsafeEntryReason::ThroughSymlink,
+                });
+            }
+            Ok(_) => (),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+            Err(error) => {
+                return Err(BetterIoError::new(current, "checking unpacked path", error).into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Keeps track of unpacked bytes and refuses to go above the limit
+#[derive(Debug)]
+struct SizeBudget {
+    limit: u64,
+    used: u64,
+}
+
+impl SizeBudget {
+    fn new(limit: u64) -> Self {
+        Self { limit, used: 0 }
+    }
+
+    fn remaining(&self) -> u64 {
+        self.limit - self.used
+    }
+
+    fn take(&mut self, size: u64) -> Result<(), ArchiveDownloadError> {
+        match self.used.checked_add(size) {
+            Some(used) if used <= self.limit => {
+                self.used = used;
+                Ok(())
+            }
+            _ => Err(ArchiveDownloadError::SizeLimit(self.limit)),
+        }
+    }
+}
+
+fn create_parent_dirs(target_dir: &Path, relative: &Path) -> Result<PathBuf, ArchiveDownloadError> {
+    check_no_symlink_in_path(target_dir, relative)?;
+
+    let location = target_dir.join(relative);
+
+    if let Some(parent) = location.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| BetterIoError::new(parent, "creating dir for archive entry", e))?;
+    }
+
+    Ok(location)
+}
+
+/// Copies content of single entry into new file while enforcing size budget.
+/// Header size is not trusted, so reader is capped by remaining budget.
+fn write_entry_file(
+    reader: &mut impl Read,
+    location: &Path,
+    budget: &mut SizeBudget,
+) -> Result<(), ArchiveDownloadError> {
+    // Replace instead of truncate so we never write into a file which is hard linked from somewhere else
+    if std::fs::symlink_metadata(location).is_ok() {
+        std::fs::remove_file(location)
+            .map_err(|e| BetterIoError::new(location, "removing old file before unpack", e))?;
+    }
+
+    let mut file = std::fs::File::create(location)
+        .map_err(|e| BetterIoError::new(location, "creating unpacked file", e))?;
+
+    let written = std::io::copy(&mut reader.take(budget.remaining() + 1), &mut file)
+        .map_err(|e| BetterIoError::new(location, "writing unpacked file", e))?;
+
+    budget.take(written)
+}
+
+#[cfg(target_family = "unix")]
+fn set_unpacked_mode(location: &Path, relative: &Path, mode: u32) -> Result<(), BetterIoError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Never keep setuid/setgid/sticky bits from downloaded archive
+    let mut mode = mode & 0o777;
+
+    if EXECUTABLE_ENTRIES
+        .iter()
+        .any(|this| relative == Path::new(this))
+    {
+        mode |= 0o755;
+    }
+
+    std::fs::set_permissions(location, std::fs::Permissions::from_mode(mode))
+        .map_err(|e| BetterIoError::new(location, "setting permissions of unpacked file", e))
+}
+
+/// Unpacks tar stream into `target_dir` validating every entry on the way.
+/// Returns amount of unpacked bytes.
+#[cfg(any(target_os = "linux", all(target_os = "macos", target_arch = "x86_64")))]
+pub fn unpack_tar(
+    reader: impl Read,
+    target_dir: &Path,
+    limit: u64,
+) -> Result<u64, ArchiveDownloadError> {
+    use tar::EntryType;
+
+    std::fs::create_dir_all(target_dir)
+        .map_err(|e| BetterIoError::new(target_dir, "creating unpack dir", e))?;
+
+    let mut archive = tar::Archive::new(reader);
+    let mut budget = SizeBudget::new(limit);
+    let mut symlinks = Vec::new();
+
+    let entries = archive
+        .entries()
+        .map_err(|e| BetterIoError::new(target_dir, "reading archive entries", e))?;
+
+    for entry in entries {
+        let mut entry =
+            entry.map_err(|e| BetterIoError::new(target_dir, "reading archive entry", e))?;
+
+        let raw_path = entry
+            .path()
+            .map_err(|e| BetterIoError::new(target_dir, "reading archive entry path", e))?
+            .into_owned();
+
+        let unsafe_entry = |reason| ArchiveDownloadError::UnsafeEntry {
+            entry: raw_path.clone(),
+            reason,
+        };
+
+        let relative = sanitize_entry_path(&raw_path).map_err(unsafe_entry)?;
+
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+
+        match entry.header().entry_type() {
+            EntryType::Directory => {
+                check_no_symlink_in_path(target_dir, &relative)?;
+
+                let location = target_dir.join(&relative);
+                std::fs::create_dir_all(&location)
+                    .map_err(|e| BetterIoError::new(&location, "creating unpacked dir", e))?;
+            }
+            EntryType::Regular | EntryType::Continuous => {
+                let location = create_parent_dirs(target_dir, &relative)?;
+                write_entry_file(&mut entry, &location, &mut budget)?;
+
+                let mode = entry.header().mode().unwrap_or(0o644);
+                set_unpacked_mode(&location, &relative, mode)?;
+            }
+            EntryType::Symlink => {
+                let link = entry
+                    .link_name()
+                    .map_err(|e| BetterIoError::new(target_dir, "reading archive link name", e))?
+                    .ok_or(unsafe_entry(UnsafeEntryReason::UnsupportedType))?
+                    .into_owned();
+
+                check_symlink_target(&relative, &link).map_err(unsafe_entry)?;
+
+                let location = create_parent_dirs(target_dir, &relative)?;
+
+                if std::fs::symlink_metadata(&location).is_ok() {
+                    std::fs::remove_file(&location).map_err(|e| {
+                        BetterIoError::new(&location, "removing old file before unpack", e)
+                    })?;
+                }
+
+                std::os::unix::fs::symlink(&link, &location)
+                    .map_err(|e| BetterIoError::new(&location, "creating unpacked symlink", e))?;
+
+                symlinks.push(relative);
+            }
+            EntryType::Link => {
+                let link = entry
+                    .link_name()
+                    .map_err(|e| BetterIoError::new(target_dir, "reading archive link name", e))?
+                    .ok_or(unsafe_entry(UnsafeEntryReason::UnsupportedType))?
+                    .into_owned();
+
+                // Hard link targets are relative to archive root, not to the entry
+                let source = sanitize_entry_path(&link)
+                    .map_err(|_| unsafe_entry(UnsafeEntryReason::LinkEscape))?;
+                check_no_symlink_in_path(target_dir, &source)?;
+
+                let location = create_parent_dirs(target_dir, &relative)?;
+                let source = target_dir.join(source);
+
+                std::fs::hard_link(&source, &location)
+                    .map_err(|e| BetterIoError::new(&location, "creating unpacked hard link", e))?;
+            }
+            // Handled by tar itself while iterating
+            EntryType::XGlobalHeader
+            | EntryType::XHeader
+            | EntryType::GNULongName
+            | EntryType::GNULongLink => (),
+            other => {
+                tracing::warn!(
+                    "Skipping archive entry {} with unsupported type {other:?}",
+                    raw_path.display()
+                );
+            }
+        }
+    }
+
+    // Later entries may turn a link which was safe on its own into a part of escaping chain
+    for link in &symlinks {
+        check_resolved_symlink(target_dir, link)?;
+    }
+
+    Ok(budget.used)
+}
+
+/// Unpacks `.zip` archive from disk validating every entry. Symlinks are refused as ollama never ships them for windows.
+#[cfg(target_os = "windows")]
+pub fn unpack_archive(zip_location: &Path, target_dir: &Path) -> Result<(), ArchiveDownloadError> {
+    let file = std::fs::File::open(zip_location)
+        .map_err(|e| BetterIoError::new(zip_location, "opening archive descriptor", e))?;
+
+    tracing::info!("Starting unpacking of archive - {}", zip_location.display());
+
+    std::fs::create_dir_all(target_dir)
+        .map_err(|e| BetterIoError::new(target_dir, "creating unpack dir", e))?;
+
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut budget = SizeBudget::new(MAX_UNPACKED_SIZE);
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+
+        let raw_path = PathBuf::from(entry.name());
+
+        let unsafe_entry = |reason| ArchiveDownloadError::UnsafeEntry {
+            entry: raw_path.clone(),
+            reason,
+        };
+
+        let relative = sanitize_entry_path(&raw_path).map_err(unsafe_entry)?;
+
+        // Zip stores `\` separators as is, so double check with parser of zip itself
+        if entry.enclosed_name().is_none() {
+            return Err(unsafe_entry(UnsafeEntryReason::ParentTraversal));
+        }
+
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+
+        if entry.is_symlink() {
+            return Err(unsafe_entry(UnsafeEntryReason::UnsupportedType));
+        }
+
+        if entry.is_dir() {
+            check_no_symlink_in_path(target_dir, &relative)?;
+
+            let location = target_dir.join(&relative);
+            std::fs::create_dir_all(&location)
+                .map_err(|e| BetterIoError::new(&location, "creating unpacked dir", e))?;
+
+            continue;
+        }
+
+        let location = create_parent_dirs(target_dir, &relative)?;
+        write_entry_file(&mut entry, &location, &mut budget)?;
+    }
+
+    tracing::info!("Finished unpacking of archive - {}", zip_location.display());
+
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    /// Writes header with raw name, bypassing path validation of [`tar::Header::set_path`]
+    fn raw_header(name: &str, kind: tar::EntryType, size: u64, mode: u32) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+
+        let raw_name = &mut header.as_old_mut().name;
+        raw_name[..name.len()].copy_from_slice(name.as_bytes());
+
+        header.set_entry_type(kind);
+        header.set_size(size);
+        header.set_mode(mode);
+        header.set_cksum();
+
+        header
+    }
+
+    fn raw_link_header(name: &str, kind: tar::EntryType, link: &str) -> tar::Header {
+        let mut header = raw_header(name, kind, 0, 0o777);
+
+        let raw_link = &mut header.as_old_mut().linkname;
+        raw_link[..link.len()].copy_from_slice(link.as_bytes());
+        header.set_cksum();
+
+        header
+    }
+
+    fn archive(entries: &[(tar::Header, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        for (header, data) in entries {
+            builder.append(header, *data).unwrap();
+        }
+
+        builder.into_inner().unwrap()
+    }
+
+    fn file(name: &str, data: &'static [u8]) -> (tar::Header, &'static [u8]) {
+        (
+            raw_header(name, tar::EntryType::Regular, data.len() as u64, 0o644),
+            data,
+        )
+    }
+
+    fn unsafe_reason(result: Result<u64, ArchiveDownloadError>) -> UnsafeEntryReason {
+        match result {
+            Err(ArchiveDownloadError::UnsafeEntry { reason, .. }) => reason,
+            other => panic!("expected unsafe entry error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unpacks_regular_archive_and_keeps_executable() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path();
+        let target = root.join("ollama");
+
+        let data = archive(&[
+            (raw_header("bin/", tar::EntryType::Directory, 0, 0o755), &[]),
+            (
+                raw_header("bin/ollama", tar::EntryType::Regular, 4, 0o644),
+                b"\x7fELF",
+            ),
+            file("./lib/ollama/libggml.so", b"lib"),
+            (
+                raw_link_header(
+                    "lib/ollama/libggml.so.0",
+                    tar::EntryType::Symlink,
+                    "libggml.so",
+                ),
+                &[],
+            ),
+        ]);
+
+        let unpacked = unpack_tar(data.as_slice(), &target, MAX_UNPACKED_SIZE).unwrap();
+        assert_eq!(unpacked, 7);
+
+        let mode = std::fs::metadata(target.join("bin/ollama"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+
+        assert_eq!(
+            std::fs::read(target.join("lib/ollama/libggml.so.0")).unwrap(),
+            b"lib"
+        );
+    }
+
+    #[test]
+    fn refuses_parent_traversal() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path();
+        let target = root.join("ollama");
+
+        let data = archive(&[file("../../.bashrc", b"evil")]);
+
+        let reason = unsafe_reason(unpack_tar(data.as_slice(), &target, MAX_UNPACKED_SIZE));
+        assert_eq!(reason, UnsafeEntryReason::ParentTraversal);
+        assert!(!root.join(".bashrc").exists());
+    }
+
+    #[test]
+    fn refuses_absolute_path() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path();
+        let target = root.join("ollama");
+        let escape = root.join("absolute.txt");
+
+        let data = archive(&[file(escape.to_str().unwrap(), b"evil")]);
+
+        let reason = unsafe_reason(unpack_tar(data.as_slice(), &target, MAX_UNPACKED_SIZE));
+        assert_eq!(reason, UnsafeEntryReason::AbsolutePath);
+        assert!(!escape.exists());
+    }
+
+    #[test]
+    fn refuses_symlink_escape() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path();
+        let target = root.join("ollama");
+
+        for link in ["../../outside", "/etc/passwd", "lib/../../../outside"] {
+            let data = archive(&[(
+                raw_link_header("bin/link", tar::EntryType::Symlink, link),
+                &[],
+            )]);
+
+            let reason = unsafe_reason(unpack_tar(data.as_slice(), &target, MAX_UNPACKED_SIZE));
+            assert_eq!(reason, UnsafeEntryReason::LinkEscape, "link {link}");
+        }
+    }
+
+    #[test]
+    fn refuses_symlink_chain_escape() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path();
+        let target = root.join("ollama");
+
+        // Each link stays inside target dir on its own, together `e` resolves to its parent
+        let to_root = || {
+            (
+                raw_link_header("sub/d", tar::EntryType::Symlink, ".."),
+                &[][..],
+            )
+        };
+        let chain = || {
+            (
+                raw_link_header("e", tar::EntryType::Symlink, "sub/d/.."),
+                &[][..],
+            )
+        };
+
+        for entries in [[to_root(), chain()], [chain(), to_root()]] {
+            let data = archive(&entries);
+
+            let reason = unsafe_reason(unpack_tar(data.as_slice(), &target, MAX_UNPACKED_SIZE));
+            assert_eq!(reason, UnsafeEntryReason::LinkEscape);
+
+            std::fs::remove_dir_all(&target).unwrap();
+        }
+    }
+
+    #[test]
+    fn refuses_hard_link_escape() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path();
+        let target = root.join("ollama");
+
+        let data = archive(&[(
+            raw_link_header("bin/link", tar::EntryType::Link, "../secret"),
+            &[],
+        )]);
+
+        let reason = unsafe_reason(unpack_tar(data.as_slice(), &target, MAX_UNPACKED_SIZE));
+        assert_eq!(reason, UnsafeEntryReason::LinkEscape);
+    }
+
+    #[test]
+    fn refuses_write_through_planted_symlink() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path();
+        let target = root.join("ollama");
+        std::fs::create_dir_all(root.join("outside")).unwrap();
+
+        // Link itself is valid, but would be used as a dir to escape on next entry
+        std::fs::create_dir_all(&target).unwrap();
+        std::os::unix::fs::symlink(root.join("outside"), target.join("lib")).unwrap();
+
+        let data = archive(&[file("lib/evil.so", b"evil")]);
+
+        let reason = unsafe_reason(unpack_tar(data.as_slice(), &target, MAX_UNPACKED_SIZE));
+        assert_eq!(reason, UnsafeEntryReason::ThroughSymlink);
+        assert!(!root.join("outside/evil.so").exists());
+    }
+
+    #[test]
+    fn refuses_archive_above_size_limit() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path();
+        let target = root.join("ollama");
+
+        let data = archive(&[file("a", b"0123456789"), file("b", b"0123456789")]);
+
+        match unpack_tar(data.as_slice(), &target, 15) {
+            Err(ArchiveDownloadError::SizeLimit(15)) => (),
+            other => panic!("expected size limit error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn strips_setuid_bits() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path();
+        let target = root.join("ollama");
+
+        let data = archive(&[(
+            raw_header("tool", tar::EntryType::Regular, 2, 0o4755),
+            b"ok",
+        )]);
+
+        unpack_tar(data.as_slice(), &target, MAX_UNPACKED_SIZE).unwrap();
+
+        let mode = std::fs::metadata(target.join("tool"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o7777, 0o755);
+    }
+}
@@ -1,36 +1,94 @@
 use std::path::{Path, PathBuf};
 
-use crate::core::llm::download::ArchiveDownloadError;
+use crate::core::llm::{
+    download::{
+        ArchiveDownloadError, AssetSource, DownloadOptions, RuntimeArchive,
+        stream::fetch_and_unpack,
+    },
+    utils::GpuInventory,
+};
 
 #[cfg(target_arch = "aarch64")]
-const OLLAMA_LINK: &str = "https://ollama.com/download/ollama-linux-arm64.tgz";
+pub const OLLAMA_ASSET: &str = "ollama-linux-arm64.tgz";
 #[cfg(target_arch = "x86_64")]
-const OLLAMA_LINK: &str = "https://ollama.com/download/ollama-linux-amd64.tgz";
+pub const OLLAMA_ASSET: &str = "ollama-linux-amd64.tgz";
 #[cfg(target_arch = "x86_64")]
-const AMD_GPU_ADDON: &str = "https://ollama.com/download/ollama-linux-amd64-rocm.tgz";
+const AMD_GPU_ADDON_ASSET: &str = "ollama-linux-amd64-rocm.tgz";
 
 const OLLAMA_DOWNLOAD_FILENAME: &str = "ollama.tgz";
+#[cfg(target_arch = "x86_64")]
 const OLLAMA_ROCM_DOWNLOAD_FILENAME: &str = "ollama_rocm.tgz";
 
+/// Archives which [`ollama_download`] fetches on this machine
+pub(super) async fn runtime_archives() -> Vec<RuntimeArchive> {
+    let mut archives = vec![RuntimeArchive {
+        asset: OLLAMA_ASSET,
+        cache_name: OLLAMA_DOWNLOAD_FILENAME,
+        required: true,
+    }];
+
+    #[cfg(target_arch = "x86_64")]
+    if GpuInventory::detect().await.runtime_variant()
+        == crate::core::llm::utils::RuntimeVariant::Rocm
+    {
+        archives.push(RuntimeArchive {
+            asset: AMD_GPU_ADDON_ASSET,
+            cache_name: OLLAMA_ROCM_DOWNLOAD_FILENAME,
+            required: false,
+        });
+    }
+
+    archives
+}
+
 pub async fn ollama_download(
     cache_dir: impl AsRef<Path>,
     target_dir: impl AsRef<Path>,
+    source: &AssetSource,
+    options: &DownloadOptions,
 ) -> Result<PathBuf, ArchiveDownloadError> {
     let client = reqwest::Client::new();
 
-    let rocm = !crate::core::llm::utils::is_nvidia().await;
+    // CUDA libraries are part of base archive, only ROCm needs an addon
+    let variant = GpuInventory::detect().await.runtime_variant();
+    tracing::info!("Selected ollama runtime variant: {variant:?}");
 
     let cache_dir = cache_dir.as_ref();
 
     let ollama_location = cache_dir.join(OLLAMA_DOWNLOAD_FILENAME);
-    super::download_file(&client, OLLAMA_LINK, &ollama_location).await?;
-    super::unpack_archive(&ollama_location, target_dir.as_ref())?;
+    fetch_and_unpack(
+        &client,
+        &source.locate(OLLAMA_ASSET)?,
+        &ollama_location,
+        target_dir.as_ref(),
+        OLLAMA_DOWNLOAD_FILENAME,
+        options,
+    )
+    .await?;
 
-    if rocm {
-        let ollama_rocm_location = cache_dir.join(OLLAMA_ROCM_DOWNLOAD_FILENAME);
+    #[cfg(target_arch = "x86_64")]
+    if variant == crate::core::llm::utils::RuntimeVariant::Rocm {
+        match source.locate(AMD_GPU_ADDON_ASSET) {
+            Ok(location) => {
+                let ollama_rocm_location = cache_dir.join(OLLAMA_ROCM_DOWNLOAD_FILENAME);
 
-        super::download_file(&client, AMD_GPU_ADDON, &ollama_rocm_location).await?;
-        super::unpack_archive(&ollama_rocm_location, target_dir.as_ref())?;
+                fetch_and_unpack(
+                    &client,
+                    &location,
+                    &ollama_rocm_location,
+                    target_dir.as_ref(),
+                    OLLAMA_ROCM_DOWNLOAD_FILENAME,
+                    options,
+                )
+                .await?;
+            }
+            Err(ArchiveDownloadError::MissingAsset(asset)) => {
+                tracing::warn!(
+                    "{asset} is not provided by selected source. Installing without ROCm"
+                );
+            }
+            Err(e) => return Err(e),
+        }
     }
 
     Ok(ollama_location)
@@ -1,37 +1,75 @@
 use std::path::{Path, PathBuf};
 
-use crate::llm::download::ArchiveDownloadError;
+#[cfg(target_arch = "aarch64")]
+use crate::core::llm::download::AssetLocation;
+use crate::core::llm::download::{
+    ArchiveDownloadError, AssetSource, DownloadOptions, RuntimeArchive,
+};
 
 #[cfg(target_arch = "aarch64")]
-const OLLAMA_LINK: &str = "https://ollama.com/download/Ollama.dmg";
+pub const OLLAMA_ASSET: &str = "Ollama.dmg";
 #[cfg(target_arch = "x86_64")]
-const OLLAMA_LINK: &str = "https://ollama.com/download/ollama-darwin.tgz";
+pub const OLLAMA_ASSET: &str = "ollama-darwin.tgz";
 
 #[cfg(target_arch = "aarch64")]
 const OLLAMA_DOWNLOAD_FILENAME: &str = "ollama.dmg";
 #[cfg(target_arch = "x86_64")]
 const OLLAMA_DOWNLOAD_FILENAME: &str = "ollama.tgz";
 
+/// Archives which [`ollama_download`] fetches on this machine
+pub(super) async fn runtime_archives() -> Vec<RuntimeArchive> {
+    vec![RuntimeArchive {
+        asset: OLLAMA_ASSET,
+        cache_name: OLLAMA_DOWNLOAD_FILENAME,
+        required: true,
+    }]
+}
+
 pub async fn ollama_download(
     cache_dir: impl AsRef<Path>,
     _target_dir: impl AsRef<Path>,
+    source: &AssetSource,
+    options: &DownloadOptions,
 ) -> Result<PathBuf, ArchiveDownloadError> {
     let client = reqwest::Client::new();
 
     let cache_dir = cache_dir.as_ref();
 
     let ollama_location = cache_dir.join(OLLAMA_DOWNLOAD_FILENAME);
-    super::download_file(&client, OLLAMA_LINK, &ollama_location).await?;
 
     #[cfg(target_arch = "x86_64")]
     {
         const TAR_UNPACK_DIR: &str = "ollama";
 
         let unpack_dir = _target_dir.as_ref().join(TAR_UNPACK_DIR);
-        super::unpack_archive(&ollama_location, &unpack_dir)?;
+        super::stream::fetch_and_unpack(
+            &client,
+            &source.locate(OLLAMA_ASSET)?,
+            &ollama_location,
+            &unpack_dir,
+            OLLAMA_DOWNLOAD_FILENAME,
+            options,
+        )
+        .await?;
 
         return Ok(unpack_dir.join("ollama"));
     }
 
-    Ok(ollama_location)
+    // Disk image is mounted by installer, so it is always kept
+    #[cfg(target_arch = "aarch64")]
+    match source.locate(OLLAMA_ASSET)? {
+        AssetLocation::Remote(url) => {
+            super::download_file(
+                &client,
+                &url,
+                &ollama_location,
+                OLLAMA_DOWNLOAD_FILENAME,
+                options,
+            )
+            .await?;
+
+            Ok(ollama_location)
+        }
+        AssetLocation::Local(path) => Ok(path),
+    }
 }
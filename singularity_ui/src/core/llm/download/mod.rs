@@ -1,12 +1,20 @@
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use futures_util::StreamExt;
 use tokio::io::AsyncWriteExt;
 
+mod bundle;
+mod extract;
 #[cfg(target_os = "linux")]
 mod linux;
 #[cfg(target_os = "macos")]
 mod macos;
+#[cfg(any(target_os = "linux", all(target_os = "macos", target_arch = "x86_64")))]
+mod stream;
 #[cfg(target_os = "windows")]
 mod windows;
 
@@ -17,6 +25,11 @@ pub use macos::*;
 #[cfg(target_os = "windows")]
 pub use windows::*;
 
+pub use bundle::import_bundle_models;
+pub use extract::UnsafeEntryReason;
+#[cfg(target_os = "windows")]
+use extract::unpack_archive;
+
 use crate::error::BetterIoError;
 
 #[derive(Debug, thiserror::Error)]
@@ -27,26 +40,152 @@ pub enum ArchiveDownloadError {
     Network(#[from] reqwest::Error),
     #[error("Network error. Failed to request file")]
     FailedRequest,
+    #[error("Selected source does not provide {0} archive")]
+    MissingAsset(&'static str),
+    #[error("Refused to unpack archive entry: {}. Reason: {reason}", entry.display())]
+    UnsafeEntry {
+        entry: std::path::PathBuf,
+        reason: UnsafeEntryReason,
+    },
+    #[error("Archive unpacks to more than {0} bytes")]
+    SizeLimit(u64),
+    #[error("Unpacking task failed. Reason: {0}")]
+    Task(#[from] tokio::task::JoinError),
     #[cfg(target_os = "windows")]
     #[error(transparent)]
     Zip(#[from] ::zip::result::ZipError),
 }
 
-async fn download_file(
-    client: &reqwest::Client,
-    url: &str,
-    location: &Path,
-) -> Result<(), ArchiveDownloadError> {
-    // Send HEAD request to get file size
+/// Where ollama publishes latest archives
+pub const DEFAULT_DOWNLOAD_BASE: &str = "https://ollama.com/download";
+
+/// Resolves archive names, as published by ollama, to their location
+#[derive(Debug, Clone)]
+pub enum AssetSource {
+    /// `<base>/<archive name>`. Either ollama.com or a mirror with the same layout
+    BaseUrl(String),
+    /// Explicit urls of single release, keyed by archive name
+    Release(HashMap<String, String>),
+    /// Runtime archive picked by user. Provides only base archive, without GPU addons
+    Archive(PathBuf),
+    /// Directory with archives named as published by ollama and optional `models` store
+    Bundle(PathBuf),
+}
+
+impl Default for AssetSource {
+    fn default() -> Self {
+        Self::BaseUrl(DEFAULT_DOWNLOAD_BASE.to_owned())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetLocation {
+    Remote(String),
+    Local(PathBuf),
+}
+
+impl AssetSource {
+    pub fn locate(&self, asset: &'static str) -> Result<AssetLocation, ArchiveDownloadError> {
+        match self {
+            Self::BaseUrl(base) => Ok(AssetLocation::Remote(format!(
+                "{}/{asset}",
+                base.trim_end_matches('/')
+            ))),
+            Self::Release(assets) => assets
+                .get(asset)
+                .cloned()
+                .map(AssetLocation::Remote)
+                .ok_or(ArchiveDownloadError::MissingAsset(asset)),
+            Self::Archive(path) if asset == OLLAMA_ASSET => Ok(AssetLocation::Local(path.clone())),
+            Self::Archive(_) => Err(ArchiveDownloadError::MissingAsset(asset)),
+            Self::Bundle(dir) => {
+                let path = dir.join(asset);
+
+                match path.is_file() {
+                    true => Ok(AssetLocation::Local(path)),
+                    false => Err(ArchiveDownloadError::MissingAsset(asset)),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadStage {
+    Download,
+    Unpack,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadProgress {
+    /// File name of archive in cache dir
+    pub archive: &'static str,
+    pub stage: DownloadStage,
+    /// For unpack this counts compressed bytes consumed by unpacker
+    pub done: u64,
+    pub total: u64,
+}
+
+impl DownloadProgress {
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 0.0;
+        }
+
+        (self.done as f64 / self.total as f64).min(1.0) as f32
+    }
+}
+
+#[derive(Clone)]
+pub struct DownloadOptions {
+    /// Keep downloaded archive in cache dir, so reinstall skips network
+    pub keep_archive: bool,
+    /// Called from tokio and blocking threads, keep it cheap
+    pub progress: Arc<dyn Fn(DownloadProgress) + Send + Sync>,
+}
+
+impl std::fmt::Debug for DownloadOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownloadOptions")
+            .field("keep_archive", &self.keep_archive)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            keep_archive: true,
+            progress: Arc::new(|_| ()),
+        }
+    }
+}
+
+impl DownloadOptions {
+    fn report(&self, archive: &'static str, stage: DownloadStage, done: u64, total: u64) {
+        (self.progress)(DownloadProgress {
+            archive,
+            stage,
+            done,
+            total,
+        })
+    }
+}
+
+/// Requests size of remote file
+async fn remote_size(client: &reqwest::Client, url: &str) -> Result<u64, ArchiveDownloadError> {
     let head_response = client.head(url).send().await?;
 
-    let total_size = head_response
+    head_response
         .headers()
         .get(reqwest::header::CONTENT_LENGTH)
         .and_then(|val| val.to_str().ok())
         .and_then(|s| s.parse::<u64>().ok())
-        .ok_or(ArchiveDownloadError::FailedRequest)?;
+        .ok_or(ArchiveDownloadError::FailedRequest)
+}
 
+/// Checks if archive from previous run with the same size is present in cache
+async fn is_cached(location: &Path, total_size: u64) -> Result<bool, ArchiveDownloadError> {
     let is_exists = tokio::fs::try_exists(location)
         .await
         .map_err(|error| BetterIoError {
@@ -55,32 +194,142 @@ async fn download_file(
             error,
         })?;
 
-    if is_exists {
-        match tokio::fs::metadata(location).await {
-            Ok(meta) => {
-                const LOG_MESSAGE: &str =
-                    "Existing download with matching size found. Proceeding with unpack";
+    if is_exists
+        && let Ok(meta) = tokio::fs::metadata(location).await
+        && meta.len() == total_size
+    {
+        tracing::warn!("Existing download with matching size found. Proceeding with unpack");
 
-                #[cfg(target_family = "unix")]
-                {
-                    use std::os::unix::fs::MetadataExt;
+        return Ok(true);
+    }
 
-                    if meta.size() == total_size {
-                        tracing::warn!("{LOG_MESSAGE}");
-                    }
-                }
+    Ok(false)
+}
+
+/// Archive fetched by runtime install
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RuntimeArchive {
+    asset: &'static str,
+    /// File name of archive in cache dir
+    cache_name: &'static str,
+    /// Optional addons are skipped if source does not provide them
+    required: bool,
+}
+
+/// Compressed runtime archives unpack to about three times their size
+const UNPACK_RATIO: u64 = 3;
 
-                #[cfg(target_family = "windows")]
-                if meta.len() == total_size {
-                    tracing::warn!("{LOG_MESSAGE}");
+/// Expected disk usage of runtime install, in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuntimeEstimate {
+    /// Written into cache dir. Local and already cached archives are not counted
+    pub download: u64,
+    /// Written into install dir
+    pub unpacked: u64,
+}
+
+/// Sizes up archives which install from `source` would fetch, without downloading them
+pub async fn estimate_runtime(
+    cache_dir: &Path,
+    source: &AssetSource,
+) -> Result<RuntimeEstimate, ArchiveDownloadError> {
+    let client = reqwest::Client::new();
+    let mut estimate = RuntimeEstimate::default();
+
+    for archive in runtime_archives().await {
+        let location = match source.locate(archive.asset) {
+            Ok(location) => location,
+            Err(ArchiveDownloadError::MissingAsset(_)) if !archive.required => continue,
+            Err(e) => return Err(e),
+        };
+
+        let size = match location {
+            AssetLocation::Remote(url) => {
+                let size = remote_size(&client, &url).await?;
+                let is_cached = tokio::fs::metadata(cache_dir.join(archive.cache_name))
+                    .await
+                    .is_ok_and(|meta| meta.len() == size);
+
+                if !is_cached {
+                    estimate.download += size;
                 }
 
-                return Ok(());
+                size
             }
-            _ => (),
+            AssetLocation::Local(path) => tokio::fs::metadata(&path)
+                .await
+                .map_err(|e| BetterIoError::new(&path, "reading size of local archive", e))?
+                .len(),
+        };
+
+        estimate.unpacked += size * UNPACK_RATIO;
+    }
+
+    Ok(estimate)
+}
+
+/// Logs download speed every 1MB
+#[derive(Debug)]
+struct SpeedLog {
+    start_time: tokio::time::Instant,
+    total_size: u64,
+}
+
+impl SpeedLog {
+    fn new(total_size: u64) -> Self {
+        Self {
+            start_time: tokio::time::Instant::now(),
+            total_size,
         }
     }
 
+    fn log(&self, downloaded: u64) {
+        if downloaded.is_multiple_of(1024 * 1024) || downloaded == self.total_size {
+            let elapsed = self.start_time.elapsed().as_secs_f64();
+            let speed = downloaded as f64 / elapsed / 1024.0 / 1024.0; // MB/s
+            let progress = if self.total_size > 0 {
+                (downloaded as f64 / self.total_size as f64 * 100.0) as u32
+            } else {
+                0
+            };
+
+            tracing::debug!(
+                "Downloaded: {:.1} MB | Progress: {}% | Speed: {:.1} MB/s",
+                downloaded as f64 / 1024.0 / 1024.0,
+                progress,
+                speed
+            );
+        }
+    }
+
+    fn finish(&self) {
+        tracing::info!(
+            "Download completed in {:.2} seconds",
+            self.start_time.elapsed().as_secs_f64()
+        );
+    }
+}
+
+/// Downloads whole file to disk. Used where archive can't be unpacked while it arrives.
+#[cfg_attr(
+    any(target_os = "linux", all(target_os = "macos", target_arch = "x86_64")),
+    allow(dead_code)
+)]
+async fn download_file(
+    client: &reqwest::Client,
+    url: &str,
+    location: &Path,
+    archive: &'static str,
+    options: &DownloadOptions,
+) -> Result<(), ArchiveDownloadError> {
+    let total_size = remote_size(client, url).await?;
+
+    if is_cached(location, total_size).await? {
+        options.report(archive, DownloadStage::Download, total_size, total_size);
+
+        return Ok(());
+    }
+
     // Start the actual download
     let response = client.get(url).send().await?;
 
@@ -98,7 +347,7 @@ async fn download_file(
 
     let mut stream = response.bytes_stream();
     let mut downloaded = 0u64;
-    let start_time = tokio::time::Instant::now();
+    let speed_log = SpeedLog::new(total_size);
 
     while let Some(chunk) = stream.next().await {
         let chunk = chunk?;
@@ -112,23 +361,8 @@ async fn download_file(
 
         downloaded += chunk.len() as u64;
 
-        // Print progress every 1MB
-        if downloaded % (1024 * 1024) == 0 || downloaded == total_size {
-            let elapsed = start_time.elapsed().as_secs_f64();
-            let speed = downloaded as f64 / elapsed / 1024.0 / 1024.0; // MB/s
-            let progress = if total_size > 0 {
-                (downloaded as f64 / total_size as f64 * 100.0) as u32
-            } else {
-                0
-            };
-
-            tracing::debug!(
-                "Downloaded: {:.1} MB | Progress: {}% | Speed: {:.1} MB/s",
-                downloaded as f64 / 1024.0 / 1024.0,
-                progress,
-                speed
-            );
-        }
+        speed_log.log(downloaded);
+        options.report(archive, DownloadStage::Download, downloaded, total_size);
     }
 
     file.flush().await.map_err(|error| BetterIoError {
@@ -137,35 +371,7 @@ async fn download_file(
         error,
     })?;
 
-    tracing::info!(
-        "Download completed in {:.2} seconds",
-        start_time.elapsed().as_secs_f64()
-    );
-
-    Ok(())
-}
-
-#[cfg(any(target_os = "linux", all(target_os = "macos", target_arch = "x86_64")))]
-fn unpack_archive(tar_location: &Path, target_dir: &Path) -> Result<(), ArchiveDownloadError> {
-    let file = std::fs::File::open(&tar_location).map_err(|error| BetterIoError {
-        location: tar_location.to_path_buf(),
-        context: "opening archive descriptor",
-        error,
-    })?;
-
-    tracing::info!("Starting unpacking of archive - {}", tar_location.display());
-
-    let decoder = flate2::read::MultiGzDecoder::new(file);
-
-    let mut archive = tar::Archive::new(decoder);
-
-    archive.unpack(target_dir).map_err(|error| BetterIoError {
-        location: target_dir.to_path_buf(),
-        context: "unpacking of archive",
-        error,
-    })?;
-
-    tracing::info!("Finished unpacking of archive - {}", tar_location.display());
+    speed_log.finish();
 
     Ok(())
 }
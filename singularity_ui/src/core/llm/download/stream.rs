@@ -0,0 +1,485 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use futures_util::StreamExt;
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+
+use crate::{
+    core::llm::download::{
+        ArchiveDownloadError, AssetLocation, DownloadOptions, DownloadStage, SpeedLog,
+        extract::{MAX_UNPACKED_SIZE, unpack_tar},
+    },
+    error::BetterIoError,
+};
+
+/// Amount of downloaded chunks which may wait for unpacker before download pauses
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Progress is reported at most once per this amount of bytes
+const REPORT_STEP: u64 = 1024 * 1024;
+
+/// Blocking reader over chunks sent by async download
+#[derive(Debug)]
+struct ChannelReader {
+    rx: mpsc::Receiver<Vec<u8>>,
+    current: Vec<u8>,
+    offset: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.offset == self.current.len() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => {
+                    self.current = chunk;
+                    self.offset = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.current.len() - self.offset);
+        buf[..len].copy_from_slice(&self.current[self.offset..self.offset + len]);
+        self.offset += len;
+
+        Ok(len)
+    }
+}
+
+/// Reports how much of compressed archive was consumed by unpacker
+#[derive(Debug)]
+struct ProgressReader<R> {
+    inner: R,
+    archive: &'static str,
+    options: DownloadOptions,
+    total: u64,
+    consumed: u64,
+    reported: u64,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.consumed += len as u64;
+
+        if self.consumed - self.reported >= REPORT_STEP || len == 0 {
+            self.reported = self.consumed;
+            self.options.report(
+                self.archive,
+                DownloadStage::Unpack,
+                self.consumed,
+                self.total,
+            );
+        }
+
+        Ok(len)
+    }
+}
+
+/// Runs decompression and unpack on blocking thread, so tokio workers stay free
+fn spawn_unpacker(
+    reader: impl Read + Send + 'static,
+    target_dir: &Path,
+    archive: &'static str,
+    total: u64,
+    options: &DownloadOptions,
+) -> tokio::task::JoinHandle<Result<u64, ArchiveDownloadError>> {
+    let reader = ProgressReader {
+        inner: reader,
+        archive,
+        options: options.clone(),
+        total,
+        consumed: 0,
+        reported: 0,
+    };
+    let target_dir = target_dir.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let decoder = flate2::read::MultiGzDecoder::new(reader);
+
+        unpack_tar(decoder, &target_dir, MAX_UNPACKED_SIZE)
+    })
+}
+
+fn partial_location(location: &Path) -> PathBuf {
+    let mut name = location.as_os_str().to_os_string();
+    name.push(".part");
+
+    name.into()
+}
+
+/// Unpacks `.tgz` archive which is already on disk
+async fn unpack_file(
+    location: &Path,
+    target_dir: &Path,
+    archive: &'static str,
+    options: &DownloadOptions,
+) -> Result<(), ArchiveDownloadError> {
+    let file = std::fs::File::open(location)
+        .map_err(|e| BetterIoError::new(location, "opening archive descriptor", e))?;
+
+    let total_size = file
+        .metadata()
+        .map_err(|e| BetterIoError::new(location, "reading archive size", e))?
+        .len();
+
+    tracing::info!("Starting unpacking of archive - {}", location.display());
+
+    let unpacked = spawn_unpacker(file, target_dir, archive, total_size, options).await??;
+    log_unpacked(archive, unpacked);
+
+    Ok(())
+}
+
+/// Unpacks `.tgz` archive from `location`. Remote archives are unpacked while bytes arrive.
+/// With [`DownloadOptions::keep_archive`] remote archive is also written to `cache_location`
+/// and reused on next call if size matches the remote one.
+pub async fn fetch_and_unpack(
+    client: &reqwest::Client,
+    location: &AssetLocation,
+    cache_location: &Path,
+    target_dir: &Path,
+    archive: &'static str,
+    options: &DownloadOptions,
+) -> Result<(), ArchiveDownloadError> {
+    let url = match location {
+        AssetLocation::Remote(url) => url,
+        AssetLocation::Local(path) => return unpack_file(path, target_dir, archive, options).await,
+    };
+
+    let total_size = super::remote_size(client, url).await?;
+
+    if super::is_cached(cache_location, total_size).await? {
+        options.report(archive, DownloadStage::Download, total_size, total_size);
+
+        return unpack_file(cache_location, target_dir, archive, options).await;
+    }
+
+    let response = client.get(url).send().await?;
+
+    if !response.status().is_success() {
+        return Err(ArchiveDownloadError::FailedRequest);
+    }
+
+    let partial = partial_location(cache_location);
+
+    let mut cache_file = if options.keep_archive {
+        let file = tokio::fs::File::create(&partial)
+            .await
+            .map_err(|error| BetterIoError::new(&partial, "creation of file descriptor", error))?;
+
+        Some(file)
+    } else {
+        None
+    };
+
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let reader = ChannelReader {
+        rx,
+        current: Vec::new(),
+        offset: 0,
+    };
+
+    tracing::info!("Starting streamed unpacking of archive - {url}");
+
+    let mut unpacker = spawn_unpacker(reader, target_dir, archive, total_size, options);
+    let mut unpacked = None;
+    let mut tx = Some(tx);
+
+    let download = async {
+        let mut stream = response.bytes_stream();
+        let mut downloaded = 0u64;
+        let mut reported = 0u64;
+        let speed_log = SpeedLog::new(total_size);
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            downloaded += chunk.len() as u64;
+
+            if let Some(file) = cache_file.as_mut() {
+                file.write_all(&chunk).await.map_err(|error| {
+                    BetterIoError::new(&partial, "saving of downloaded chunk", error)
+                })?;
+            }
+
+            // Unpacker stopped early, it has already returned once channel is closed.
+            // Only clean end of tar lets the rest of archive go to cache, its error is reported right away
+            if let Some(sender) = &tx
+                && sender.send(Vec::from(chunk)).await.is_err()
+            {
+                tx = None;
+
+                let res = (&mut unpacker).await?;
+                let failed = res.is_err();
+                unpacked = Some(res);
+
+                if failed || cache_file.is_none() {
+                    return Ok(false);
+                }
+            }
+
+            speed_log.log(downloaded);
+
+            if downloaded - reported >= REPORT_STEP || downloaded == total_size {
+                reported = downloaded;
+                options.report(archive, DownloadStage::Download, downloaded, total_size);
+            }
+        }
+
+        if downloaded != total_size {
+            return Err(ArchiveDownloadError::FailedRequest);
+        }
+
+        if let Some(file) = cache_file.as_mut() {
+            file.flush()
+                .await
+                .map_err(|error| BetterIoError::new(&partial, "flushing file descriptor", error))?;
+        }
+
+        speed_log.finish();
+
+        Ok(true)
+    }
+    .await;
+
+    // Closing channel signals end of archive to unpacker
+    drop(tx);
+    drop(cache_file);
+
+    let unpacked = match unpacked {
+        Some(unpacked) => unpacked,
+        None => unpacker.await?,
+    };
+
+    let (complete, unpacked) = match (download, unpacked) {
+        (Ok(complete), Ok(unpacked)) => (complete, unpacked),
+        (Err(e), _) | (Ok(_), Err(e)) => {
+            if options.keep_archive {
+                let _ = tokio::fs::remove_file(&partial).await;
+            }
+
+            return Err(e);
+        }
+    };
+
+    if options.keep_archive && !complete {
+        let _ = tokio::fs::remove_file(&partial).await;
+    } else if options.keep_archive {
+        tokio::fs::rename(&partial, cache_location)
+            .await
+            .map_err(|e| BetterIoError::new(&partial, "moving downloaded archive to cache", e))?;
+    }
+
+    log_unpacked(archive, unpacked);
+
+    Ok(())
+}
+
+fn log_unpacked(archive: &str, unpacked: u64) {
+    tracing::info!(
+        "Finished unpacking of archive - {archive}. Unpacked {:.1} MB",
+        unpacked as f64 / 1024.0 / 1024.0
+    );
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::{
+        io::Write,
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    /// `.tgz` with executable `bin/ollama`, followed by `trailer` bytes which unpacker never reads
+    fn tgz(trailer: usize) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "bin/ollama", &b"ELF!"[..])
+            .unwrap();
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+
+        let mut archive = encoder.finish().unwrap();
+        archive.resize(archive.len() + trailer, 0);
+
+        archive
+    }
+
+    /// Serves `body` to GET requests, while HEAD reports `size` as length of archive.
+    /// With `stall` GET promises `size` bytes and keeps connection open after `body`.
+    /// Returns url and counter of GET requests
+    async fn archive_server(size: usize, body: Vec<u8>, stall: bool) -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let downloads = Arc::new(AtomicUsize::new(0));
+
+        tokio::spawn({
+            let downloads = downloads.clone();
+
+            async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let mut request = [0u8; 4096];
+                    let _ = stream.read(&mut request).await;
+
+                    let (length, body) = match request.starts_with(b"HEAD") {
+                        true => (size, &[][..]),
+                        false => {
+                            downloads.fetch_add(1, Ordering::SeqCst);
+                            (if stall { size } else { body.len() }, &body[..])
+                        }
+                    };
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {length}\r\nconnection: close\r\n\r\n"
+                    );
+
+                    let _ = stream.write_all(head.as_bytes()).await;
+                    let _ = stream.write_all(body).await;
+
+                    if stall {
+                        tokio::spawn(async move {
+                            let _stream = stream;
+                            std::future::pending::<()>().await
+                        });
+                    }
+                }
+            }
+        });
+
+        (format!("http://{address}/ollama.tgz"), downloads)
+    }
+
+    async fn fetch(url: String, dir: &Path) -> Result<(), ArchiveDownloadError> {
+        fetch_and_unpack(
+            &reqwest::Client::new(),
+            &AssetLocation::Remote(url),
+            &dir.join("ollama.tgz"),
+            &dir.join("runtime"),
+            "ollama.tgz",
+            &DownloadOptions::default(),
+        )
+        .await
+    }
+
+    #[test]
+    fn channel_reader_joins_chunks() {
+        let (tx, rx) = mpsc::channel(4);
+        for chunk in ["hel", "", "lo"] {
+            tx.try_send(chunk.as_bytes().to_vec()).unwrap();
+        }
+        drop(tx);
+
+        let mut reader = ChannelReader {
+            rx,
+            current: Vec::new(),
+            offset: 0,
+        };
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+
+        assert_eq!(text, "hello");
+    }
+
+    #[test]
+    fn progress_reader_reports_consumed_bytes() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let options = DownloadOptions {
+            keep_archive: false,
+            progress: Arc::new({
+                let reports = reports.clone();
+                move |progress| {
+                    reports
+                        .lock()
+                        .unwrap()
+                        .push((progress.stage, progress.done, progress.total))
+                }
+            }),
+        };
+        let data = vec![0u8; REPORT_STEP as usize * 2 + 10];
+        let total = data.len() as u64;
+
+        let mut reader = ProgressReader {
+            inner: &data[..],
+            archive: "ollama.tgz",
+            options,
+            total,
+            consumed: 0,
+            reported: 0,
+        };
+        std::io::copy(&mut reader, &mut std::io::sink()).unwrap();
+
+        let reports = reports.lock().unwrap();
+        assert!(reports.len() >= 3);
+        assert!(reports.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        assert_eq!(reports.last(), Some(&(DownloadStage::Unpack, total, total)));
+    }
+
+    #[tokio::test]
+    async fn streams_archive_into_runtime_and_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        // Unpacker stops at end of tar long before the rest of body arrives
+        let body = tgz(8 * 1024 * 1024);
+        let (url, _) = archive_server(body.len(), body.clone(), false).await;
+
+        fetch(url, dir.path()).await.unwrap();
+
+        let ollama = dir.path().join("runtime/bin/ollama");
+        assert_eq!(std::fs::read(&ollama).unwrap(), b"ELF!");
+        assert_eq!(std::fs::read(dir.path().join("ollama.tgz")).unwrap(), body);
+        assert!(!dir.path().join("ollama.tgz.part").exists());
+    }
+
+    #[tokio::test]
+    async fn broken_archive_fails_without_waiting_for_rest_of_body() {
+        let dir = tempfile::tempdir().unwrap();
+        // Not a gzip stream, rest of promised body never arrives
+        let body = vec![b'x'; 256 * 1024];
+        let (url, _) = archive_server(64 * 1024 * 1024, body, true).await;
+
+        let res = tokio::time::timeout(Duration::from_secs(10), fetch(url, dir.path()))
+            .await
+            .expect("download kept going after unpacker failed");
+
+        assert!(res.is_err());
+        assert!(!dir.path().join("ollama.tgz.part").exists());
+        assert!(!dir.path().join("ollama.tgz").exists());
+    }
+
+    #[tokio::test]
+    async fn truncated_body_fails_and_removes_partial_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = tgz(0);
+        let (url, _) = archive_server(body.len() + 100, body, false).await;
+
+        let res = fetch(url, dir.path()).await;
+
+        assert!(matches!(res, Err(ArchiveDownloadError::FailedRequest)));
+        assert!(!dir.path().join("ollama.tgz.part").exists());
+        assert!(!dir.path().join("ollama.tgz").exists());
+    }
+
+    #[tokio::test]
+    async fn reuses_cached_archive_of_same_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = tgz(0);
+        std::fs::write(dir.path().join("ollama.tgz"), &body).unwrap();
+        let (url, downloads) = archive_server(body.len(), body, false).await;
+
+        fetch(url, dir.path()).await.unwrap();
+
+        assert_eq!(downloads.load(Ordering::SeqCst), 0);
+        assert!(dir.path().join("runtime/bin/ollama").is_file());
+    }
+}
@@ -1,56 +1,135 @@
 use std::path::{Path, PathBuf};
 
-use crate::{error::BetterIoError, llm::download::ArchiveDownloadError};
+use crate::core::llm::{
+    download::{
+        ArchiveDownloadError, AssetLocation, AssetSource, DownloadOptions, DownloadStage,
+        RuntimeArchive,
+    },
+    utils::GpuInventory,
+};
 
 #[cfg(target_arch = "aarch64")]
-const OLLAMA_LINK: &str = "https://ollama.com/download/ollama-windows-arm64.zip";
+pub const OLLAMA_ASSET: &str = "ollama-windows-arm64.zip";
 #[cfg(target_arch = "x86_64")]
-const OLLAMA_LINK: &str = "https://ollama.com/download/ollama-windows-amd64.zip";
+pub const OLLAMA_ASSET: &str = "ollama-windows-amd64.zip";
 
 #[cfg(target_arch = "x86_64")]
-const AMD_GPU_ADDON: &str = "https://ollama.com/download/ollama-windows-amd64-rocm.zip";
+const AMD_GPU_ADDON_ASSET: &str = "ollama-windows-amd64-rocm.zip";
 
 const OLLAMA_DOWNLOAD_FILENAME: &str = "ollama.zip";
+#[cfg(target_arch = "x86_64")]
 const OLLAMA_ROCM_DOWNLOAD_FILENAME: &str = "ollama_rocm.zip";
 
+/// Archives which [`ollama_download`] fetches on this machine
+pub(super) async fn runtime_archives() -> Vec<RuntimeArchive> {
+    let mut archives = vec![RuntimeArchive {
+        asset: OLLAMA_ASSET,
+        cache_name: OLLAMA_DOWNLOAD_FILENAME,
+        required: true,
+    }];
+
+    #[cfg(target_arch = "x86_64")]
+    if GpuInventory::detect().await.runtime_variant()
+        == crate::core::llm::utils::RuntimeVariant::Rocm
+    {
+        archives.push(RuntimeArchive {
+            asset: AMD_GPU_ADDON_ASSET,
+            cache_name: OLLAMA_ROCM_DOWNLOAD_FILENAME,
+            required: false,
+        });
+    }
+
+    archives
+}
+
 pub async fn ollama_download(
     cache_dir: impl AsRef<Path>,
     target_dir: impl AsRef<Path>,
+    source: &AssetSource,
+    options: &DownloadOptions,
 ) -> Result<PathBuf, ArchiveDownloadError> {
     let client = reqwest::Client::new();
 
-    let rocm = !crate::llm::utils::is_nvidia().await;
+    // CUDA libraries are part of base archive, only ROCm needs an addon
+    let variant = GpuInventory::detect().await.runtime_variant();
+    tracing::info!("Selected ollama runtime variant: {variant:?}");
 
     let cache_dir = cache_dir.as_ref();
 
     let ollama_location = cache_dir.join(OLLAMA_DOWNLOAD_FILENAME);
-    super::download_file(&client, OLLAMA_LINK, &ollama_location).await?;
-    unpack_archive(&ollama_location, target_dir.as_ref())?;
-
-    if rocm {
-        let ollama_rocm_location = cache_dir.join(OLLAMA_ROCM_DOWNLOAD_FILENAME);
-
-        super::download_file(&client, AMD_GPU_ADDON, &ollama_rocm_location).await?;
-        unpack_archive(&ollama_rocm_location, target_dir.as_ref())?;
+    download_and_unpack(
+        &client,
+        &source.locate(OLLAMA_ASSET)?,
+        &ollama_location,
+        target_dir.as_ref(),
+        OLLAMA_DOWNLOAD_FILENAME,
+        options,
+    )
+    .await?;
+
+    #[cfg(target_arch = "x86_64")]
+    if variant == crate::core::llm::utils::RuntimeVariant::Rocm {
+        match source.locate(AMD_GPU_ADDON_ASSET) {
+            Ok(location) => {
+                let ollama_rocm_location = cache_dir.join(OLLAMA_ROCM_DOWNLOAD_FILENAME);
+
+                download_and_unpack(
+                    &client,
+                    &location,
+                    &ollama_rocm_location,
+                    target_dir.as_ref(),
+                    OLLAMA_ROCM_DOWNLOAD_FILENAME,
+                    options,
+                )
+                .await?;
+            }
+            Err(ArchiveDownloadError::MissingAsset(asset)) => {
+                tracing::warn!(
+                    "{asset} is not provided by selected source. Installing without ROCm"
+                );
+            }
+            Err(e) => return Err(e),
+        }
     }
 
     Ok(ollama_location)
 }
 
-fn unpack_archive(tar_location: &Path, target_dir: &Path) -> Result<(), ArchiveDownloadError> {
-    let file = std::fs::File::open(&tar_location).map_err(|error| BetterIoError {
-        location: tar_location.to_path_buf(),
-        context: "opening archive descriptor",
-        error,
-    })?;
-
-    tracing_log::log::info!("Starting unpacking of archive - {}", tar_location.display());
-
-    let mut archive = zip::ZipArchive::new(file)?;
-
-    archive.extract(target_dir)?;
-
-    tracing_log::log::info!("Finished unpacking of archive - {}", tar_location.display());
+/// Zip needs random access, so remote archive is fully downloaded to `cache_location` before unpack starts
+async fn download_and_unpack(
+    client: &reqwest::Client,
+    location: &AssetLocation,
+    cache_location: &Path,
+    target_dir: &Path,
+    archive: &'static str,
+    options: &DownloadOptions,
+) -> Result<(), ArchiveDownloadError> {
+    let (location, downloaded) = match location {
+        AssetLocation::Remote(url) => {
+            super::download_file(client, url, cache_location, archive, options).await?;
+
+            (cache_location, true)
+        }
+        AssetLocation::Local(path) => (path.as_path(), false),
+    };
+
+    options.report(archive, DownloadStage::Unpack, 0, 1);
+
+    tokio::task::spawn_blocking({
+        let location = location.to_path_buf();
+        let target_dir = target_dir.to_path_buf();
+
+        move || super::unpack_archive(&location, &target_dir)
+    })
+    .await??;
+
+    options.report(archive, DownloadStage::Unpack, 1, 1);
+
+    if downloaded && !options.keep_archive {
+        let _ = tokio::fs::remove_file(location)
+            .await
+            .inspect_err(|e| tracing::warn!("Failed to remove downloaded archive. Reason: {e}"));
+    }
 
     Ok(())
 }
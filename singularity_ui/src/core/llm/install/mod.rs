@@ -1,5 +1,9 @@
 #[cfg(target_os = "macos")]
 mod macos;
+#[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+mod staged;
 
 #[cfg(target_os = "macos")]
 pub use macos::*;
+#[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+pub use staged::*;
@@ -0,0 +1,306 @@
+use std::path::{Path, PathBuf};
+
+use crate::{core::llm::serve::ollama_binary_location, error::BetterIoError};
+
+const STAGING_SUFFIX: &str = "staging";
+const PREVIOUS_SUFFIX: &str = "previous";
+
+#[derive(Debug, thiserror::Error)]
+pub enum StagedInstallError {
+    #[error(transparent)]
+    Io(#[from] BetterIoError),
+    #[error("Staged ollama in {} failed to report its version", .0.display())]
+    SmokeTest(PathBuf),
+    #[error("There is no previous ollama install to roll back to")]
+    NoPreviousInstall,
+}
+
+/// Directories used to swap ollama installs. All of them are siblings, so swap is a plain rename on the same filesystem.
+///
+/// - `current` is what gets started and reported by [`crate::core::llm::version`]
+/// - `staging` receives fresh unpack and is promoted only after smoke test
+/// - `previous` keeps last working install for rollback
+#[derive(Debug, Clone)]
+pub struct InstallLayout {
+    current: PathBuf,
+    staging: PathBuf,
+    previous: PathBuf,
+}
+
+impl InstallLayout {
+    pub fn new(current: impl Into<PathBuf>) -> Self {
+        let current = current.into();
+
+        let sibling = |suffix: &str| {
+            let mut name = current.file_name().unwrap_or_default().to_os_string();
+            name.push(".");
+            name.push(suffix);
+
+            current.with_file_name(name)
+        };
+
+        Self {
+            staging: sibling(STAGING_SUFFIX),
+            previous: sibling(PREVIOUS_SUFFIX),
+            current,
+        }
+    }
+
+    /// Every dir owned by the layout, including leftovers of interrupted install
+    pub fn dirs(&self) -> [&Path; 3] {
+        [&self.current, &self.staging, &self.previous]
+    }
+
+    pub async fn has_previous(&self) -> bool {
+        tokio::fs::try_exists(&self.previous)
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Removes leftovers of interrupted install and returns empty staging dir
+    pub async fn prepare_staging(&self) -> Result<&Path, BetterIoError> {
+        remove_dir_if_exists(&self.staging).await?;
+
+        tokio::fs::create_dir_all(&self.staging)
+            .await
+            .map_err(|e| BetterIoError::new(&self.staging, "creating staging dir", e))?;
+
+        Ok(&self.staging)
+    }
+
+    pub async fn discard_staging(&self) -> Result<(), BetterIoError> {
+        remove_dir_if_exists(&self.staging).await
+    }
+
+    /// Runs staged binary. Staging is discarded if it can't report its version
+    pub async fn smoke_test(&self) -> Result<String, StagedInstallError> {
+        match staged_version(&self.staging).await {
+            Some(version) => Ok(version),
+            None => {
+                self.discard_staging().await?;
+
+                Err(StagedInstallError::SmokeTest(self.staging.clone()))
+            }
+        }
+    }
+
+    /// Promotes staging to current. Current install becomes previous one.
+    /// Staging has to pass [`Self::smoke_test`] first
+    pub async fn commit(&self) -> Result<(), StagedInstallError> {
+        let had_current = exists(&self.current).await?;
+
+        if had_current {
+            remove_dir_if_exists(&self.previous).await?;
+            rename(&self.current, &self.previous).await?;
+        }
+
+        if let Err(e) = rename(&self.staging, &self.current).await {
+            if had_current {
+                let _ = rename(&self.previous, &self.current)
+                    .await
+                    .inspect_err(|e| {
+                        tracing::error!("Failed to restore previous install. Reason: {e}")
+                    });
+            }
+
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+
+    /// Swaps current and previous installs, so rollback can be undone the same way.
+    /// Current install waits in staging during the swap and is put back if any rename fails
+    pub async fn rollback(&self) -> Result<(), StagedInstallError> {
+        if !exists(&self.previous).await? {
+            return Err(StagedInstallError::NoPreviousInstall);
+        }
+
+        remove_dir_if_exists(&self.staging).await?;
+
+        let had_current = exists(&self.current).await?;
+
+        if had_current {
+            rename(&self.current, &self.staging).await?;
+        }
+
+        if let Err(e) = rename(&self.previous, &self.current).await {
+            if had_current {
+                let _ = rename(&self.staging, &self.current).await.inspect_err(|e| {
+                    tracing::error!("Failed to restore current install. Reason: {e}")
+                });
+            }
+
+            return Err(e.into());
+        }
+
+        if had_current && let Err(e) = rename(&self.staging, &self.previous).await {
+            let undo = async {
+                rename(&self.current, &self.previous).await?;
+                rename(&self.staging, &self.current).await
+            };
+
+            let _ = undo
+                .await
+                .inspect_err(|e| tracing::error!("Failed to restore current install. Reason: {e}"));
+
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+
+    /// Restores working install if app was killed between renames of [`Self::commit`] or [`Self::rollback`].
+    /// Staging is promoted only if it passes smoke test, so interrupted download is never used
+    pub async fn recover(&self) -> Result<(), BetterIoError> {
+        if exists(&self.current).await? {
+            return Ok(());
+        }
+
+        if exists(&self.staging).await? && staged_version(&self.staging).await.is_some() {
+            tracing::warn!("Found interrupted ollama install swap. Restoring staged one");
+
+            rename(&self.staging, &self.current).await?;
+        } else if exists(&self.previous).await? {
+            tracing::warn!("Found interrupted ollama install. Restoring previous one");
+
+            rename(&self.previous, &self.current).await?;
+        }
+
+        Ok(())
+    }
+}
+
+async fn staged_version(dir: &Path) -> Option<String> {
+    crate::core::llm::ollama_version(&ollama_binary_location(dir)).await
+}
+
+async fn exists(path: &Path) -> Result<bool, BetterIoError> {
+    tokio::fs::try_exists(path)
+        .await
+        .map_err(|e| BetterIoError::new(path, "checking install dir", e))
+}
+
+async fn rename(from: &Path, to: &Path) -> Result<(), BetterIoError> {
+    tokio::fs::rename(from, to)
+        .await
+        .map_err(|e| BetterIoError::new(from, "swapping install dirs", e))
+}
+
+async fn remove_dir_if_exists(path: &Path) -> Result<(), BetterIoError> {
+    match tokio::fs::remove_dir_all(path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(BetterIoError::new(path, "removing old install dir", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Install dir with `version` file telling installs apart
+    fn install(dir: &Path, version: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("version"), version).unwrap();
+    }
+
+    fn version(dir: &Path) -> Option<String> {
+        std::fs::read_to_string(dir.join("version")).ok()
+    }
+
+    #[tokio::test]
+    async fn failed_smoke_test_keeps_current_install() {
+        let scratch = tempfile::tempdir().unwrap();
+        let layout = InstallLayout::new(scratch.path().join("ollama"));
+        let [current, staging, previous] = layout.dirs();
+        install(current, "0.12.5");
+
+        // Staging without binary can't report its version
+        layout.prepare_staging().await.unwrap();
+        let res = layout.smoke_test().await;
+
+        assert!(matches!(res, Err(StagedInstallError::SmokeTest(_))));
+        assert_eq!(version(current).as_deref(), Some("0.12.5"));
+        assert!(!staging.exists());
+        assert!(!previous.exists());
+    }
+
+    #[tokio::test]
+    async fn rollback_swaps_current_and_previous() {
+        let scratch = tempfile::tempdir().unwrap();
+        let layout = InstallLayout::new(scratch.path().join("ollama"));
+        let [current, staging, previous] = layout.dirs();
+
+        assert!(matches!(
+            layout.rollback().await,
+            Err(StagedInstallError::NoPreviousInstall)
+        ));
+
+        install(current, "0.12.6");
+        install(previous, "0.12.5");
+        install(staging, "leftover");
+
+        layout.rollback().await.unwrap();
+        assert_eq!(version(current).as_deref(), Some("0.12.5"));
+        assert_eq!(version(previous).as_deref(), Some("0.12.6"));
+        assert!(!staging.exists());
+
+        layout.rollback().await.unwrap();
+        assert_eq!(version(current).as_deref(), Some("0.12.6"));
+        assert_eq!(version(previous).as_deref(), Some("0.12.5"));
+    }
+
+    #[tokio::test]
+    async fn recovers_previous_install_after_interrupted_commit() {
+        let scratch = tempfile::tempdir().unwrap();
+        let layout = InstallLayout::new(scratch.path().join("ollama"));
+        let [current, staging, previous] = layout.dirs();
+
+        // Killed after current was moved to previous, before staging was promoted
+        install(previous, "0.12.5");
+        install(staging, "0.12.6");
+
+        layout.recover().await.unwrap();
+        assert_eq!(version(current).as_deref(), Some("0.12.5"));
+        assert!(!previous.exists());
+
+        // Nothing to do once current is in place
+        layout.recover().await.unwrap();
+        assert_eq!(version(current).as_deref(), Some("0.12.5"));
+    }
+
+    /// Install with binary which passes smoke test
+    #[cfg(target_os = "linux")]
+    fn runnable_install(dir: &Path, version: &str) {
+        use std::os::unix::fs::PermissionsExt;
+
+        install(dir, version);
+
+        let bin = ollama_binary_location(dir);
+        std::fs::create_dir_all(bin.parent().unwrap()).unwrap();
+        std::fs::write(
+            &bin,
+            format!("#!/bin/sh\necho 'ollama version is {version}'\n"),
+        )
+        .unwrap();
+        std::fs::set_permissions(&bin, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn recovers_current_install_after_interrupted_rollback() {
+        let scratch = tempfile::tempdir().unwrap();
+        let layout = InstallLayout::new(scratch.path().join("ollama"));
+        let [current, staging, previous] = layout.dirs();
+
+        // Killed after current was moved aside, before previous took its place
+        runnable_install(staging, "0.12.6");
+        install(previous, "0.12.5");
+
+        layout.recover().await.unwrap();
+        assert_eq!(version(current).as_deref(), Some("0.12.6"));
+        assert_eq!(version(previous).as_deref(), Some("0.12.5"));
+        assert!(!staging.exists());
+    }
+}
@@ -1,37 +1,46 @@
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{LazyLock, Mutex, atomic::AtomicBool},
 };
 
-use ollama_rs::generation::completion::request::GenerationRequest;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use ollama_rs::generation::{
+    chat::{ChatMessage, request::ChatMessageRequest},
+    completion::request::GenerationRequest,
+    images::Image,
+};
 
 use crate::{
     APP_ID,
-    core::llm::{download::ollama_download, serve::ollama_serve},
+    core::llm::{
+        download::{AssetSource, DownloadOptions, ollama_download},
+        options::GenerationOptions,
+        serve::ollama_serve,
+    },
+    core::{conversations::Role, settings, storage},
     error::BetterIoError,
 };
 
 pub mod download;
 pub mod install;
+pub mod options;
+pub mod preflight;
 pub mod serve;
+pub mod store;
+pub mod update;
 pub mod utils;
 
-const OLLAMA_DATA_DIR: &str = "ollama";
-const MODEL_NAME: &str = "gemma3:1b";
+pub(crate) const OLLAMA_DATA_DIR: &str = "ollama";
+pub(crate) const MODEL_NAME: &str = "gemma3:1b";
 
 static IS_OLLAMA_LOADED: AtomicBool = AtomicBool::new(false);
 
 static OLLAMA_BACKEND: Mutex<Option<tokio::process::Child>> = Mutex::new(Option::None);
 
-static OLLAMA_CLIENT: LazyLock<ollama_rs::Ollama> = LazyLock::new(|| ollama_rs::Ollama::default());
-
-/// Returns string with ollama installed version. None means that ollama probably not installed or missing in $PATH env
-async fn ollama_version(binary_dir: Option<PathBuf>) -> Option<String> {
-    let ollama_bin = match binary_dir {
-        Some(dir) => serve::ollama_binary_location(&dir),
-        None => "ollama".into(),
-    };
+static OLLAMA_CLIENT: LazyLock<ollama_rs::Ollama> = LazyLock::new(ollama_rs::Ollama::default);
 
+/// Returns string with version of given ollama binary. None means that ollama probably not installed
+async fn ollama_version(ollama_bin: &Path) -> Option<String> {
     let res = tokio::process::Command::new(ollama_bin)
         .arg("-v")
         .output()
@@ -63,7 +72,7 @@ async fn ollama_version(binary_dir: Option<PathBuf>) -> Option<String> {
     Some(version)
 }
 
-async fn get_or_create_app_dir(root: Option<PathBuf>) -> Result<PathBuf, BetterIoError> {
+pub(crate) async fn get_or_create_app_dir(root: Option<PathBuf>) -> Result<PathBuf, BetterIoError> {
     let path = root
         .unwrap_or(dirs::data_dir().expect("invalid os"))
         .join(APP_ID);
@@ -80,33 +89,508 @@ async fn get_or_create_app_dir(root: Option<PathBuf>) -> Result<PathBuf, BetterI
     Ok(path)
 }
 
-pub async fn version() -> anyhow::Result<Option<String>> {
+#[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+pub(crate) async fn install_layout() -> Result<install::InstallLayout, BetterIoError> {
+    let ollama_dir = get_or_create_app_dir(None).await?.join(OLLAMA_DATA_DIR);
+
+    Ok(install::InstallLayout::new(ollama_dir))
+}
+
+/// Ollama found in `$PATH`, e.g. installed by distro package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemOllama {
+    pub location: PathBuf,
+    pub version: String,
+}
+
+pub async fn system_ollama() -> Option<SystemOllama> {
+    let location = serve::system_binary_location()?;
+    let version = ollama_version(&location).await?;
+
+    Some(SystemOllama { location, version })
+}
+
+/// Version of app managed install, ignoring system one
+pub async fn managed_version() -> anyhow::Result<Option<String>> {
     let ollama_dir = get_or_create_app_dir(None).await?.join(OLLAMA_DATA_DIR);
 
-    let version = ollama_version(Some(ollama_dir)).await;
+    #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+    install::InstallLayout::new(&ollama_dir).recover().await?;
+
+    let version = ollama_version(&serve::ollama_binary_location(&ollama_dir)).await;
 
     Ok(version)
 }
 
-pub async fn llm_download() -> anyhow::Result<()> {
+/// Version of ollama which will be started, according to settings
+pub async fn version(settings: &settings::Settings) -> anyhow::Result<Option<String>> {
+    if settings.runtime.use_system
+        && let Some(system) = system_ollama().await
+    {
+        return Ok(Some(system.version));
+    }
+
+    managed_version().await
+}
+
+/// Binary which will be started. Falls back to app managed one if system ollama disappeared
+async fn selected_binary(settings: &settings::Settings) -> anyhow::Result<PathBuf> {
+    if settings.runtime.use_system {
+        match serve::system_binary_location() {
+            Some(location) => return Ok(location),
+            None => tracing::warn!("System ollama is selected, but missing in $PATH"),
+        }
+    }
+
+    let ollama_dir = get_or_create_app_dir(None).await?.join(OLLAMA_DATA_DIR);
+
+    Ok(serve::ollama_binary_location(&ollama_dir))
+}
+
+/// Switches between system and app managed ollama. Running backend is stopped, next request starts selected one
+pub async fn llm_use_system(use_system: bool) -> anyhow::Result<()> {
+    settings::update(|settings| settings.runtime.use_system = use_system).await?;
+
+    llm_unload().await
+}
+
+/// Store used by ollama installed outside of the app, `~/.ollama/models`
+fn user_models_dir() -> PathBuf {
+    dirs::home_dir()
+        .expect("invalid os")
+        .join(".ollama")
+        .join("models")
+}
+
+/// Ollama model store when nothing is set. Same default as ollama itself uses
+fn default_models_dir() -> PathBuf {
+    std::env::var_os("OLLAMA_MODELS")
+        .map(PathBuf::from)
+        .unwrap_or_else(user_models_dir)
+}
+
+/// Location of ollama model store, as set in settings
+pub async fn models_dir() -> Result<PathBuf, settings::SettingsError> {
+    Ok(settings::load()
+        .await?
+        .models
+        .store
+        .unwrap_or_else(default_models_dir))
+}
+
+/// Moves models into `target` and makes it the model store. `None` returns to default location.
+/// Backend is stopped during migration and picks new store on next start.
+pub async fn llm_migrate_models(
+    target: Option<PathBuf>,
+    progress: impl Fn(store::StoreProgress) + Send + 'static,
+) -> anyhow::Result<()> {
+    let source = models_dir().await?;
+    let target_dir = target.clone().unwrap_or_else(default_models_dir);
+
+    if source != target_dir {
+        llm_unload().await?;
+
+        migrate_store(source, target_dir, progress).await?;
+    }
+
+    settings::update(|settings| settings.models.store = target).await?;
+
+    Ok(())
+}
+
+/// Models of ollama used before the app, which are not visible to app runtime yet
+#[derive(Debug, Clone)]
+pub struct ExistingStore {
+    pub location: PathBuf,
+    pub models: Vec<store::StoredModel>,
+}
+
+/// Looks for models in `~/.ollama/models` when app uses another store
+pub async fn llm_existing_store() -> anyhow::Result<Option<ExistingStore>> {
+    let location = user_models_dir();
+
+    if location == models_dir().await? {
+        return Ok(None);
+    }
+
+    let models = tokio::task::spawn_blocking({
+        let location = location.clone();
+
+        move || store::list_models(&location)
+    })
+    .await??;
+
+    if models.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(ExistingStore { location, models }))
+}
+
+/// Points app runtime at `location`, models stay where they are
+pub async fn llm_share_store(location: PathBuf) -> anyhow::Result<()> {
+    settings::update(|settings| settings.models.store = Some(location)).await?;
+
+    llm_unload().await
+}
+
+/// Copies models with given names from `source` store into app store. Blobs present in both are copied once
+pub async fn llm_import_models(
+    source: PathBuf,
+    names: Vec<String>,
+    progress: impl Fn(store::StoreProgress) + Send + 'static,
+) -> anyhow::Result<store::ImportReport> {
+    let target = models_dir().await?;
+
+    let models = tokio::task::spawn_blocking({
+        let source = source.clone();
+
+        move || store::list_models(&source)
+    })
+    .await??
+    .into_iter()
+    .filter(|model| names.contains(&model.name))
+    .collect::<Vec<_>>();
+
+    let mut seen = std::collections::HashSet::new();
+    let bytes = models
+        .iter()
+        .flat_map(|model| model.manifest.blobs())
+        .filter(|blob| seen.insert(&blob.digest))
+        .filter(|blob| {
+            !std::fs::metadata(store::blob_location(&target, &blob.digest))
+                .is_ok_and(|meta| meta.len() == blob.size)
+        })
+        .map(|blob| blob.size)
+        .sum();
+
+    preflight::check_space(&[preflight::SpaceRequirement {
+        dir: target.clone(),
+        purpose: "imported models",
+        bytes,
+    }])?;
+
+    let report = tokio::task::spawn_blocking(move || {
+        store::import_models(&source, &target, &models, progress)
+    })
+    .await??;
+
+    Ok(report)
+}
+
+/// Re-hashes every blob of model store and reports missing, corrupt and orphaned ones
+pub async fn llm_verify_models(
+    progress: impl Fn(store::StoreProgress) + Send + 'static,
+) -> anyhow::Result<store::StoreReport> {
+    let models_dir = models_dir().await?;
+
+    let report =
+        tokio::task::spawn_blocking(move || store::verify(&models_dir, progress)).await??;
+
+    Ok(report)
+}
+
+/// Pulls `models` again. Corrupt blobs from `report` are removed first, otherwise ollama would reuse them
+pub async fn llm_repull_models(
+    report: store::StoreReport,
+    models: Vec<String>,
+) -> anyhow::Result<()> {
+    let models_dir = models_dir().await?;
+
+    tokio::task::spawn_blocking({
+        let models = models.clone();
+
+        move || store::remove_corrupt_blobs(&models_dir, &report, &models)
+    })
+    .await??;
+
+    llm_load().await?;
+
+    for model in models {
+        tracing::info!("Pulling {model} again");
+
+        OLLAMA_CLIENT.pull_model(model, false).await?;
+    }
+
+    Ok(())
+}
+
+/// Removes blobs which no model points to. Returns amount of freed bytes.
+/// Store shared with system ollama is refused, as its pulls can't be stopped by the app
+pub async fn llm_remove_orphans() -> anyhow::Result<u64> {
+    if let Some(store) = storage::shared_model_store().await? {
+        return Err(storage::StorageError::SharedModels(store).into());
+    }
+
+    let models_dir = models_dir().await?;
+
+    // Running pull keeps its partial blobs unreferenced until it finishes
+    llm_unload().await?;
+
+    let freed = tokio::task::spawn_blocking(move || store::remove_orphans(&models_dir)).await??;
+
+    Ok(freed)
+}
+
+async fn migrate_store(
+    source: PathBuf,
+    target: PathBuf,
+    progress: impl Fn(store::StoreProgress) + Send + 'static,
+) -> anyhow::Result<()> {
+    if !tokio::fs::try_exists(&source).await.unwrap_or_default() {
+        return Ok(());
+    }
+
+    // Same filesystem, whole store is moved with a single rename
+    if !tokio::fs::try_exists(&target).await.unwrap_or_default() {
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| BetterIoError::new(parent, "creating model store dir", e))?;
+        }
+
+        if tokio::fs::rename(&source, &target).await.is_ok() {
+            tracing::info!(
+                "Moved model store from {} to {}",
+                source.display(),
+                target.display()
+            );
+
+            return Ok(());
+        }
+    }
+
+    let bytes = tokio::task::spawn_blocking({
+        let source = source.clone();
+
+        move || {
+            store::store_files(&source)?
+                .iter()
+                .map(|file| std::fs::metadata(file).map(|meta| meta.len()))
+                .sum::<std::io::Result<u64>>()
+                .map_err(|e| BetterIoError::new(&source, "reading size of model store", e))
+        }
+    })
+    .await??;
+
+    preflight::check_space(&[preflight::SpaceRequirement {
+        dir: target.clone(),
+        purpose: "models",
+        bytes,
+    }])?;
+
+    tokio::task::spawn_blocking(move || store::migrate(&source, &target, progress)).await??;
+
+    Ok(())
+}
+
+/// Disk space which runtime install from `source` needs
+async fn runtime_requirements(
+    source: &AssetSource,
+) -> anyhow::Result<Vec<preflight::SpaceRequirement>> {
+    let cache_dir = get_or_create_app_dir(Some(dirs::cache_dir().expect("invalid os"))).await?;
+    let estimate = download::estimate_runtime(&cache_dir, source).await?;
+
+    #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+    let install_dir = get_or_create_app_dir(None).await?.join(OLLAMA_DATA_DIR);
+    // Installer copies app out of disk image
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    let install_dir = PathBuf::from("/Applications");
+
+    Ok(vec![
+        preflight::SpaceRequirement {
+            dir: cache_dir,
+            purpose: "runtime archives",
+            bytes: estimate.download,
+        },
+        preflight::SpaceRequirement {
+            dir: install_dir,
+            purpose: "runtime",
+            bytes: estimate.unpacked,
+        },
+    ])
+}
+
+/// Disk space which pull of `model` needs. Unknown size is not checked
+async fn model_requirements(model: &str) -> anyhow::Result<Vec<preflight::SpaceRequirement>> {
+    let models_dir = models_dir().await?;
+    let bytes = preflight::model_download_size(model, &models_dir)
+        .await
+        .unwrap_or_default();
+
+    Ok(vec![preflight::SpaceRequirement {
+        dir: models_dir,
+        purpose: "model",
+        bytes,
+    }])
+}
+
+/// Checks that runtime from `source`, if any, and default model fit on disk.
+/// Fails with [`preflight::PreflightError`] if they don't, warnings mean that little space would be left.
+pub async fn llm_preflight(
+    source: Option<&AssetSource>,
+) -> anyhow::Result<Vec<preflight::SpaceWarning>> {
+    let mut requirements = model_requirements(MODEL_NAME).await?;
+
+    if let Some(source) = source {
+        requirements.extend(runtime_requirements(source).await?);
+    }
+
+    Ok(preflight::check_space(&requirements)?)
+}
+
+/// Installs runtime from `source`. Models shipped in a bundle are copied into model store
+pub async fn llm_download(source: AssetSource, options: DownloadOptions) -> anyhow::Result<()> {
+    install_runtime(&source, &options).await?;
+
+    if let AssetSource::Bundle(bundle) = source {
+        let models_dir = models_dir().await?;
+
+        tokio::task::spawn_blocking(move || download::import_bundle_models(&bundle, &models_dir))
+            .await??;
+    }
+
+    Ok(())
+}
+
+/// Downloads runtime archives from `source` and replaces current install.
+/// Models live outside of runtime dir, so they are kept as is.
+async fn install_runtime(source: &AssetSource, options: &DownloadOptions) -> anyhow::Result<()> {
+    preflight::check_space(&runtime_requirements(source).await?)?;
+
     let cache_dir = get_or_create_app_dir(Some(dirs::cache_dir().expect("invalid os"))).await?;
-    let target_dir = get_or_create_app_dir(None).await?.join(OLLAMA_DATA_DIR);
 
-    let _ollama_location = ollama_download(cache_dir, target_dir).await?;
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    {
+        let target_dir = get_or_create_app_dir(None).await?.join(OLLAMA_DATA_DIR);
+        let ollama_location = ollama_download(cache_dir, target_dir, source, options).await?;
+
+        install::ollama_install(ollama_location).await?;
+    }
+
+    #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+    {
+        let layout = install_layout().await?;
+        let staging_dir = layout.prepare_staging().await?;
+
+        if let Err(e) = ollama_download(cache_dir, staging_dir, source, options).await {
+            let _ = layout.discard_staging().await.inspect_err(|e| {
+                tracing::warn!("Failed to clean up staged ollama install. Reason: {e}")
+            });
+
+            return Err(e.into());
+        }
+
+        let version = layout.smoke_test().await?;
+
+        tracing::info!("Staged ollama reports version {version}. Promoting install");
+
+        // Running backend still points to replaced binary
+        llm_unload().await?;
+
+        layout.commit().await?;
+    }
+
+    Ok(())
+}
+
+/// Returns true if previous ollama install was kept and can be restored
+pub async fn llm_can_rollback() -> anyhow::Result<bool> {
+    #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+    return Ok(install_layout().await?.has_previous().await);
+
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    Ok(false)
+}
+
+/// Swaps current ollama install with previous one. Calling it twice returns to the original install
+pub async fn llm_rollback() -> anyhow::Result<()> {
+    #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+    {
+        let layout = install_layout().await?;
+
+        llm_unload().await?;
+        layout.rollback().await?;
+
+        Ok(())
+    }
 
     #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-    install::ollama_install(_ollama_location).await?;
+    Err(anyhow::anyhow!(
+        "Rollback is not supported for ollama app installs"
+    ))
+}
+
+/// Compares installed runtime with release manifest from settings.
+/// Returns release which should be installed, respecting pinned version.
+pub async fn llm_check_update() -> anyhow::Result<Option<update::Release>> {
+    let settings = settings::load().await?;
+    let installed = managed_version().await?;
+
+    let releases = update::fetch_manifest(&settings.update.manifest_url).await?;
+
+    let release = update::select_update(
+        installed.as_deref(),
+        &releases,
+        settings.update.pinned_version.as_deref(),
+    )?;
+
+    Ok(release.cloned())
+}
+
+/// Installs given version from release manifest in place of current one
+pub async fn llm_upgrade(version: String, options: DownloadOptions) -> anyhow::Result<()> {
+    let settings = settings::load().await?;
+    let releases = update::fetch_manifest(&settings.update.manifest_url).await?;
+
+    let release = releases
+        .iter()
+        .find(|release| release.version_string() == version)
+        .ok_or(update::UpdateError::UnknownVersion(version))?;
+
+    tracing::info!("Upgrading ollama to {}", release.version_string());
+
+    install_runtime(&release.asset_source(), &options).await
+}
+
+/// Keeps runtime on given version. `None` follows latest release again
+pub async fn llm_pin_version(version: Option<String>) -> anyhow::Result<()> {
+    settings::update(|settings| settings.update.pinned_version = version).await?;
+
+    Ok(())
+}
+
+pub async fn llm_set_manifest_url(url: String) -> anyhow::Result<()> {
+    settings::update(|settings| settings.update.manifest_url = url).await?;
 
     Ok(())
 }
 
 pub async fn llm_download_model() -> anyhow::Result<String> {
+    llm_pull_model(MODEL_NAME).await
+}
+
+/// Pulls `model` unless it is already in model store, e.g. one chosen by persona
+pub async fn llm_pull_model(model: &str) -> anyhow::Result<String> {
     if !IS_OLLAMA_LOADED.load(std::sync::atomic::Ordering::SeqCst) {
         return Err(anyhow::anyhow!("You need to start llm engine first"));
     }
 
+    // Offline installs bring models with them and can't reach registry
+    let is_present = OLLAMA_CLIENT
+        .list_local_models()
+        .await?
+        .iter()
+        .any(|local| local.name == model || local.name == format!("{model}:latest"));
+
+    if is_present {
+        return Ok(format!("{model} is already present"));
+    }
+
+    preflight::check_space(&model_requirements(model).await?)?;
+
     let msg = OLLAMA_CLIENT
-        .pull_model(MODEL_NAME.to_owned(), false)
+        .pull_model(model.to_owned(), false)
         .await?
         .message;
 
@@ -114,12 +598,14 @@ pub async fn llm_download_model() -> anyhow::Result<String> {
 }
 
 pub async fn llm_load() -> anyhow::Result<()> {
+    let settings = settings::load().await?;
+    let ollama_bin = selected_binary(&settings).await?;
+    let models_dir = settings.models.store.unwrap_or_else(default_models_dir);
+
     let mut ollama_backend_lock = OLLAMA_BACKEND.lock().expect("POISONED LOCK");
 
     if ollama_backend_lock.is_none() {
-        let ollama_dir = get_or_create_app_dir(None).await?.join(OLLAMA_DATA_DIR);
-
-        let child = ollama_serve(ollama_dir)?;
+        let child = ollama_serve(&ollama_bin, &models_dir)?;
 
         *ollama_backend_lock = Some(child)
     } else {
@@ -131,7 +617,6 @@ pub async fn llm_load() -> anyhow::Result<()> {
     Ok(())
 }
 
-#[allow(dead_code)]
 pub async fn llm_unload() -> anyhow::Result<()> {
     let mut ollama_backend_lock = OLLAMA_BACKEND.lock().expect("POISONED LOCK");
 
@@ -142,15 +627,103 @@ pub async fn llm_unload() -> anyhow::Result<()> {
     Ok(())
 }
 
-pub async fn llm_generate(prompt: String) -> anyhow::Result<String> {
+/// Generates reply of `model` to `prompt`. Parameters missing from `options` take defaults of model
+pub async fn llm_generate(
+    model: &str,
+    prompt: String,
+    system: &str,
+    options: GenerationOptions,
+) -> anyhow::Result<String> {
+    if !IS_OLLAMA_LOADED.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(anyhow::anyhow!("You need to start llm engine first"));
+    }
+
+    let options = options.or(GenerationOptions::for_model(model));
+    let mut request =
+        GenerationRequest::new(model.to_owned(), prompt).options(options.model_options());
+    if !system.trim().is_empty() {
+        request = request.system(system);
+    }
+
+    let res = OLLAMA_CLIENT.generate(request).await?.response;
+
+    Ok(res)
+}
+
+/// Message of chat sent to model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatTurn {
+    pub role: Role,
+    pub content: String,
+    /// Content of image files, only models with vision support see them
+    pub images: Vec<Vec<u8>>,
+}
+
+impl ChatTurn {
+    pub fn new(role: Role, content: String) -> Self {
+        Self {
+            role,
+            content,
+            images: Vec::new(),
+        }
+    }
+}
+
+/// Generates next message of `model` in chat made of `messages`, oldest first
+pub async fn llm_chat(
+    model: &str,
+    messages: Vec<ChatTurn>,
+    options: GenerationOptions,
+) -> anyhow::Result<String> {
     if !IS_OLLAMA_LOADED.load(std::sync::atomic::Ordering::SeqCst) {
         return Err(anyhow::anyhow!("You need to start llm engine first"));
     }
 
+    let messages = messages
+        .into_iter()
+        .map(|turn| {
+            let message = match turn.role {
+                Role::System => ChatMessage::system(turn.content),
+                Role::User => ChatMessage::user(turn.content),
+                Role::Assistant => ChatMessage::assistant(turn.content),
+            };
+
+            if turn.images.is_empty() {
+                message
+            } else {
+                message.with_images(
+                    turn.images
+                        .iter()
+                        .map(|data| Image::from_base64(BASE64_STANDARD.encode(data)))
+                        .collect(),
+                )
+            }
+        })
+        .collect();
+
+    let options = options.or(GenerationOptions::for_model(model));
+    let request =
+        ChatMessageRequest::new(model.to_owned(), messages).options(options.model_options());
+
     let res = OLLAMA_CLIENT
-        .generate(GenerationRequest::new(MODEL_NAME.to_owned(), prompt))
+        .send_chat_messages(request)
         .await?
-        .response;
+        .message
+        .content;
 
     Ok(res)
 }
+
+/// Whether `model` advertises it can see images, model must be pulled
+pub async fn llm_supports_vision(model: &str) -> anyhow::Result<bool> {
+    if !IS_OLLAMA_LOADED.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(anyhow::anyhow!("You need to start llm engine first"));
+    }
+
+    let info = OLLAMA_CLIENT.show_model_info(model.to_owned()).await?;
+
+    Ok(info
+        .capabilities
+        .iter()
+        .any(|capability| capability == "vision"))
+}
@@ -0,0 +1,186 @@
+//! Generation parameters sent with every request of conversation
+
+use ollama_rs::models::ModelOptions;
+use serde::{Deserialize, Serialize};
+
+/// Names of numeric parameters, in order they are shown
+pub const PARAMETERS: [&str; 7] = [
+    "temperature",
+    "top_p",
+    "top_k",
+    "seed",
+    "num_ctx",
+    "num_predict",
+    "repeat_penalty",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum OptionsError {
+    #[error("{name} must be a number, got {value:?}")]
+    NotNumber { name: String, value: String },
+    #[error("Unknown parameter {0}")]
+    Unknown(String),
+}
+
+/// Parameters of generation, `None` leaves choice to model defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationOptions {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    pub seed: Option<i32>,
+    /// Context window in tokens
+    pub num_ctx: Option<u64>,
+    /// Longest reply in tokens, -1 for no limit
+    pub num_predict: Option<i32>,
+    pub repeat_penalty: Option<f32>,
+    /// Generation ends at any of these
+    pub stop: Vec<String>,
+}
+
+fn parse<T: std::str::FromStr>(name: &str, value: &str) -> Result<Option<T>, OptionsError> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| OptionsError::NotNumber {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        })
+}
+
+fn show<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+impl GenerationOptions {
+    /// Recommended parameters of model family, e.g. `gemma3` for `gemma3:1b`
+    pub fn for_model(model: &str) -> Self {
+        let family = model.split(':').next().unwrap_or(model);
+
+        let (temperature, top_p, top_k) = match family {
+            family if family.starts_with("gemma") => (1.0, 0.95, 64),
+            family if family.starts_with("llama") => (0.6, 0.9, 40),
+            family if family.starts_with("qwen") => (0.7, 0.8, 20),
+            family if family.starts_with("mistral") => (0.7, 0.95, 40),
+            _ => (0.8, 0.9, 40),
+        };
+
+        Self {
+            temperature: Some(temperature),
+            top_p: Some(top_p),
+            top_k: Some(top_k),
+            num_ctx: Some(4096),
+            repeat_penalty: Some(1.1),
+            ..Self::default()
+        }
+    }
+
+    /// Parameters set here, others taken from `defaults`
+    pub fn or(self, defaults: Self) -> Self {
+        Self {
+            temperature: self.temperature.or(defaults.temperature),
+            top_p: self.top_p.or(defaults.top_p),
+            top_k: self.top_k.or(defaults.top_k),
+            seed: self.seed.or(defaults.seed),
+            num_ctx: self.num_ctx.or(defaults.num_ctx),
+            num_predict: self.num_predict.or(defaults.num_predict),
+            repeat_penalty: self.repeat_penalty.or(defaults.repeat_penalty),
+            stop: if self.stop.is_empty() {
+                defaults.stop
+            } else {
+                self.stop
+            },
+        }
+    }
+
+    /// Value of parameter from [`PARAMETERS`] as text, empty if it is not set
+    pub fn get(&self, name: &str) -> String {
+        match name {
+            "temperature" => show(self.temperature),
+            "top_p" => show(self.top_p),
+            "top_k" => show(self.top_k),
+            "seed" => show(self.seed),
+            "num_ctx" => show(self.num_ctx),
+            "num_predict" => show(self.num_predict),
+            "repeat_penalty" => show(self.repeat_penalty),
+            _ => String::new(),
+        }
+    }
+
+    /// Sets parameter from [`PARAMETERS`], empty value unsets it
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), OptionsError> {
+        let value = value.trim();
+
+        match name {
+            "temperature" => self.temperature = parse(name, value)?,
+            "top_p" => self.top_p = parse(name, value)?,
+            "top_k" => self.top_k = parse(name, value)?,
+            "seed" => self.seed = parse(name, value)?,
+            "num_ctx" => self.num_ctx = parse(name, value)?,
+            "num_predict" => self.num_predict = parse(name, value)?,
+            "repeat_penalty" => self.repeat_penalty = parse(name, value)?,
+            _ => return Err(OptionsError::Unknown(name.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    pub fn model_options(&self) -> ModelOptions {
+        let mut options = ModelOptions::default();
+
+        if let Some(temperature) = self.temperature {
+            options = options.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            options = options.top_p(top_p);
+        }
+        if let Some(top_k) = self.top_k {
+            options = options.top_k(top_k);
+        }
+        if let Some(seed) = self.seed {
+            options = options.seed(seed);
+        }
+        if let Some(num_ctx) = self.num_ctx {
+            options = options.num_ctx(num_ctx);
+        }
+        if let Some(num_predict) = self.num_predict {
+            options = options.num_predict(num_predict);
+        }
+        if let Some(repeat_penalty) = self.repeat_penalty {
+            options = options.repeat_penalty(repeat_penalty);
+        }
+        if !self.stop.is_empty() {
+            options = options.stop(self.stop.clone());
+        }
+
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_values_override_model_defaults() {
+        let mut options = GenerationOptions::default();
+        options.set("temperature", "0.2").unwrap();
+        options.set("seed", " 7 ").unwrap();
+        assert!(options.set("top_k", "many").is_err());
+
+        let merged = options.or(GenerationOptions::for_model("gemma3:1b"));
+        assert_eq!(merged.temperature, Some(0.2));
+        assert_eq!(merged.seed, Some(7));
+        assert_eq!(merged.top_k, Some(64));
+        assert_eq!(merged.get("top_p"), "0.95");
+
+        let mut merged = merged;
+        merged.set("temperature", "").unwrap();
+        assert_eq!(merged.temperature, None);
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::error::BetterIoError;
+
+mod model;
+
+pub use model::model_download_size;
+
+/// Install is allowed, but user is warned if less than this would be left on disk
+pub const LOW_SPACE_MARGIN: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PreflightError {
+    #[error(transparent)]
+    Io(#[from] BetterIoError),
+    #[error(
+        "Not enough disk space on {} for {purpose}. Required {} MB, available {} MB",
+        dir.display(),
+        megabytes(*required),
+        megabytes(*available)
+    )]
+    NotEnoughSpace {
+        dir: PathBuf,
+        purpose: String,
+        required: u64,
+        available: u64,
+    },
+}
+
+/// Bytes which are going to be written into `dir`
+#[derive(Debug, Clone)]
+pub struct SpaceRequirement {
+    pub dir: PathBuf,
+    pub purpose: &'static str,
+    pub bytes: u64,
+}
+
+/// Write fits, but leaves less than [`LOW_SPACE_MARGIN`] on disk
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "Low disk space on {}: {purpose} needs {} MB, only {} MB would be left",
+    dir.display(),
+    megabytes(*required),
+    megabytes(available - required)
+)]
+pub struct SpaceWarning {
+    pub dir: PathBuf,
+    pub purpose: String,
+    pub required: u64,
+    pub available: u64,
+}
+
+fn megabytes(bytes: u64) -> u64 {
+    bytes / 1024 / 1024
+}
+
+/// Directories are often created by the install itself, so look at the closest existing one
+fn existing_ancestor(dir: &Path) -> &Path {
+    dir.ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(dir)
+}
+
+/// Requirements with equal key are written to the same filesystem
+#[cfg(unix)]
+fn filesystem_key(dir: &Path) -> Result<String, BetterIoError> {
+    use std::os::unix::fs::MetadataExt;
+
+    let meta = std::fs::metadata(dir)
+        .map_err(|e| BetterIoError::new(dir, "checking filesystem of directory", e))?;
+
+    Ok(meta.dev().to_string())
+}
+
+/// Requirements with equal key are written to the same filesystem
+#[cfg(not(unix))]
+fn filesystem_key(dir: &Path) -> Result<String, BetterIoError> {
+    Ok(dir
+        .components()
+        .next()
+        .map(|root| root.as_os_str().to_string_lossy().to_lowercase())
+        .unwrap_or_default())
+}
+
+/// Compares requirements with free space. Requirements which land on one filesystem are summed up.
+///
+/// Blocking, but cheap enough to call from async code.
+pub fn check_space(requirements: &[SpaceRequirement]) -> Result<Vec<SpaceWarning>, PreflightError> {
+    let mut filesystems: HashMap<String, (PathBuf, Vec<&'static str>, u64)> = HashMap::new();
+
+    for requirement in requirements.iter().filter(|this| this.bytes > 0) {
+        let dir = existing_ancestor(&requirement.dir);
+        let (_, purposes, bytes) = filesystems
+            .entry(filesystem_key(dir)?)
+            .or_insert_with(|| (dir.to_path_buf(), Vec::new(), 0));
+
+        if !purposes.contains(&requirement.purpose) {
+            purposes.push(requirement.purpose);
+        }
+        *bytes += requirement.bytes;
+    }
+
+    let mut warnings = Vec::new();
+
+    for (dir, purposes, required) in filesystems.into_values() {
+        let available = fs4::available_space(&dir)
+            .map_err(|e| BetterIoError::new(&dir, "checking free disk space", e))?;
+        let purpose = purposes.join(" and ");
+
+        if available < required {
+            return Err(PreflightError::NotEnoughSpace {
+                dir,
+                purpose,
+                required,
+                available,
+            });
+        }
+
+        if available - required < LOW_SPACE_MARGIN {
+            let warning = SpaceWarning {
+                dir,
+                purpose,
+                required,
+                available,
+            };
+            tracing::warn!("{warning}");
+
+            warnings.push(warning);
+        }
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_when_requirement_exceeds_free_space() {
+        let scratch = tempfile::tempdir().unwrap();
+
+        let res = check_space(&[SpaceRequirement {
+            dir: scratch.path().join("not").join("created"),
+            purpose: "runtime",
+            bytes: u64::MAX / 2,
+        }]);
+
+        assert!(matches!(res, Err(PreflightError::NotEnoughSpace { .. })));
+    }
+
+    #[test]
+    fn sums_requirements_on_same_filesystem() {
+        let scratch = tempfile::tempdir().unwrap();
+        let available = fs4::available_space(scratch.path()).unwrap();
+
+        let requirement = |purpose| SpaceRequirement {
+            dir: scratch.path().to_path_buf(),
+            purpose,
+            bytes: available / 3 * 2,
+        };
+
+        let res = check_space(&[requirement("archives"), requirement("runtime")]);
+
+        match res {
+            Err(PreflightError::NotEnoughSpace { purpose, .. }) => {
+                assert_eq!(purpose, "archives and runtime")
+            }
+            res => panic!("expected to be blocked, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn empty_requirements_pass() {
+        let scratch = tempfile::tempdir().unwrap();
+
+        let res = check_space(&[SpaceRequirement {
+            dir: scratch.path().to_path_buf(),
+            purpose: "model",
+            bytes: 0,
+        }]);
+
+        assert!(res.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use crate::core::llm::store::{Manifest, blob_location};
+
+const REGISTRY_URL: &str = "https://registry.ollama.ai/v2";
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+
+/// `gemma3:1b` lives at `library/gemma3/manifests/1b`
+fn manifest_url(name: &str) -> String {
+    let (repository, tag) = name.split_once(':').unwrap_or((name, "latest"));
+
+    match repository.contains('/') {
+        true => format!("{REGISTRY_URL}/{repository}/manifests/{tag}"),
+        false => format!("{REGISTRY_URL}/library/{repository}/manifests/{tag}"),
+    }
+}
+
+/// Bytes which pull of `name` writes into model store, blobs already present are not counted.
+/// `None` if registry can't be reached, e.g. on offline installs.
+pub async fn model_download_size(name: &str, models_dir: &Path) -> Option<u64> {
+    let response = reqwest::Client::new()
+        .get(manifest_url(name))
+        .header(reqwest::header::ACCEPT, MANIFEST_MEDIA_TYPE)
+        .send()
+        .await
+        .inspect_err(|e| tracing::debug!("Failed to request manifest of {name}. Reason: {e}"))
+        .ok()?;
+
+    if !response.status().is_success() {
+        tracing::debug!(
+            "Manifest request of {name} failed with status {}",
+            response.status()
+        );
+
+        return None;
+    }
+
+    let body = response
+        .bytes()
+        .await
+        .inspect_err(|e| tracing::debug!("Failed to read manifest of {name}. Reason: {e}"))
+        .ok()?;
+
+    let manifest = serde_json::from_slice::<Manifest>(&body)
+        .inspect_err(|e| tracing::debug!("Malformed manifest of {name}. Reason: {e}"))
+        .ok()?;
+
+    let size = manifest
+        .blobs()
+        .filter(|layer| {
+            !std::fs::metadata(blob_location(models_dir, &layer.digest))
+                .is_ok_and(|meta| meta.len() == layer.size)
+        })
+        .map(|layer| layer.size)
+        .sum();
+
+    Some(size)
+}
@@ -1,4 +1,7 @@
-use std::{path::Path, process::Stdio};
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+};
 
 use tokio::process::Child;
 
@@ -18,16 +21,29 @@ pub use macos::*;
 #[cfg(target_os = "windows")]
 pub use windows::*;
 
-pub(super) fn ollama_serve(ollama_dir: impl AsRef<Path>) -> Result<Child, BetterIoError> {
-    let bin = ollama_binary_location(ollama_dir.as_ref());
+#[cfg(target_os = "windows")]
+const SYSTEM_BINARY_NAME: &str = "ollama.exe";
+#[cfg(not(target_os = "windows"))]
+const SYSTEM_BINARY_NAME: &str = "ollama";
+
+/// Looks up ollama in `$PATH` the same way shell does. None means there is no system install
+pub fn system_binary_location() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(SYSTEM_BINARY_NAME))
+        .find(|candidate| candidate.is_file())
+}
 
+pub(super) fn ollama_serve(bin: &Path, models_dir: &Path) -> Result<Child, BetterIoError> {
     tracing::info!("starting ollama binary in - {}", bin.display());
 
     tokio::process::Command::new(bin)
         .kill_on_drop(true)
         .arg("serve")
+        .env("OLLAMA_MODELS", models_dir)
         .stderr(Stdio::inherit())
         .stdout(Stdio::piped())
         .spawn()
-        .map_err(|e| BetterIoError::new(ollama_dir.as_ref(), "start of ollama binary", e))
+        .map_err(|e| BetterIoError::new(bin, "start of ollama binary", e))
 }
@@ -0,0 +1,268 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::{
+    core::llm::store::{MigrationError, collect_files},
+    error::BetterIoError,
+};
+
+/// Registry used for model names without explicit host
+const DEFAULT_REGISTRY: &str = "registry.ollama.ai";
+/// Namespace used for model names without explicit owner
+const DEFAULT_NAMESPACE: &str = "library";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ManifestLayer {
+    pub digest: String,
+    pub size: u64,
+}
+
+/// Ollama model manifest, in docker distribution format
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Manifest {
+    pub config: ManifestLayer,
+    pub layers: Vec<ManifestLayer>,
+}
+
+impl Manifest {
+    /// Config and layers, each of them is a blob in store
+    pub fn blobs(&self) -> impl Iterator<Item = &ManifestLayer> {
+        std::iter::once(&self.config).chain(&self.layers)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.blobs().map(|blob| blob.size).sum()
+    }
+}
+
+/// Model found in a store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredModel {
+    /// Name as ollama shows it, e.g. `gemma3:1b`
+    pub name: String,
+    /// Path of manifest relative to store root
+    pub manifest_path: PathBuf,
+    pub manifest: Manifest,
+}
+
+/// Ollama stores `sha256:<hex>` blob as `blobs/sha256-<hex>`
+pub(crate) fn blob_location(store: &Path, digest: &str) -> PathBuf {
+    store.join("blobs").join(digest.replace(':', "-"))
+}
+
+/// `manifests/registry.ollama.ai/library/gemma3/1b` becomes `gemma3:1b`
+fn model_name(relative: &Path) -> Option<String> {
+    let parts = relative
+        .iter()
+        .map(|part| part.to_str())
+        .collect::<Option<Vec<_>>>()?;
+
+    let [registry, namespace, model, tag] = parts[..] else {
+        return None;
+    };
+
+    let name = match (registry, namespace) {
+        (DEFAULT_REGISTRY, DEFAULT_NAMESPACE) => format!("{model}:{tag}"),
+        (DEFAULT_REGISTRY, namespace) => format!("{namespace}/{model}:{tag}"),
+        (registry, namespace) => format!("{registry}/{namespace}/{model}:{tag}"),
+    };
+
+    Some(name)
+}
+
+/// Lists models of store at `root`. Unreadable manifests are skipped with a warning
+pub fn list_models(root: &Path) -> Result<Vec<StoredModel>, BetterIoError> {
+    let manifests_dir = root.join("manifests");
+
+    let mut files = Vec::new();
+    collect_files(&manifests_dir, &mut files)?;
+
+    let mut models = Vec::with_capacity(files.len());
+
+    for file in files {
+        let relative = file
+            .strip_prefix(&manifests_dir)
+            .expect("collected from inside of manifests dir");
+
+        let Some(name) = model_name(relative) else {
+            tracing::warn!("Skipping unexpected manifest {}", file.display());
+            continue;
+        };
+
+        let content = std::fs::read(&file)
+            .map_err(|e| BetterIoError::new(&file, "reading model manifest", e))?;
+
+        match serde_json::from_slice::<Manifest>(&content) {
+            Ok(manifest) => models.push(StoredModel {
+                name,
+                manifest_path: file
+                    .strip_prefix(root)
+                    .expect("collected from inside of store")
+                    .to_path_buf(),
+                manifest,
+            }),
+            Err(e) => tracing::warn!(
+                "Skipping malformed manifest {}. Reason: {e}",
+                file.display()
+            ),
+        }
+    }
+
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(models)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub models: usize,
+    /// Blobs written into target store
+    pub copied_blobs: usize,
+    /// Blobs already present in target store or shared by several imported models
+    pub reused_blobs: usize,
+}
+
+/// Copies `models` from store at `source` into store at `target`.
+/// Every blob is copied once, blobs already present in `target` are reused. Hard links are used where possible.
+///
+/// Blocking, run it on blocking thread.
+pub fn import_models(
+    source: &Path,
+    target: &Path,
+    models: &[StoredModel],
+    progress: impl Fn(super::StoreProgress),
+) -> Result<ImportReport, MigrationError> {
+    let mut report = ImportReport::default();
+    let mut seen = std::collections::HashSet::new();
+
+    let blobs = models
+        .iter()
+        .flat_map(|model| model.manifest.blobs())
+        .filter(|blob| seen.insert(blob.digest.as_str()))
+        .collect::<Vec<_>>();
+    report.reused_blobs = models
+        .iter()
+        .map(|model| model.manifest.blobs().count())
+        .sum::<usize>()
+        - blobs.len();
+
+    let mut state = super::StoreProgress {
+        done: 0,
+        total: blobs.iter().map(|blob| blob.size).sum(),
+    };
+    progress(state);
+
+    for blob in blobs {
+        let from = blob_location(source, &blob.digest);
+        let to = blob_location(target, &blob.digest);
+
+        if std::fs::metadata(&to).is_ok_and(|meta| meta.len() == blob.size) {
+            report.reused_blobs += 1;
+        } else {
+            super::copy_verified(&from, &to)?;
+            report.copied_blobs += 1;
+        }
+
+        state.done += blob.size;
+        progress(state);
+    }
+
+    // Blobs are in place, manifests make models visible to ollama.
+    // Ollama rewrites manifests on pull, so they are never shared through a link
+    for model in models {
+        let from = source.join(&model.manifest_path);
+        let to = target.join(&model.manifest_path);
+
+        super::copy_with_progress(&from, &to, |_| ())?;
+        report.models += 1;
+    }
+
+    tracing::info!(
+        "Imported {} models from {}: {} blobs copied, {} reused",
+        report.models,
+        source.display(),
+        report.copied_blobs,
+        report.reused_blobs
+    );
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_follow_ollama_format() {
+        assert_eq!(
+            model_name(Path::new("registry.ollama.ai/library/gemma3/1b")).as_deref(),
+            Some("gemma3:1b")
+        );
+        assert_eq!(
+            model_name(Path::new("registry.ollama.ai/user/model/latest")).as_deref(),
+            Some("user/model:latest")
+        );
+        assert_eq!(
+            model_name(Path::new("hf.co/org/model/q4")).as_deref(),
+            Some("hf.co/org/model:q4")
+        );
+        assert_eq!(model_name(Path::new("gemma3/1b")), None);
+    }
+
+    fn write_blob(store: &Path, digest: &str, content: &str) {
+        let location = blob_location(store, digest);
+        std::fs::create_dir_all(location.parent().unwrap()).unwrap();
+        std::fs::write(location, content).unwrap();
+    }
+
+    fn write_manifest(store: &Path, model: &str, layers: &[&str]) {
+        let layer = |digest: &&str| format!(r#"{{"digest":"{digest}","size":4}}"#);
+        let manifest = format!(
+            r#"{{"config":{{"digest":"sha256:config","size":4}},"layers":[{}]}}"#,
+            layers.iter().map(layer).collect::<Vec<_>>().join(",")
+        );
+
+        let location = store
+            .join("manifests/registry.ollama.ai/library")
+            .join(model);
+        std::fs::create_dir_all(&location).unwrap();
+        std::fs::write(location.join("latest"), manifest).unwrap();
+    }
+
+    #[test]
+    fn import_copies_shared_blobs_once() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let source = dir.path().join("source");
+        let target = dir.path().join("target");
+
+        for digest in ["sha256:config", "sha256:first", "sha256:second"] {
+            write_blob(&source, digest, "blob");
+        }
+        write_manifest(&source, "first", &["sha256:first"]);
+        write_manifest(&source, "second", &["sha256:second"]);
+        write_blob(&target, "sha256:second", "blob");
+
+        let models = list_models(&source).unwrap();
+        assert_eq!(
+            models
+                .iter()
+                .map(|model| model.name.as_str())
+                .collect::<Vec<_>>(),
+            ["first:latest", "second:latest"]
+        );
+
+        let report = import_models(&source, &target, &models, |_| ()).unwrap();
+
+        assert_eq!(
+            report,
+            ImportReport {
+                models: 2,
+                copied_blobs: 2,
+                reused_blobs: 2,
+            }
+        );
+        assert_eq!(list_models(&target).unwrap(), models);
+    }
+}
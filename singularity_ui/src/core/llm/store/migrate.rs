@@ -0,0 +1,262 @@
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    core::llm::store::{STORE_PARTS, blob_digest, file_sha256, store_files},
+    error::BetterIoError,
+};
+
+/// Report progress at least every this many copied bytes
+const REPORT_STEP: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error(transparent)]
+    Io(#[from] BetterIoError),
+    #[error("Copy of {} does not match original", .0.display())]
+    Verification(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StoreProgress {
+    /// Processed bytes. Migration counts each file twice, for copy and for verification
+    pub done: u64,
+    pub total: u64,
+}
+
+impl StoreProgress {
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 0.0;
+        }
+
+        (self.done as f64 / self.total as f64).min(1.0) as f32
+    }
+}
+
+fn file_size(location: &Path) -> Result<u64, BetterIoError> {
+    Ok(std::fs::metadata(location)
+        .map_err(|e| BetterIoError::new(location, "reading size of model file", e))?
+        .len())
+}
+
+/// Blobs are checked against digest in their name, other files by size
+fn is_valid_copy(original: &Path, copy: &Path) -> Result<bool, BetterIoError> {
+    if file_size(original)? != file_size(copy)? {
+        return Ok(false);
+    }
+
+    match blob_digest(original) {
+        Some(digest) => Ok(file_sha256(copy)? == digest),
+        None => Ok(true),
+    }
+}
+
+fn copy_file(from: &Path, to: &Path, mut copied: impl FnMut(u64)) -> Result<(), BetterIoError> {
+    let mut reader =
+        std::fs::File::open(from).map_err(|e| BetterIoError::new(from, "opening model file", e))?;
+    let mut writer =
+        std::fs::File::create(to).map_err(|e| BetterIoError::new(to, "creating model file", e))?;
+
+    let mut buf = vec![0; 1024 * 1024];
+    let mut unreported = 0;
+
+    loop {
+        let read = reader
+            .read(&mut buf)
+            .map_err(|e| BetterIoError::new(from, "reading model file", e))?;
+
+        if read == 0 {
+            break;
+        }
+
+        writer
+            .write_all(&buf[..read])
+            .map_err(|e| BetterIoError::new(to, "writing model file", e))?;
+
+        unreported += read as u64;
+        if unreported >= REPORT_STEP {
+            copied(unreported);
+            unreported = 0;
+        }
+    }
+
+    writer
+        .sync_all()
+        .map_err(|e| BetterIoError::new(to, "flushing model file", e))?;
+
+    copied(unreported);
+
+    Ok(())
+}
+
+/// Copies `from` under temporary name, verifies copy and moves it to `to`
+pub(super) fn copy_with_progress(
+    from: &Path,
+    to: &Path,
+    copied: impl FnMut(u64),
+) -> Result<(), MigrationError> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| BetterIoError::new(parent, "creating model store dir", e))?;
+    }
+
+    let mut partial = to.to_path_buf().into_os_string();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    copy_file(from, &partial, copied)?;
+
+    if !is_valid_copy(from, &partial)? {
+        let _ = std::fs::remove_file(&partial);
+
+        return Err(MigrationError::Verification(from.to_path_buf()));
+    }
+
+    std::fs::rename(&partial, to).map_err(|e| BetterIoError::new(to, "moving model file", e))?;
+
+    Ok(())
+}
+
+/// Hard links `from` to `to` when both are on the same filesystem, copies and verifies it otherwise.
+/// Only for blobs, which are never rewritten in place
+pub(super) fn copy_verified(from: &Path, to: &Path) -> Result<(), MigrationError> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| BetterIoError::new(parent, "creating model store dir", e))?;
+    }
+
+    // Stale file of another size would block the link
+    let _ = std::fs::remove_file(to);
+
+    if std::fs::hard_link(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy_with_progress(from, to, |_| ())
+}
+
+/// Copies model store from `source` into `target`, verifies every copy and only then removes originals.
+/// Files already present in `target` are kept if they match. Interrupted migration leaves `source` intact.
+///
+/// Blocking, run it on blocking thread.
+pub fn migrate(
+    source: &Path,
+    target: &Path,
+    progress: impl Fn(StoreProgress),
+) -> Result<(), MigrationError> {
+    let files = store_files(source)?;
+
+    let mut sizes = Vec::with_capacity(files.len());
+    for file in &files {
+        sizes.push(file_size(file)?);
+    }
+
+    let mut state = StoreProgress {
+        done: 0,
+        total: sizes.iter().sum::<u64>() * 2,
+    };
+    progress(state);
+
+    for (file, size) in files.iter().zip(sizes) {
+        let relative = file
+            .strip_prefix(source)
+            .expect("collected from inside of source");
+        let destination = target.join(relative);
+
+        if std::fs::metadata(&destination).is_err() || !is_valid_copy(file, &destination)? {
+            copy_with_progress(file, &destination, |copied| {
+                state.done += copied;
+                progress(state);
+            })?;
+        } else {
+            state.done += size;
+        }
+
+        state.done += size;
+        progress(state);
+    }
+
+    // Everything is verified, originals can go
+    for part in STORE_PARTS {
+        let dir = source.join(part);
+
+        match std::fs::remove_dir_all(&dir) {
+            Ok(()) => (),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => return Err(BetterIoError::new(&dir, "removing old model store", e).into()),
+        }
+    }
+
+    tracing::info!(
+        "Moved {} model files from {} to {}",
+        files.len(),
+        source.display(),
+        target.display()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const HELLO_DIGEST: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    fn scratch_store() -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+
+        let source = dir.path().join("source");
+        std::fs::create_dir_all(source.join("blobs")).unwrap();
+        std::fs::create_dir_all(source.join("manifests/library/model")).unwrap();
+        std::fs::write(source.join("manifests/library/model/latest"), "{}").unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn moves_store_and_removes_originals() {
+        let dir = scratch_store();
+        let source = dir.path().join("source");
+        let target = dir.path().join("target");
+        std::fs::write(
+            source.join("blobs").join(format!("sha256-{HELLO_DIGEST}")),
+            "hello",
+        )
+        .unwrap();
+
+        let last = std::cell::Cell::new(None);
+        migrate(&source, &target, |progress| last.set(Some(progress))).unwrap();
+
+        let last = last.get().unwrap();
+        assert_eq!(last.done, last.total);
+        assert_eq!(
+            std::fs::read_to_string(target.join("blobs").join(format!("sha256-{HELLO_DIGEST}")))
+                .unwrap(),
+            "hello"
+        );
+        assert!(target.join("manifests/library/model/latest").is_file());
+        assert!(!source.join("blobs").exists());
+        assert!(!source.join("manifests").exists());
+    }
+
+    #[test]
+    fn corrupt_blob_keeps_source() {
+        let dir = scratch_store();
+        let source = dir.path().join("source");
+        let target = dir.path().join("target");
+        let blob = source.join("blobs").join(format!("sha256-{HELLO_DIGEST}"));
+        std::fs::write(&blob, "jello").unwrap();
+
+        let res = migrate(&source, &target, |_| ());
+
+        assert!(matches!(res, Err(MigrationError::Verification(_))));
+        assert!(blob.is_file());
+        assert!(source.join("manifests/library/model/latest").is_file());
+    }
+}
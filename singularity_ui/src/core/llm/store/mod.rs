@@ -0,0 +1,86 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::BetterIoError;
+
+mod manifest;
+mod migrate;
+mod verify;
+
+pub(crate) use manifest::blob_location;
+pub use manifest::{ImportReport, Manifest, StoredModel, import_models, list_models};
+pub use migrate::{MigrationError, StoreProgress, migrate};
+use migrate::{copy_verified, copy_with_progress};
+pub use verify::{StoreReport, remove_corrupt_blobs, remove_orphans, verify};
+
+/// Blobs go first, so a manifest never points to a blob which is not copied yet
+const STORE_PARTS: &[&str] = &["blobs", "manifests"];
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), BetterIoError> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(BetterIoError::new(dir, "listing model store", e)),
+    };
+
+    for entry in entries {
+        let entry = entry.map_err(|e| BetterIoError::new(dir, "listing model store", e))?;
+        let file_type = entry
+            .file_type()
+            .map_err(|e| BetterIoError::new(entry.path(), "checking model store file", e))?;
+
+        if file_type.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+/// Every file of ollama model store under `root`, blobs first
+pub(crate) fn store_files(root: &Path) -> Result<Vec<PathBuf>, BetterIoError> {
+    let mut files = Vec::new();
+    for part in STORE_PARTS {
+        collect_files(&root.join(part), &mut files)?;
+    }
+
+    Ok(files)
+}
+
+/// Blobs are named `sha256-<hex>` after their content
+pub(crate) fn blob_digest(blob: &Path) -> Option<&str> {
+    blob.file_name()?.to_str()?.strip_prefix("sha256-")
+}
+
+/// Hex encoded SHA-256 of file content
+pub(crate) fn file_sha256(location: &Path) -> Result<String, BetterIoError> {
+    let mut file = std::fs::File::open(location)
+        .map_err(|e| BetterIoError::new(location, "opening file for hashing", e))?;
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 1024 * 1024];
+
+    loop {
+        let read = file
+            .read(&mut buf)
+            .map_err(|e| BetterIoError::new(location, "reading file for hashing", e))?;
+
+        if read == 0 {
+            break;
+        }
+
+        hasher.update(&buf[..read]);
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
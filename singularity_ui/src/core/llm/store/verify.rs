@@ -0,0 +1,263 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    core::llm::store::{StoreProgress, blob_location, file_sha256, list_models},
+    error::BetterIoError,
+};
+
+/// Result of [`verify`]. Models are listed by name, as ollama shows them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoreReport {
+    /// Referenced blobs which are not in store, with models using them
+    pub missing: Vec<(String, Vec<String>)>,
+    /// Blobs which content does not match their digest, with models using them
+    pub corrupt: Vec<(String, Vec<String>)>,
+    /// Blob files not referenced by any manifest
+    pub orphaned: Vec<PathBuf>,
+    pub checked_models: usize,
+}
+
+impl StoreReport {
+    /// Models which fail to load and need to be pulled again
+    pub fn broken_models(&self) -> Vec<String> {
+        self.missing
+            .iter()
+            .chain(&self.corrupt)
+            .flat_map(|(_, models)| models.iter().cloned())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty() && self.orphaned.is_empty()
+    }
+}
+
+/// Digest of blob, mapped to its size and models using it
+type ReferencedBlobs = HashMap<String, (u64, Vec<String>)>;
+
+/// Blobs referenced by manifests and amount of manifests
+fn referenced_blobs(root: &Path) -> Result<(ReferencedBlobs, usize), BetterIoError> {
+    let models = list_models(root)?;
+    let mut blobs = ReferencedBlobs::new();
+
+    for model in &models {
+        for blob in model.manifest.blobs() {
+            blobs
+                .entry(blob.digest.clone())
+                .or_insert_with(|| (blob.size, Vec::new()))
+                .1
+                .push(model.name.clone());
+        }
+    }
+
+    Ok((blobs, models.len()))
+}
+
+/// Files in blobs dir which no manifest points to, including leftovers of interrupted pulls
+/// Marks blobs of unfinished pull. Ollama references them only once the pull is done
+const PARTIAL_BLOB_MARKER: &str = "-partial";
+
+fn orphaned_blobs(
+    root: &Path,
+    referenced: &ReferencedBlobs,
+) -> Result<Vec<PathBuf>, BetterIoError> {
+    let referenced = referenced
+        .keys()
+        .map(|digest| blob_location(root, digest))
+        .collect::<std::collections::HashSet<_>>();
+
+    let blobs_dir = root.join("blobs");
+    let entries = match std::fs::read_dir(&blobs_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(BetterIoError::new(&blobs_dir, "listing model blobs", e)),
+    };
+
+    let mut orphaned = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| BetterIoError::new(&blobs_dir, "listing model blobs", e))?;
+
+        let is_partial = entry
+            .file_name()
+            .to_string_lossy()
+            .contains(PARTIAL_BLOB_MARKER);
+
+        if !is_partial && !referenced.contains(&entry.path()) {
+            orphaned.push(entry.path());
+        }
+    }
+
+    orphaned.sort();
+
+    Ok(orphaned)
+}
+
+/// Re-hashes every blob referenced by manifests of store at `root`. Progress counts hashed bytes.
+///
+/// Blocking, run it on blocking thread.
+pub fn verify(root: &Path, progress: impl Fn(StoreProgress)) -> Result<StoreReport, BetterIoError> {
+    let (referenced, checked_models) = referenced_blobs(root)?;
+
+    let mut report = StoreReport {
+        orphaned: orphaned_blobs(root, &referenced)?,
+        checked_models,
+        ..Default::default()
+    };
+
+    let mut state = StoreProgress {
+        done: 0,
+        total: referenced.values().map(|(size, _)| size).sum(),
+    };
+    progress(state);
+
+    let mut digests = referenced.into_iter().collect::<Vec<_>>();
+    digests.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (digest, (size, models)) in digests {
+        let location = blob_location(root, &digest);
+
+        match std::fs::metadata(&location) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                report.missing.push((digest, models));
+            }
+            Err(e) => return Err(BetterIoError::new(location, "checking model blob", e)),
+            Ok(meta) => {
+                let expected = digest.strip_prefix("sha256:").unwrap_or(&digest);
+
+                if meta.len() != size || file_sha256(&location)? != expected {
+                    tracing::warn!("Blob {digest} does not match its digest");
+
+                    report.corrupt.push((digest, models));
+                }
+            }
+        }
+
+        state.done += size;
+        progress(state);
+    }
+
+    tracing::info!(
+        "Verified {} models: {} missing, {} corrupt and {} orphaned blobs",
+        report.checked_models,
+        report.missing.len(),
+        report.corrupt.len(),
+        report.orphaned.len()
+    );
+
+    Ok(report)
+}
+
+/// Removes blobs which no manifest points to. Orphans are looked up again, so blobs of models added since
+/// last [`verify`] are kept. Returns amount of freed bytes.
+///
+/// Blocking, run it on blocking thread.
+pub fn remove_orphans(root: &Path) -> Result<u64, BetterIoError> {
+    let (referenced, _) = referenced_blobs(root)?;
+
+    let mut freed = 0;
+    for orphan in orphaned_blobs(root, &referenced)? {
+        let meta = std::fs::symlink_metadata(&orphan)
+            .map_err(|e| BetterIoError::new(&orphan, "checking orphaned blob", e))?;
+
+        let res = match meta.is_dir() {
+            true => std::fs::remove_dir_all(&orphan),
+            false => std::fs::remove_file(&orphan),
+        };
+        res.map_err(|e| BetterIoError::new(&orphan, "removing orphaned blob", e))?;
+
+        freed += meta.len();
+    }
+
+    Ok(freed)
+}
+
+/// Removes corrupt blobs of `models`, so next pull downloads them again instead of reusing
+pub fn remove_corrupt_blobs(
+    root: &Path,
+    report: &StoreReport,
+    models: &[String],
+) -> Result<(), BetterIoError> {
+    for (digest, users) in &report.corrupt {
+        if !users.iter().any(|user| models.contains(user)) {
+            continue;
+        }
+
+        let location = blob_location(root, digest);
+
+        match std::fs::remove_file(&location) {
+            Ok(()) => (),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => return Err(BetterIoError::new(location, "removing corrupt blob", e)),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const HELLO_DIGEST: &str =
+        "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    const WORLD_DIGEST: &str =
+        "sha256:486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7";
+
+    fn scratch_store() -> TempDir {
+        let store = tempfile::tempdir().unwrap();
+        let root = store.path();
+
+        let manifest = format!(
+            r#"{{"config":{{"digest":"{HELLO_DIGEST}","size":5}},"layers":[{{"digest":"{WORLD_DIGEST}","size":5}}]}}"#
+        );
+        let manifest_dir = root.join("manifests/registry.ollama.ai/library/model");
+        std::fs::create_dir_all(&manifest_dir).unwrap();
+        std::fs::write(manifest_dir.join("latest"), manifest).unwrap();
+
+        std::fs::create_dir_all(root.join("blobs")).unwrap();
+        std::fs::write(blob_location(root, HELLO_DIGEST), "hello").unwrap();
+        std::fs::write(blob_location(root, WORLD_DIGEST), "w0rld").unwrap();
+        std::fs::write(root.join("blobs/sha256-orphan"), "orphan").unwrap();
+        std::fs::write(root.join("blobs/sha256-pulled-partial-0"), "pull").unwrap();
+
+        store
+    }
+
+    #[test]
+    fn reports_corrupt_missing_and_orphaned_blobs() {
+        let store = scratch_store();
+        let root = store.path();
+
+        let report = verify(root, |_| ()).unwrap();
+
+        assert_eq!(report.checked_models, 1);
+        assert!(report.missing.is_empty());
+        assert_eq!(
+            report.corrupt,
+            [(WORLD_DIGEST.to_owned(), vec!["model:latest".to_owned()])]
+        );
+        assert_eq!(report.orphaned, [root.join("blobs/sha256-orphan")]);
+        assert_eq!(report.broken_models(), ["model:latest"]);
+
+        remove_corrupt_blobs(root, &report, &report.broken_models()).unwrap();
+        let report = verify(root, |_| ()).unwrap();
+
+        assert!(report.corrupt.is_empty());
+        assert_eq!(
+            report.missing,
+            [(WORLD_DIGEST.to_owned(), vec!["model:latest".to_owned()])]
+        );
+
+        assert_eq!(remove_orphans(root).unwrap(), 6);
+        assert!(blob_location(root, HELLO_DIGEST).is_file());
+        assert!(root.join("blobs/sha256-pulled-partial-0").is_file());
+        assert!(verify(root, |_| ()).unwrap().orphaned.is_empty());
+    }
+}
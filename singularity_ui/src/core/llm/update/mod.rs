@@ -0,0 +1,300 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use serde::Deserialize;
+
+use crate::core::llm::download::{AssetSource, OLLAMA_ASSET};
+
+/// Github releases of ollama. Any server returning the same json can be used as manifest
+pub const DEFAULT_MANIFEST_URL: &str = "https://api.github.com/repos/ollama/ollama/releases";
+
+/// Github API refuses requests without user agent
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateError {
+    #[error("Network error. Reason: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("Manifest request failed with status {0}")]
+    FailedRequest(reqwest::StatusCode),
+    #[error("Malformed release manifest. Reason: {0}")]
+    Format(#[from] serde_json::Error),
+    #[error("Version {0} is not present in release manifest")]
+    UnknownVersion(String),
+}
+
+/// Numeric part of ollama version, `0.12.6-rc1` becomes `[0, 12, 6]` and keeps prerelease suffix aside
+#[derive(Debug, Clone)]
+pub struct Version {
+    numbers: Vec<u64>,
+    pre: Option<String>,
+}
+
+impl Version {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().trim_start_matches('v');
+        let (numbers, pre) = match value.split_once('-') {
+            Some((numbers, pre)) => (numbers, Some(pre.to_owned())),
+            None => (value, None),
+        };
+
+        let numbers = numbers
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect::<Option<Vec<u64>>>()?;
+
+        Some(Self { numbers, pre })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.numbers.len().max(other.numbers.len());
+        let number = |this: &Self, index: usize| this.numbers.get(index).copied().unwrap_or(0);
+
+        (0..len)
+            .map(|index| number(self, index).cmp(&number(other, index)))
+            .find(|ordering| ordering.is_ne())
+            // Release is newer than any of its prereleases
+            .unwrap_or_else(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(this), Some(other)) => this.cmp(other),
+            })
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    /// Release notes in markdown
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+impl Release {
+    pub fn version(&self) -> Option<Version> {
+        Version::parse(&self.tag_name)
+    }
+
+    /// Version as reported by `ollama -v`
+    pub fn version_string(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    pub fn notes(&self) -> &str {
+        self.body.as_deref().unwrap_or_default()
+    }
+
+    /// Release can be installed on this platform
+    pub fn has_platform_asset(&self) -> bool {
+        self.assets.iter().any(|asset| asset.name == OLLAMA_ASSET)
+    }
+
+    pub fn asset_source(&self) -> AssetSource {
+        AssetSource::Release(
+            self.assets
+                .iter()
+                .map(|asset| (asset.name.clone(), asset.browser_download_url.clone()))
+                .collect::<HashMap<_, _>>(),
+        )
+    }
+}
+
+pub async fn fetch_manifest(url: &str) -> Result<Vec<Release>, UpdateError> {
+    let response = reqwest::Client::new()
+        .get(url)
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(UpdateError::FailedRequest(response.status()));
+    }
+
+    let body = response.bytes().await?;
+
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Picks release which should be installed instead of `installed`.
+/// With `pinned` version only that release is considered, even if it is older.
+pub fn select_update<'a>(
+    installed: Option<&str>,
+    releases: &'a [Release],
+    pinned: Option<&str>,
+) -> Result<Option<&'a Release>, UpdateError> {
+    let installed = installed.and_then(Version::parse);
+
+    if let Some(pinned) = pinned {
+        let pinned_version =
+            Version::parse(pinned).ok_or_else(|| UpdateError::UnknownVersion(pinned.to_owned()))?;
+
+        let release = releases
+            .iter()
+            .find(|release| release.version().as_ref() == Some(&pinned_version))
+            .ok_or_else(|| UpdateError::UnknownVersion(pinned.to_owned()))?;
+
+        return Ok((installed.as_ref() != Some(&pinned_version)).then_some(release));
+    }
+
+    let latest = releases
+        .iter()
+        .filter(|release| !release.draft && !release.prerelease && release.has_platform_asset())
+        .filter_map(|release| Some((release.version()?, release)))
+        .max_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(latest
+        .filter(|(version, _)| {
+            installed
+                .as_ref()
+                .is_none_or(|installed| version > installed)
+        })
+        .map(|(_, release)| release))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::core::llm::download::AssetLocation;
+
+    fn release(tag: &str, prerelease: bool) -> serde_json::Value {
+        serde_json::json!({
+            "tag_name": tag,
+            "body": format!("Notes for {tag}"),
+            "draft": false,
+            "prerelease": prerelease,
+            "assets": [{
+                "name": OLLAMA_ASSET,
+                "browser_download_url": format!("http://mirror.local/{tag}/{OLLAMA_ASSET}"),
+                "size": 42
+            }]
+        })
+    }
+
+    /// Serves the same response to every request and returns its url
+    async fn manifest_server(status: &'static str, body: String) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0u8; 4096];
+                let _ = stream.read(&mut request).await;
+
+                let response = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        format!("http://{address}/releases")
+    }
+
+    #[test]
+    fn versions_are_compared_numerically() {
+        let parse = |value| Version::parse(value).unwrap();
+
+        assert!(parse("0.12.10") > parse("0.12.9"));
+        assert!(parse("v0.13.0") > parse("0.12.99"));
+        assert!(parse("0.12.6") > parse("0.12.6-rc1"));
+        assert_eq!(parse("0.12"), parse("0.12.0"));
+        assert!(Version::parse("unknown").is_none());
+    }
+
+    #[tokio::test]
+    async fn finds_latest_stable_release_from_local_manifest() {
+        let manifest = serde_json::json!([
+            release("v0.12.7-rc0", true),
+            release("v0.12.6", false),
+            release("v0.12.5", false),
+        ]);
+        let url = manifest_server("200 OK", manifest.to_string()).await;
+
+        let releases = fetch_manifest(&url).await.unwrap();
+        assert_eq!(releases.len(), 3);
+
+        let update = select_update(Some("0.12.5"), &releases, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(update.version_string(), "0.12.6");
+        assert_eq!(update.notes(), "Notes for v0.12.6");
+        assert_eq!(
+            update.asset_source().locate(OLLAMA_ASSET).unwrap(),
+            AssetLocation::Remote(format!("http://mirror.local/v0.12.6/{OLLAMA_ASSET}"))
+        );
+
+        assert!(
+            select_update(Some("0.12.6"), &releases, None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(select_update(None, &releases, None).unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn pinned_version_wins_over_latest() {
+        let manifest = serde_json::json!([release("v0.12.6", false), release("v0.12.5", false)]);
+        let url = manifest_server("200 OK", manifest.to_string()).await;
+
+        let releases = fetch_manifest(&url).await.unwrap();
+
+        let update = select_update(Some("0.12.6"), &releases, Some("0.12.5"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(update.version_string(), "0.12.5");
+
+        assert!(
+            select_update(Some("0.12.5"), &releases, Some("0.12.5"))
+                .unwrap()
+                .is_none()
+        );
+
+        assert!(matches!(
+            select_update(Some("0.12.5"), &releases, Some("0.1.0")),
+            Err(UpdateError::UnknownVersion(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reports_failed_manifest_request() {
+        let url = manifest_server("404 Not Found", "{}".to_owned()).await;
+
+        assert!(matches!(
+            fetch_manifest(&url).await,
+            Err(UpdateError::FailedRequest(reqwest::StatusCode::NOT_FOUND))
+        ));
+    }
+}
@@ -0,0 +1,399 @@
+#[cfg(target_os = "linux")]
+use std::path::Path;
+
+const NVIDIA_VENDOR_ID: u16 = 0x10de;
+const AMD_VENDOR_ID: u16 = 0x1002;
+const INTEL_VENDOR_ID: u16 = 0x8086;
+
+/// APUs report a small carve-out as VRAM. ROCm addon is huge and mostly useless for them
+const MIN_ROCM_VRAM: u64 = 2 * 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuVendor {
+    Nvidia,
+    Amd,
+    Intel,
+    Other(u16),
+}
+
+impl From<u16> for GpuVendor {
+    fn from(id: u16) -> Self {
+        match id {
+            NVIDIA_VENDOR_ID => Self::Nvidia,
+            AMD_VENDOR_ID => Self::Amd,
+            INTEL_VENDOR_ID => Self::Intel,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl std::fmt::Display for GpuVendor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Nvidia => f.write_str("NVIDIA"),
+            Self::Amd => f.write_str("AMD"),
+            Self::Intel => f.write_str("Intel"),
+            Self::Other(id) => write!(f, "Unknown({id:#06x})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuInfo {
+    pub vendor: GpuVendor,
+    pub device_id: Option<u16>,
+    /// Kernel driver name. Only known on linux
+    pub driver: Option<String>,
+    /// Dedicated memory in bytes. Only amdgpu exposes it through sysfs
+    pub vram: Option<u64>,
+}
+
+/// Which ollama runtime flavour makes sense for this machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeVariant {
+    Cpu,
+    Cuda,
+    Rocm,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GpuInventory {
+    pub gpus: Vec<GpuInfo>,
+}
+
+impl GpuInventory {
+    pub async fn detect() -> Self {
+        #[cfg(target_os = "linux")]
+        let inventory = Self::from_sysfs("/sys");
+
+        #[cfg(target_os = "windows")]
+        let inventory = Self::from_video_controllers().await;
+
+        tracing::info!("Detected GPUs: {}", inventory.summary());
+
+        inventory
+    }
+
+    /// Reads `class/drm/card*/device` entries from sysfs mounted at `root`
+    #[cfg(target_os = "linux")]
+    pub fn from_sysfs(root: impl AsRef<Path>) -> Self {
+        let drm = root.as_ref().join("class/drm");
+
+        let entries = match std::fs::read_dir(&drm) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!("Failed to read {}. Reason: {e}", drm.display());
+
+                return Self::default();
+            }
+        };
+
+        let mut cards = entries
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                // Skip connectors like `card0-DP-1` and render nodes
+                entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.strip_prefix("card"))
+                    .is_some_and(|index| index.chars().all(|c| c.is_ascii_digit()))
+            })
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+
+        cards.sort();
+
+        let gpus = cards
+            .iter()
+            .filter_map(|card| read_sysfs_device(&card.join("device")))
+            .collect();
+
+        Self { gpus }
+    }
+
+    /// Asks WMI for PCI ids of video controllers, `PCI\VEN_10DE&DEV_2684&...`
+    #[cfg(target_os = "windows")]
+    async fn from_video_controllers() -> Self {
+        let output = tokio::process::Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "Get-CimInstance Win32_VideoController | ForEach-Object { $_.PNPDeviceID }",
+            ])
+            .output()
+            .await;
+
+        let stdout = match output {
+            Ok(output) if output.status.success() => output.stdout,
+            Ok(_) | Err(_) => {
+                tracing::warn!("Failed to list video controllers");
+
+                return Self::default();
+            }
+        };
+
+        let gpus = String::from_utf8_lossy(&stdout)
+            .lines()
+            .filter_map(|line| {
+                let id = |key: &str| {
+                    let start = line.find(key)? + key.len();
+                    u16::from_str_radix(line.get(start..start + 4)?, 16).ok()
+                };
+
+                Some(GpuInfo {
+                    vendor: id("VEN_")?.into(),
+                    device_id: id("DEV_"),
+                    driver: None,
+                    vram: None,
+                })
+            })
+            .collect();
+
+        Self { gpus }
+    }
+
+    pub fn runtime_variant(&self) -> RuntimeVariant {
+        let cuda = self.gpus.iter().any(|gpu| {
+            gpu.vendor == GpuVendor::Nvidia
+                // nouveau can't run CUDA
+                && gpu.driver.as_deref().is_none_or(|driver| driver == "nvidia")
+        });
+
+        if cuda {
+            return RuntimeVariant::Cuda;
+        }
+
+        let rocm = self.gpus.iter().any(|gpu| {
+            gpu.vendor == GpuVendor::Amd
+                // Legacy `radeon` driver is not supported by ROCm
+                && gpu.driver.as_deref().is_none_or(|driver| driver == "amdgpu")
+                && gpu.vram.is_none_or(|vram| vram >= MIN_ROCM_VRAM)
+        });
+
+        if rocm {
+            return RuntimeVariant::Rocm;
+        }
+
+        RuntimeVariant::Cpu
+    }
+
+    /// Short human readable description, e.g. `AMD (amdgpu, 8.0 GB)`
+    pub fn summary(&self) -> String {
+        if self.gpus.is_empty() {
+            return "none".to_owned();
+        }
+
+        self.gpus
+            .iter()
+            .map(|gpu| {
+                let details = [
+                    gpu.driver.clone(),
+                    gpu.vram
+                        .map(|vram| format!("{:.1} GB", vram as f64 / 1024.0 / 1024.0 / 1024.0)),
+                ]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+
+                match details.is_empty() {
+                    true => gpu.vendor.to_string(),
+                    false => format!("{} ({})", gpu.vendor, details.join(", ")),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_sysfs_device(device: &Path) -> Option<GpuInfo> {
+    let read_hex = |name: &str| {
+        let value = std::fs::read_to_string(device.join(name)).ok()?;
+
+        u16::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok()
+    };
+
+    let vendor = read_hex("vendor")?.into();
+
+    let driver = std::fs::read_link(device.join("driver"))
+        .ok()
+        .and_then(|link| link.file_name()?.to_str().map(ToOwned::to_owned));
+
+    let vram = std::fs::read_to_string(device.join("mem_info_vram_total"))
+        .ok()
+        .and_then(|value| value.trim().parse().ok());
+
+    Some(GpuInfo {
+        vendor,
+        device_id: read_hex("device"),
+        driver,
+        vram,
+    })
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    struct FakeCard<'a> {
+        name: &'a str,
+        vendor: &'a str,
+        driver: Option<&'a str>,
+        vram: Option<u64>,
+    }
+
+    fn fake_sysfs(cards: &[FakeCard<'_>]) -> TempDir {
+        let sysfs = tempfile::tempdir().unwrap();
+        let root = sysfs.path();
+
+        for card in cards {
+            let device = root.join("class/drm").join(card.name).join("device");
+            std::fs::create_dir_all(&device).unwrap();
+
+            std::fs::write(device.join("vendor"), format!("{}\n", card.vendor)).unwrap();
+            std::fs::write(device.join("device"), "0x1234\n").unwrap();
+
+            if let Some(driver) = card.driver {
+                let driver_dir = root.join("bus/pci/drivers").join(driver);
+                std::fs::create_dir_all(&driver_dir).unwrap();
+                std::os::unix::fs::symlink(&driver_dir, device.join("driver")).unwrap();
+            }
+
+            if let Some(vram) = card.vram {
+                std::fs::write(device.join("mem_info_vram_total"), format!("{vram}\n")).unwrap();
+            }
+        }
+
+        std::fs::create_dir_all(root.join("class/drm")).unwrap();
+
+        sysfs
+    }
+
+    #[test]
+    fn empty_sysfs_means_cpu() {
+        let sysfs = fake_sysfs(&[]);
+
+        let inventory = GpuInventory::from_sysfs(sysfs.path());
+
+        assert!(inventory.gpus.is_empty());
+        assert_eq!(inventory.runtime_variant(), RuntimeVariant::Cpu);
+    }
+
+    #[test]
+    fn missing_sysfs_means_cpu() {
+        let inventory = GpuInventory::from_sysfs("/definitely/not/sysfs");
+
+        assert_eq!(inventory.runtime_variant(), RuntimeVariant::Cpu);
+    }
+
+    #[test]
+    fn intel_only_means_cpu() {
+        let sysfs = fake_sysfs(&[FakeCard {
+            name: "card0",
+            vendor: "0x8086",
+            driver: Some("i915"),
+            vram: None,
+        }]);
+
+        let inventory = GpuInventory::from_sysfs(sysfs.path());
+
+        assert_eq!(inventory.gpus.len(), 1);
+        assert_eq!(inventory.gpus[0].vendor, GpuVendor::Intel);
+        assert_eq!(inventory.gpus[0].device_id, Some(0x1234));
+        assert_eq!(inventory.gpus[0].driver.as_deref(), Some("i915"));
+        assert_eq!(inventory.runtime_variant(), RuntimeVariant::Cpu);
+    }
+
+    #[test]
+    fn amd_discrete_means_rocm() {
+        let sysfs = fake_sysfs(&[
+            FakeCard {
+                name: "card0",
+                vendor: "0x8086",
+                driver: Some("i915"),
+                vram: None,
+            },
+            FakeCard {
+                name: "card1",
+                vendor: "0x1002",
+                driver: Some("amdgpu"),
+                vram: Some(8 * 1024 * 1024 * 1024),
+            },
+        ]);
+
+        let inventory = GpuInventory::from_sysfs(sysfs.path());
+
+        assert_eq!(inventory.gpus[1].vram, Some(8 * 1024 * 1024 * 1024));
+        assert_eq!(inventory.runtime_variant(), RuntimeVariant::Rocm);
+        assert_eq!(inventory.summary(), "Intel (i915); AMD (amdgpu, 8.0 GB)");
+    }
+
+    #[test]
+    fn amd_apu_and_legacy_driver_mean_cpu() {
+        let sysfs = fake_sysfs(&[
+            FakeCard {
+                name: "card0",
+                vendor: "0x1002",
+                driver: Some("amdgpu"),
+                vram: Some(512 * 1024 * 1024),
+            },
+            FakeCard {
+                name: "card1",
+                vendor: "0x1002",
+                driver: Some("radeon"),
+                vram: None,
+            },
+        ]);
+
+        let inventory = GpuInventory::from_sysfs(sysfs.path());
+
+        assert_eq!(inventory.runtime_variant(), RuntimeVariant::Cpu);
+    }
+
+    #[test]
+    fn nvidia_wins_over_amd_igpu() {
+        let sysfs = fake_sysfs(&[
+            FakeCard {
+                name: "card0",
+                vendor: "0x1002",
+                driver: Some("amdgpu"),
+                vram: Some(4 * 1024 * 1024 * 1024),
+            },
+            FakeCard {
+                name: "card1",
+                vendor: "0x10de",
+                driver: Some("nvidia"),
+                vram: None,
+            },
+        ]);
+
+        let inventory = GpuInventory::from_sysfs(sysfs.path());
+
+        assert_eq!(inventory.runtime_variant(), RuntimeVariant::Cuda);
+    }
+
+    #[test]
+    fn nouveau_means_cpu_and_connectors_are_skipped() {
+        let sysfs = fake_sysfs(&[
+            FakeCard {
+                name: "card0",
+                vendor: "0x10de",
+                driver: Some("nouveau"),
+                vram: None,
+            },
+            FakeCard {
+                name: "card0-DP-1",
+                vendor: "0x1002",
+                driver: Some("amdgpu"),
+                vram: None,
+            },
+        ]);
+
+        let inventory = GpuInventory::from_sysfs(sysfs.path());
+
+        assert_eq!(inventory.gpus.len(), 1);
+        assert_eq!(inventory.runtime_variant(), RuntimeVariant::Cpu);
+    }
+}
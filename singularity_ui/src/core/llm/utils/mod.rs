@@ -1,5 +1,5 @@
 #[cfg(any(target_os = "linux", target_os = "windows"))]
-mod nvidia;
+mod gpu;
 
 #[cfg(any(target_os = "linux", target_os = "windows"))]
-pub use nvidia::*;
+pub use gpu::*;
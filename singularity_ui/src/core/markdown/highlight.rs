@@ -0,0 +1,135 @@
+//! Syntax highlighting of fenced code blocks
+
+use std::sync::LazyLock;
+
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Color, Theme, ThemeSet},
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEME: LazyLock<Theme> = LazyLock::new(|| {
+    ThemeSet::load_defaults()
+        .themes
+        .remove("base16-ocean.dark")
+        .unwrap_or_default()
+});
+
+/// Languages missing from bundled syntaxes, with extension of their files
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("typescript", "ts"),
+    ("ts", "ts"),
+    ("tsx", "tsx"),
+    ("jsx", "jsx"),
+    ("kotlin", "kt"),
+    ("swift", "swift"),
+    ("toml", "toml"),
+    ("dockerfile", "Dockerfile"),
+    ("powershell", "ps1"),
+    ("zig", "zig"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub color: [u8; 3],
+}
+
+fn syntax(language: Option<&str>) -> &'static SyntaxReference {
+    language
+        .and_then(|language| SYNTAXES.find_syntax_by_token(language))
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text())
+}
+
+/// Colored spans of each line. Unknown languages are kept as plain text
+pub fn highlight(code: &str, language: Option<&str>) -> Vec<Vec<Span>> {
+    let mut highlighter = HighlightLines::new(syntax(language), &THEME);
+
+    LinesWithEndings::from(code)
+        .map(|line| {
+            let Ok(ranges) = highlighter.highlight_line(line, &SYNTAXES) else {
+                let foreground = THEME.settings.foreground.unwrap_or(Color::WHITE);
+                return vec![Span {
+                    text: line.trim_end_matches(['\r', '\n']).to_owned(),
+                    color: [foreground.r, foreground.g, foreground.b],
+                }];
+            };
+
+            let mut spans: Vec<Span> = Vec::new();
+            for (style, text) in ranges {
+                let text = text.trim_end_matches(['\r', '\n']);
+                let color = [style.foreground.r, style.foreground.g, style.foreground.b];
+
+                match spans.last_mut() {
+                    // Whitespace takes color of previous span, so line has less of them
+                    Some(last) if last.color == color || text.trim().is_empty() => {
+                        last.text.push_str(text)
+                    }
+                    _ if text.is_empty() => (),
+                    _ => spans.push(Span {
+                        text: text.to_owned(),
+                        color,
+                    }),
+                }
+            }
+
+            spans
+        })
+        .collect()
+}
+
+/// Name suggested when code block is saved, e.g. `snippet.rs` for `rust`
+pub fn file_name(language: Option<&str>) -> String {
+    let language = language.map(str::to_lowercase);
+    let language = language.as_deref();
+
+    let extension = EXTENSIONS
+        .iter()
+        .find(|(name, _)| Some(*name) == language)
+        .map(|(_, extension)| *extension)
+        .or_else(|| {
+            language
+                .and_then(|language| SYNTAXES.find_syntax_by_token(language))
+                .and_then(|syntax| syntax.file_extensions.first())
+                .map(String::as_str)
+        });
+
+    match extension {
+        // Dockerfile and alike are file names by themselves
+        Some(name) if name.starts_with(char::is_uppercase) => name.to_owned(),
+        Some(extension) => format!("snippet.{extension}"),
+        None => "snippet.txt".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_known_language() {
+        let code = "fn main() {\n    let answer = 42;\n}";
+        let lines = highlight(code, Some("rust"));
+
+        let text: Vec<String> = lines
+            .iter()
+            .map(|spans| spans.iter().map(|span| span.text.as_str()).collect())
+            .collect();
+        assert_eq!(text.join("\n"), code);
+
+        assert!(lines[1].len() > 1);
+        assert!(highlight("plain words", Some("no-such-language"))[0].len() == 1);
+    }
+
+    #[test]
+    fn suggests_file_name_by_language() {
+        assert_eq!(file_name(Some("rust")), "snippet.rs");
+        assert_eq!(file_name(Some("Python")), "snippet.py");
+        assert_eq!(file_name(Some("typescript")), "snippet.ts");
+        assert_eq!(file_name(Some("dockerfile")), "Dockerfile");
+        assert_eq!(file_name(Some("unknown")), "snippet.txt");
+        assert_eq!(file_name(None), "snippet.txt");
+    }
+}
@@ -0,0 +1,358 @@
+//! Markdown of messages as flat list of blocks, which UI renders without nesting
+
+pub mod highlight;
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListItem {
+    /// `•` or number of ordered item, e.g. `2.`
+    pub marker: String,
+    pub text: String,
+    /// Nesting level, 0 for top level items
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    Paragraph(String),
+    Heading {
+        /// 1 to 6
+        level: u8,
+        text: String,
+    },
+    /// Nested lists are flattened into items with depth
+    List(Vec<ListItem>),
+    Code {
+        /// Language declared after opening fence
+        language: Option<String>,
+        code: String,
+    },
+    /// Text of quote, nested blocks are kept as plain text
+    Quote(String),
+    Table {
+        header: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+    Rule,
+}
+
+/// Numbering of list being parsed, `None` for bullet lists
+struct OpenList {
+    next: Option<u64>,
+}
+
+#[derive(Default)]
+struct Builder {
+    blocks: Vec<Block>,
+    /// Inline text of block being parsed
+    text: String,
+    /// Link targets waiting for end of link text
+    links: Vec<String>,
+
+    quote_depth: usize,
+    quote: String,
+
+    lists: Vec<OpenList>,
+    items: Vec<ListItem>,
+    /// Marker of item which text is collected
+    item_marker: Option<String>,
+
+    code: Option<Option<String>>,
+
+    table: Option<(Vec<String>, Vec<Vec<String>>)>,
+    row: Vec<String>,
+}
+
+impl Builder {
+    fn take_text(&mut self) -> String {
+        std::mem::take(&mut self.text).trim().to_owned()
+    }
+
+    /// Text of finished paragraph or heading
+    fn push_text_block(&mut self, block: impl FnOnce(String) -> Block) {
+        if self.quote_depth > 0 {
+            self.quote.push_str(&self.text);
+            self.quote.push_str("\n\n");
+            self.text.clear();
+        } else if !self.lists.is_empty() {
+            // Paragraphs of item stay in its text
+            self.text.push('\n');
+        } else {
+            let text = self.take_text();
+            if !text.is_empty() {
+                self.blocks.push(block(text));
+            }
+        }
+    }
+
+    /// Adds collected text as item of list
+    fn flush_item(&mut self) {
+        let text = self.take_text();
+
+        // Empty marker continues item which text was split by code block
+        if let Some(marker) = self.item_marker.take()
+            && !(marker.is_empty() && text.is_empty())
+        {
+            self.items.push(ListItem {
+                marker,
+                text,
+                depth: self.lists.len().saturating_sub(1),
+            });
+        }
+    }
+
+    /// Items collected so far become list block, e.g. before code block inside item
+    fn flush_list(&mut self) {
+        if !self.items.is_empty() {
+            self.blocks
+                .push(Block::List(std::mem::take(&mut self.items)));
+        }
+    }
+
+    fn start(&mut self, tag: Tag<'_>) {
+        match tag {
+            Tag::BlockQuote(_) => self.quote_depth += 1,
+            Tag::CodeBlock(kind) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().map(ToOwned::to_owned)
+                    }
+                    CodeBlockKind::Indented => None,
+                };
+
+                if self.quote_depth > 0 {
+                    return;
+                }
+
+                if !self.lists.is_empty() {
+                    // Item text before code keeps its place, rest of item continues after it
+                    if self.item_marker.is_some() {
+                        self.flush_item();
+                        self.item_marker = Some(String::new());
+                    }
+                    self.flush_list();
+                }
+
+                self.code = Some(language);
+            }
+            Tag::List(_) if self.quote_depth > 0 => (),
+            Tag::List(start) => {
+                if self.item_marker.is_some() {
+                    self.flush_item();
+                }
+                self.lists.push(OpenList { next: start });
+            }
+            Tag::Item if self.quote_depth > 0 => self.text.push_str("\n• "),
+            Tag::Item => {
+                let marker = match self.lists.last_mut() {
+                    Some(OpenList { next: Some(number) }) => {
+                        *number += 1;
+                        format!("{}.", *number - 1)
+                    }
+                    _ => "•".to_owned(),
+                };
+                self.item_marker = Some(marker);
+            }
+            Tag::Table(_) => self.table = Some((Vec::new(), Vec::new())),
+            Tag::Link { dest_url, .. } => self.links.push(dest_url.to_string()),
+            Tag::Image { dest_url, .. } => {
+                self.text.push_str("[image: ");
+                self.links.push(dest_url.to_string());
+            }
+            _ => (),
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => self.push_text_block(Block::Paragraph),
+            TagEnd::Heading(level) => self.push_text_block(|text| Block::Heading {
+                level: heading_level(level),
+                text,
+            }),
+            TagEnd::BlockQuote(_) => {
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+
+                if self.quote_depth == 0 {
+                    self.quote.push_str(&self.text);
+                    self.text.clear();
+
+                    let quote = std::mem::take(&mut self.quote).trim().to_owned();
+                    if !quote.is_empty() {
+                        self.blocks.push(Block::Quote(quote));
+                    }
+                }
+            }
+            TagEnd::CodeBlock => {
+                if let Some(language) = self.code.take() {
+                    let code = std::mem::take(&mut self.text);
+
+                    self.blocks.push(Block::Code {
+                        language,
+                        code: code.strip_suffix('\n').unwrap_or(&code).to_owned(),
+                    });
+                }
+            }
+            TagEnd::List(_) if self.quote_depth > 0 => (),
+            TagEnd::List(_) => {
+                self.lists.pop();
+
+                if self.lists.is_empty() {
+                    self.flush_list();
+                }
+            }
+            TagEnd::Item if self.quote_depth > 0 => (),
+            TagEnd::Item if self.item_marker.is_some() => self.flush_item(),
+            TagEnd::TableCell => {
+                let cell = self.take_text();
+                self.row.push(cell);
+            }
+            TagEnd::TableHead => {
+                let row = std::mem::take(&mut self.row);
+                if let Some((header, _)) = self.table.as_mut() {
+                    *header = row;
+                }
+            }
+            TagEnd::TableRow => {
+                let row = std::mem::take(&mut self.row);
+                if let Some((_, rows)) = self.table.as_mut() {
+                    rows.push(row);
+                }
+            }
+            TagEnd::Table => {
+                if let Some((header, rows)) = self.table.take() {
+                    self.blocks.push(Block::Table { header, rows });
+                }
+            }
+            TagEnd::Link => {
+                if let Some(url) = self.links.pop()
+                    && !self.text.ends_with(&url)
+                {
+                    self.text.push_str(&format!(" ({url})"));
+                }
+            }
+            TagEnd::Image => {
+                self.links.pop();
+                self.text.push(']');
+            }
+            _ => (),
+        }
+    }
+
+    fn event(&mut self, event: Event<'_>) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text)
+            | Event::Code(text)
+            | Event::InlineMath(text)
+            | Event::DisplayMath(text)
+            | Event::Html(text)
+            | Event::InlineHtml(text) => self.text.push_str(&text),
+            Event::SoftBreak => self.text.push(' '),
+            Event::HardBreak => self.text.push('\n'),
+            Event::TaskListMarker(checked) => {
+                self.text.push_str(if checked { "☑ " } else { "☐ " })
+            }
+            Event::Rule if self.quote_depth == 0 && self.lists.is_empty() => {
+                self.blocks.push(Block::Rule)
+            }
+            _ => (),
+        }
+    }
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+/// Splits message into blocks. Inline emphasis is dropped, links keep their target in brackets
+pub fn parse(text: &str) -> Vec<Block> {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+
+    let mut builder = Builder::default();
+    for event in Parser::new_ext(text, options) {
+        builder.event(event);
+    }
+
+    builder.blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_blocks() {
+        let blocks = parse(
+            "# Title\n\nSome **bold** and `code`, see [docs](https://docs.rs).\n\n\
+            > quoted\n> text\n\n---\n\n| a | b |\n|---|---|\n| 1 | 2 |\n",
+        );
+
+        assert_eq!(
+            blocks,
+            [
+                Block::Heading {
+                    level: 1,
+                    text: "Title".to_owned()
+                },
+                Block::Paragraph("Some bold and code, see docs (https://docs.rs).".to_owned()),
+                Block::Quote("quoted text".to_owned()),
+                Block::Rule,
+                Block::Table {
+                    header: vec!["a".to_owned(), "b".to_owned()],
+                    rows: vec![vec!["1".to_owned(), "2".to_owned()]],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn flattens_lists_around_code() {
+        let blocks = parse(
+            "3. Install\n   ```bash\n   cargo add tokio\n   ```\n4. Run\n   - fast\n   - safe\n",
+        );
+
+        let item = |marker: &str, text: &str, depth| ListItem {
+            marker: marker.to_owned(),
+            text: text.to_owned(),
+            depth,
+        };
+
+        assert_eq!(
+            blocks,
+            [
+                Block::List(vec![item("3.", "Install", 0)]),
+                Block::Code {
+                    language: Some("bash".to_owned()),
+                    code: "cargo add tokio".to_owned()
+                },
+                Block::List(vec![
+                    item("4.", "Run", 0),
+                    item("•", "fast", 1),
+                    item("•", "safe", 1)
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_unfinished_code() {
+        assert_eq!(
+            parse("```rust\nfn main() {"),
+            [Block::Code {
+                language: Some("rust".to_owned()),
+                code: "fn main() {".to_owned()
+            }]
+        );
+    }
+}
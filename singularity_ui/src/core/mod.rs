@@ -1 +1,6 @@
+pub mod conversations;
 pub mod llm;
+pub mod markdown;
+pub mod personas;
+pub mod settings;
+pub mod storage;
@@ -0,0 +1,295 @@
+//! Named presets of model, system prompt and parameters, chosen when conversation starts
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        conversations::{ConversationSettings, context::ContextSettings},
+        llm::get_or_create_app_dir,
+        llm::options::GenerationOptions,
+    },
+    error::BetterIoError,
+};
+
+const PERSONAS_FILENAME: &str = "personas.json";
+/// Marks files written by [`to_file`]
+const FILE_FORMAT: &str = "singularity-personas";
+const FILE_VERSION: u32 = 1;
+
+/// Serialises read-modify-write cycles of library
+static PERSONAS_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Debug, thiserror::Error)]
+pub enum PersonaError {
+    #[error(transparent)]
+    Io(#[from] BetterIoError),
+    #[error("Malformed personas file. Reason: {0}")]
+    Format(#[from] serde_json::Error),
+    #[error("Persona needs a name")]
+    EmptyName,
+    #[error("File has no personas")]
+    NoPersonas,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Persona {
+    pub name: String,
+    /// Empty for default model
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub system_prompt: String,
+    #[serde(default)]
+    pub options: GenerationOptions,
+    #[serde(default)]
+    pub context: ContextSettings,
+}
+
+impl Persona {
+    pub fn from_settings(name: String, settings: &ConversationSettings) -> Self {
+        Self {
+            name,
+            model: settings.model.clone().unwrap_or_default(),
+            system_prompt: settings.system_prompt.clone(),
+            options: settings.options.clone(),
+            context: settings.context.clone(),
+        }
+    }
+
+    /// Settings of conversation started with persona
+    pub fn settings(&self) -> ConversationSettings {
+        ConversationSettings {
+            model: Some(self.model.clone()).filter(|model| !model.is_empty()),
+            system_prompt: self.system_prompt.clone(),
+            options: self.options.clone(),
+            context: self.context.clone(),
+        }
+    }
+}
+
+/// Shared personas file, one persona file is accepted on import as well
+#[derive(Debug, Serialize, Deserialize)]
+struct PersonasFile {
+    format: String,
+    version: u32,
+    personas: Vec<Persona>,
+}
+
+/// Content of file sharing `personas`
+pub fn to_file(personas: &[Persona]) -> String {
+    let file = PersonasFile {
+        format: FILE_FORMAT.to_owned(),
+        version: FILE_VERSION,
+        personas: personas.to_vec(),
+    };
+
+    serde_json::to_string_pretty(&file).expect("personas are always serializable")
+}
+
+/// Personas in shared file, either written by [`to_file`] or a single persona object
+pub fn from_file(content: &[u8]) -> Result<Vec<Persona>, PersonaError> {
+    let value = serde_json::from_slice::<serde_json::Value>(content)?;
+
+    let personas = if value.get("personas").is_some() {
+        serde_json::from_value::<PersonasFile>(value)?.personas
+    } else {
+        vec![serde_json::from_value::<Persona>(value)?]
+    };
+
+    let personas = personas
+        .into_iter()
+        .map(|persona| Persona {
+            name: persona.name.trim().to_owned(),
+            ..persona
+        })
+        .filter(|persona| !persona.name.is_empty())
+        .collect::<Vec<_>>();
+
+    if personas.is_empty() {
+        return Err(PersonaError::NoPersonas);
+    }
+
+    Ok(personas)
+}
+
+/// File name suggested on export, e.g. `rust-reviewer.persona.json`
+pub fn file_name(persona: &Persona) -> String {
+    let name = persona
+        .name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if name.is_empty() {
+        "persona.json".to_owned()
+    } else {
+        format!("{name}.persona.json")
+    }
+}
+
+/// Library of personas in application data dir
+#[derive(Debug, Clone)]
+pub struct PersonaLibrary {
+    location: PathBuf,
+}
+
+impl PersonaLibrary {
+    pub fn new(location: impl Into<PathBuf>) -> Self {
+        Self {
+            location: location.into(),
+        }
+    }
+
+    pub async fn open_default() -> Result<Self, PersonaError> {
+        Ok(Self::new(
+            get_or_create_app_dir(None).await?.join(PERSONAS_FILENAME),
+        ))
+    }
+
+    /// Personas sorted by name. Missing file means empty library
+    pub async fn personas(&self) -> Result<Vec<Persona>, PersonaError> {
+        let content = match tokio::fs::read(&self.location).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(BetterIoError::new(&self.location, "reading personas", e).into()),
+        };
+
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    async fn write(location: &Path, personas: &[Persona]) -> Result<(), PersonaError> {
+        let content = serde_json::to_vec_pretty(personas)?;
+
+        if let Some(parent) = location.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| BetterIoError::new(parent, "creating personas dir", e))?;
+        }
+
+        // Write next to target and rename, so crash never leaves half written library
+        let temp = location.with_extension("json.tmp");
+
+        tokio::fs::write(&temp, content)
+            .await
+            .map_err(|e| BetterIoError::new(&temp, "writing personas", e))?;
+
+        tokio::fs::rename(&temp, location)
+            .await
+            .map_err(|e| BetterIoError::new(location, "replacing personas", e))?;
+
+        Ok(())
+    }
+
+    /// Loads library, applies change and saves it back. Returns updated personas
+    async fn update(
+        &self,
+        change: impl FnOnce(&mut Vec<Persona>),
+    ) -> Result<Vec<Persona>, PersonaError> {
+        let _guard = PERSONAS_LOCK.lock().await;
+
+        let mut personas = self.personas().await?;
+
+        change(&mut personas);
+        personas.sort_by_key(|persona| persona.name.to_lowercase());
+        Self::write(&self.location, &personas).await?;
+
+        Ok(personas)
+    }
+
+    /// Adds personas, replacing ones with same name
+    pub async fn save(&self, added: Vec<Persona>) -> Result<Vec<Persona>, PersonaError> {
+        if added.iter().any(|persona| persona.name.trim().is_empty()) {
+            return Err(PersonaError::EmptyName);
+        }
+
+        self.update(|personas| {
+            for persona in added {
+                personas.retain(|existing| existing.name != persona.name);
+                personas.push(persona);
+            }
+        })
+        .await
+    }
+
+    pub async fn remove(&self, name: String) -> Result<Vec<Persona>, PersonaError> {
+        self.update(|personas| personas.retain(|persona| persona.name != name))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn persona(name: &str, model: &str) -> Persona {
+        Persona {
+            name: name.to_owned(),
+            model: model.to_owned(),
+            system_prompt: format!("You are {name}"),
+            options: GenerationOptions {
+                temperature: Some(0.2),
+                ..GenerationOptions::default()
+            },
+            context: ContextSettings::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn library_replaces_persona_with_same_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = PersonaLibrary::new(dir.path().join(PERSONAS_FILENAME));
+
+        assert!(library.personas().await.unwrap().is_empty());
+
+        library
+            .save(vec![
+                persona("Translator", ""),
+                persona("Rust reviewer", ""),
+            ])
+            .await
+            .unwrap();
+        let personas = library
+            .save(vec![persona("Translator", "llama3.2")])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            personas
+                .iter()
+                .map(|persona| (persona.name.as_str(), persona.model.as_str()))
+                .collect::<Vec<_>>(),
+            [("Rust reviewer", ""), ("Translator", "llama3.2")]
+        );
+        assert_eq!(library.personas().await.unwrap(), personas);
+
+        library.remove("Translator".to_owned()).await.unwrap();
+        assert_eq!(library.personas().await.unwrap().len(), 1);
+        assert!(library.save(vec![persona(" ", "")]).await.is_err());
+    }
+
+    #[test]
+    fn shares_personas_through_files() {
+        let personas = vec![persona("Rust reviewer", "qwen2.5-coder")];
+
+        assert_eq!(from_file(to_file(&personas).as_bytes()).unwrap(), personas);
+        assert_eq!(
+            from_file(br#"{"name": " Commit writer ", "system_prompt": "Write commits"}"#).unwrap()
+                [0]
+            .name,
+            "Commit writer"
+        );
+        assert!(from_file(br#"{"format": "x", "version": 1, "personas": []}"#).is_err());
+        assert_eq!(file_name(&personas[0]), "rust-reviewer.persona.json");
+
+        let settings = personas[0].settings();
+        assert_eq!(settings.model(), "qwen2.5-coder");
+        assert_eq!(
+            Persona::from_settings("Rust reviewer".to_owned(), &settings),
+            personas[0]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{core::llm::get_or_create_app_dir, error::BetterIoError};
+
+const SETTINGS_FILENAME: &str = "settings.json";
+
+/// Serialises read-modify-write cycles of [`update`]
+static SETTINGS_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error(transparent)]
+    Io(#[from] BetterIoError),
+    #[error("Malformed settings file. Reason: {0}")]
+    Format(#[from] serde_json::Error),
+}
+
+/// User settings persisted in application data dir.
+/// Every field has a default, so settings written by older versions keep loading.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub update: UpdateSettings,
+    pub runtime: RuntimeSettings,
+    pub models: ModelSettings,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelSettings {
+    /// Model store passed to ollama through `OLLAMA_MODELS`. `None` keeps ollama default
+    pub store: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuntimeSettings {
+    /// Start ollama found in `$PATH` instead of app managed one
+    pub use_system: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateSettings {
+    /// Url of release list in github releases format
+    pub manifest_url: String,
+    /// Keeps runtime on this version instead of latest one
+    pub pinned_version: Option<String>,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            manifest_url: crate::core::llm::update::DEFAULT_MANIFEST_URL.to_owned(),
+            pinned_version: None,
+        }
+    }
+}
+
+async fn settings_location() -> Result<PathBuf, BetterIoError> {
+    Ok(get_or_create_app_dir(None).await?.join(SETTINGS_FILENAME))
+}
+
+/// Reads settings from disk. Missing file means defaults
+pub async fn load() -> Result<Settings, SettingsError> {
+    read(&settings_location().await?).await
+}
+
+/// Reads settings at startup. Malformed file is moved aside and defaults are used,
+/// so broken settings never stop the app
+pub async fn load_or_reset() -> Result<Settings, SettingsError> {
+    reset_malformed(&settings_location().await?).await
+}
+
+async fn read(location: &Path) -> Result<Settings, SettingsError> {
+    let content = match tokio::fs::read(location).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Settings::default()),
+        Err(e) => return Err(BetterIoError::new(location, "reading settings", e).into()),
+    };
+
+    Ok(serde_json::from_slice(&content)?)
+}
+
+async fn reset_malformed(location: &Path) -> Result<Settings, SettingsError> {
+    match read(location).await {
+        Err(SettingsError::Format(e)) => {
+            tracing::error!("Failed to parse settings, using defaults. Reason: {e}");
+
+            let backup = location.with_extension("json.bad");
+
+            tokio::fs::rename(location, &backup)
+                .await
+                .map_err(|e| BetterIoError::new(location, "moving malformed settings aside", e))?;
+
+            tracing::info!("Moved malformed settings to {}", backup.display());
+
+            Ok(Settings::default())
+        }
+        res => res,
+    }
+}
+
+pub async fn save(settings: &Settings) -> Result<(), SettingsError> {
+    let location = settings_location().await?;
+    let content = serde_json::to_vec_pretty(settings)?;
+
+    // Write next to target and rename, so crash never leaves half written settings
+    let temp = location.with_extension("json.tmp");
+
+    tokio::fs::write(&temp, content)
+        .await
+        .map_err(|e| BetterIoError::new(&temp, "writing settings", e))?;
+
+    tokio::fs::rename(&temp, &location)
+        .await
+        .map_err(|e| BetterIoError::new(&location, "replacing settings", e))?;
+
+    Ok(())
+}
+
+/// Loads settings, applies change and saves them back. Returns updated settings
+pub async fn update(change: impl FnOnce(&mut Settings)) -> Result<Settings, SettingsError> {
+    let _guard = SETTINGS_LOCK.lock().await;
+
+    let mut settings = load().await?;
+
+    change(&mut settings);
+    save(&settings).await?;
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn malformed_settings_are_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join(SETTINGS_FILENAME);
+
+        tokio::fs::write(&location, b"{\"runtime\": ")
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            read(&location).await,
+            Err(SettingsError::Format(_))
+        ));
+        assert_eq!(
+            reset_malformed(&location).await.unwrap(),
+            Settings::default()
+        );
+
+        // Following loads see no file and start from defaults as well
+        assert_eq!(read(&location).await.unwrap(), Settings::default());
+        assert!(location.with_extension("json.bad").exists());
+    }
+
+    #[tokio::test]
+    async fn valid_settings_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join(SETTINGS_FILENAME);
+
+        let mut settings = Settings::default();
+        settings.runtime.use_system = true;
+
+        tokio::fs::write(&location, serde_json::to_vec(&settings).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(reset_malformed(&location).await.unwrap(), settings);
+        assert!(location.exists());
+    }
+}
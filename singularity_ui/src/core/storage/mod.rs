@@ -0,0 +1,345 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    APP_ID,
+    core::{
+        llm::{get_or_create_app_dir, llm_unload, models_dir, serve::system_binary_location},
+        settings::{self, SettingsError},
+    },
+    error::BetterIoError,
+};
+
+/// Subdirectory of application data dir with saved conversations
+pub(crate) const CONVERSATIONS_DIR: &str = "conversations";
+/// Subdirectory of application data dir with log files
+const LOGS_DIR: &str = "logs";
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error(transparent)]
+    Io(#[from] BetterIoError),
+    #[error(transparent)]
+    Settings(#[from] SettingsError),
+    #[error("Storage task failed. Reason: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    #[error("Model store {0} is shared with system ollama and is kept")]
+    SharedModels(PathBuf),
+}
+
+/// Kinds of data written by the app, which can be removed separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageCategory {
+    /// Runtime archives kept in cache dir for reinstall
+    Archives,
+    /// App managed ollama, with staged and previous installs
+    Runtime,
+    /// Ollama model store
+    Models,
+    Conversations,
+    Logs,
+}
+
+impl StorageCategory {
+    pub const ALL: [Self; 5] = [
+        Self::Archives,
+        Self::Runtime,
+        Self::Models,
+        Self::Conversations,
+        Self::Logs,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Archives => "Cached archives",
+            Self::Runtime => "Runtime",
+            Self::Models => "Models",
+            Self::Conversations => "Conversations",
+            Self::Logs => "Logs",
+        }
+    }
+
+    /// Removing this data stops the chat until runtime and model are installed again
+    pub fn needs_reinstall(self) -> bool {
+        matches!(self, Self::Runtime | Self::Models)
+    }
+
+    /// Files and dirs which belong to category. Some of them may not exist
+    pub async fn locations(self) -> Result<Vec<PathBuf>, StorageError> {
+        let locations = match self {
+            Self::Archives => vec![cache_dir().await?],
+            Self::Runtime => runtime_dirs().await?,
+            Self::Models => vec![models_dir().await?],
+            Self::Conversations => vec![get_or_create_app_dir(None).await?.join(CONVERSATIONS_DIR)],
+            Self::Logs => vec![logs_dir()],
+        };
+
+        Ok(locations)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CategoryUsage {
+    pub category: StorageCategory,
+    pub locations: Vec<PathBuf>,
+    pub bytes: u64,
+    /// Data is used by ollama installed outside of the app and can't be cleaned
+    pub shared: bool,
+}
+
+/// Where log files are written. Sync, because logger is set up before async runtime starts
+pub fn logs_dir() -> PathBuf {
+    dirs::data_dir()
+        .expect("invalid os")
+        .join(APP_ID)
+        .join(LOGS_DIR)
+}
+
+async fn cache_dir() -> Result<PathBuf, BetterIoError> {
+    get_or_create_app_dir(Some(dirs::cache_dir().expect("invalid os"))).await
+}
+
+async fn runtime_dirs() -> Result<Vec<PathBuf>, BetterIoError> {
+    #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+    return Ok(crate::core::llm::install_layout()
+        .await?
+        .dirs()
+        .map(Path::to_path_buf)
+        .to_vec());
+
+    // Ollama.app in /Applications is shared with the system and never removed
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    Ok(vec![
+        get_or_create_app_dir(None)
+            .await?
+            .join(crate::core::llm::OLLAMA_DATA_DIR),
+    ])
+}
+
+/// Total size of files under `location`. Symlinks are not followed
+fn disk_usage(location: &Path) -> Result<u64, BetterIoError> {
+    let meta = match std::fs::symlink_metadata(location) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => {
+            return Err(BetterIoError::new(
+                location,
+                "reading size of stored file",
+                e,
+            ));
+        }
+    };
+
+    if !meta.is_dir() {
+        return Ok(meta.len());
+    }
+
+    let entries = std::fs::read_dir(location)
+        .map_err(|e| BetterIoError::new(location, "listing stored files", e))?;
+
+    let mut total = 0;
+    for entry in entries {
+        let entry = entry.map_err(|e| BetterIoError::new(location, "listing stored files", e))?;
+
+        total += disk_usage(&entry.path())?;
+    }
+
+    Ok(total)
+}
+
+fn remove_location(location: &Path) -> Result<(), BetterIoError> {
+    let res = match std::fs::symlink_metadata(location) {
+        Ok(meta) if meta.is_dir() => std::fs::remove_dir_all(location),
+        Ok(_) => std::fs::remove_file(location),
+        Err(e) => Err(e),
+    };
+
+    match res {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(BetterIoError::new(location, "removing stored files", e)),
+    }
+}
+
+/// Current log file may be held open by the logger, so failures only skip that file
+fn remove_logs(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let _ = remove_location(&entry.path())
+            .inspect_err(|e| tracing::warn!("Failed to remove log file. Reason: {e}"));
+    }
+}
+
+/// Reports disk usage of every category
+pub async fn usage() -> Result<Vec<CategoryUsage>, StorageError> {
+    let shared_store = shared_model_store().await?;
+    let mut usage = Vec::with_capacity(StorageCategory::ALL.len());
+
+    for category in StorageCategory::ALL {
+        let locations = category.locations().await?;
+
+        let bytes = tokio::task::spawn_blocking({
+            let locations = locations.clone();
+
+            move || {
+                locations
+                    .iter()
+                    .map(|location| disk_usage(location))
+                    .sum::<Result<u64, _>>()
+            }
+        })
+        .await??;
+
+        usage.push(CategoryUsage {
+            category,
+            locations,
+            bytes,
+            shared: category == StorageCategory::Models && shared_store.is_some(),
+        });
+    }
+
+    Ok(usage)
+}
+
+/// Store outside of application data dir is shared when ollama installed outside of the app can use it.
+/// Without such ollama the store is managed by the app only, wherever it lives
+fn is_shared_store(store: &Path, app_dir: &Path, has_system_ollama: bool) -> bool {
+    has_system_ollama && !store.starts_with(app_dir)
+}
+
+/// Model store which system ollama uses as well, so the app never removes it
+pub async fn shared_model_store() -> Result<Option<PathBuf>, StorageError> {
+    let store = models_dir().await?;
+    let app_dir = get_or_create_app_dir(None).await?;
+    let has_system_ollama =
+        settings::load().await?.runtime.use_system || system_binary_location().is_some();
+
+    Ok(is_shared_store(&store, &app_dir, has_system_ollama).then_some(store))
+}
+
+/// Checked before backend is stopped, so refused cleanup leaves chat running
+fn ensure_removable(
+    category: StorageCategory,
+    shared_store: Option<&Path>,
+) -> Result<(), StorageError> {
+    match shared_store {
+        Some(store) if category == StorageCategory::Models => {
+            Err(StorageError::SharedModels(store.to_path_buf()))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn remove_category(category: StorageCategory, locations: &[PathBuf]) -> Result<(), StorageError> {
+    match category {
+        StorageCategory::Logs => locations.iter().for_each(|dir| remove_logs(dir)),
+        _ => locations
+            .iter()
+            .try_for_each(|location| remove_location(location))?,
+    }
+
+    Ok(())
+}
+
+/// Removes data of given category. Running backend is stopped first, so it doesn't hold removed files
+pub async fn clean(category: StorageCategory) -> anyhow::Result<()> {
+    let locations = category.locations().await?;
+    let shared_store = match category {
+        StorageCategory::Models => shared_model_store().await?,
+        _ => None,
+    };
+
+    ensure_removable(category, shared_store.as_deref())?;
+
+    if category.needs_reinstall() {
+        llm_unload().await?;
+    }
+
+    tracing::info!("Removing {}: {locations:?}", category.label());
+
+    tokio::task::spawn_blocking(move || remove_category(category, &locations))
+        .await
+        .map_err(StorageError::from)??;
+
+    Ok(())
+}
+
+/// Returns app to the first run state: removes every category and settings.
+/// Model store shared with system ollama is kept.
+pub async fn reset_app_data() -> anyhow::Result<()> {
+    let app_dir = get_or_create_app_dir(None).await?;
+    let shared_store = shared_model_store().await?;
+
+    for category in StorageCategory::ALL {
+        if category == StorageCategory::Models
+            && let Some(store) = &shared_store
+        {
+            tracing::info!("Keeping shared model store {}", store.display());
+
+            continue;
+        }
+
+        clean(category).await?;
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let entries = std::fs::read_dir(&app_dir)
+            .map_err(|e| BetterIoError::new(&app_dir, "listing application dir", e))?;
+
+        // Logs dir is skipped, it is cleaned above and logger keeps writing into it
+        for entry in entries.flatten() {
+            if entry.file_name() != LOGS_DIR {
+                remove_location(&entry.path())?;
+            }
+        }
+
+        Ok::<_, BetterIoError>(())
+    })
+    .await
+    .map_err(StorageError::from)?
+    .map_err(StorageError::from)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_model_store_is_never_removed() {
+        let shared = tempfile::tempdir().unwrap();
+        let blob = shared.path().join("blobs/sha256-model");
+        std::fs::create_dir_all(blob.parent().unwrap()).unwrap();
+        std::fs::write(&blob, "model").unwrap();
+
+        let res = ensure_removable(StorageCategory::Models, Some(shared.path()));
+
+        assert!(matches!(res, Err(StorageError::SharedModels(dir)) if dir == shared.path()));
+        assert!(blob.is_file());
+    }
+
+    #[test]
+    fn app_model_store_is_removed() {
+        let store = tempfile::tempdir().unwrap();
+        let models = store.path().join("models");
+        std::fs::create_dir_all(models.join("blobs")).unwrap();
+
+        ensure_removable(StorageCategory::Models, None).unwrap();
+        remove_category(StorageCategory::Models, std::slice::from_ref(&models)).unwrap();
+
+        assert!(!models.exists());
+    }
+
+    #[test]
+    fn store_outside_app_dir_is_shared_only_with_system_ollama() {
+        let app_dir = Path::new("/data/app");
+        let default_store = Path::new("/home/user/.ollama/models");
+
+        assert!(!is_shared_store(default_store, app_dir, false));
+        assert!(is_shared_store(default_store, app_dir, true));
+        assert!(!is_shared_store(&app_dir.join("models"), app_dir, true));
+    }
+}
@@ -1,12 +1,21 @@
-use std::{rc::Rc, sync::LazyLock};
+use std::sync::LazyLock;
 
-use slint::{ModelRc, ToSharedString, VecModel};
+use slint::ToSharedString;
 
-use crate::core::llm::{llm_download, llm_download_model, llm_generate, llm_load};
+use crate::{
+    app::runtime::{describe_progress, download_options, refresh_runtime_info},
+    core::llm::{
+        download::AssetSource, llm_can_rollback, llm_download, llm_download_model, llm_load,
+        llm_preflight, llm_use_system,
+    },
+};
 
+mod app;
 mod core;
 mod error;
 
+pub use core::storage::logs_dir;
+
 slint::include_modules!();
 
 const APP_ID: &str = env!("APP_ID");
@@ -18,13 +27,40 @@ pub static TOKIO_RUNTIME: LazyLock<tokio::runtime::Runtime> = LazyLock::new(|| {
         .expect("Critical error. Failed to start tokio runtime")
 });
 
+/// Maps source picked in download dialog to location of runtime archives
+fn install_source(ui: &App) -> AssetSource {
+    let value = ui.get_install_source_value().trim().to_owned();
+
+    match ui.get_install_source_kind() {
+        1 if !value.is_empty() => AssetSource::BaseUrl(value),
+        2 if !value.is_empty() => AssetSource::Archive(value.into()),
+        3 if !value.is_empty() => AssetSource::Bundle(value.into()),
+        _ => AssetSource::default(),
+    }
+}
+
 pub fn setup_app() -> Result<App, Box<dyn std::error::Error>> {
-    let is_ollama_installed = TOKIO_RUNTIME.block_on(core::llm::version())?.is_some();
+    // Read once before anything else, so malformed settings are reset instead of stopping startup
+    let settings = TOKIO_RUNTIME.block_on(core::settings::load_or_reset())?;
+
+    let ollama_version = TOKIO_RUNTIME.block_on(core::llm::version(&settings))?;
+    let is_ollama_installed = ollama_version.is_some();
+    let can_rollback = TOKIO_RUNTIME.block_on(llm_can_rollback())?;
 
     let ui = App::new()?;
 
     ui.set_show_download_warning(is_ollama_installed);
     ui.set_finished_loading(is_ollama_installed);
+    ui.set_runtime_version(ollama_version.unwrap_or_default().to_shared_string());
+    ui.set_can_rollback(can_rollback);
+
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    ui.set_gpu_summary(
+        TOKIO_RUNTIME
+            .block_on(core::llm::utils::GpuInventory::detect())
+            .summary()
+            .to_shared_string(),
+    );
 
     ui.on_download_accepted({
         let ui = ui.clone_strong();
@@ -34,12 +70,46 @@ pub fn setup_app() -> Result<App, Box<dyn std::error::Error>> {
                 let ui = ui.clone_strong();
 
                 async move {
-                    let _ = async_compat::Compat::new(llm_download())
-                        .await
-                        .inspect_err(|e| {
-                            let _ = slint::quit_event_loop();
-                            tracing::error!("Failed to download ollama. Reason: {e}");
-                        });
+                    let use_system = ui.get_use_system_ollama();
+                    let source = install_source(&ui);
+
+                    ui.set_download_status("Checking free disk space…".into());
+
+                    let preflight =
+                        async_compat::Compat::new(llm_preflight((!use_system).then_some(&source)))
+                            .await;
+
+                    match preflight {
+                        Ok(warnings) => ui.set_download_warning(
+                            warnings
+                                .iter()
+                                .map(ToString::to_string)
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                                .to_shared_string(),
+                        ),
+                        Err(e) => {
+                            tracing::error!("Download is blocked. Reason: {e}");
+                            ui.set_download_error(e.to_shared_string());
+
+                            return;
+                        }
+                    }
+
+                    let options = download_options(&ui, ui.get_keep_archives(), |ui, progress| {
+                        ui.set_download_status(describe_progress(progress).to_shared_string());
+                        ui.set_download_progress(progress.fraction());
+                    });
+
+                    let res = match use_system {
+                        true => async_compat::Compat::new(llm_use_system(true)).await,
+                        false => async_compat::Compat::new(llm_download(source, options)).await,
+                    };
+
+                    let _ = res.inspect_err(|e| {
+                        let _ = slint::quit_event_loop();
+                        tracing::error!("Failed to download ollama. Reason: {e}");
+                    });
 
                     let _ = async_compat::Compat::new(llm_load())
                         .await
@@ -55,6 +125,8 @@ pub fn setup_app() -> Result<App, Box<dyn std::error::Error>> {
                             tracing::error!("Failed to download model. Reason: {e}");
                         });
 
+                    refresh_runtime_info(&ui).await;
+
                     ui.set_finished_loading(true);
                 }
             })
@@ -66,50 +138,14 @@ pub fn setup_app() -> Result<App, Box<dyn std::error::Error>> {
         }
     });
 
-    let messages = Rc::new(VecModel::from(Vec::new()));
-
-    let messages_rc: ModelRc<ChatMessage> = messages.clone().into();
-    ui.set_messages(messages_rc);
-
-    ui.on_send_clicked({
-        let messages = messages.clone();
-        move |text| {
-            messages.push(ChatMessage {
-                is_user: true,
-                text: text.clone(),
-            });
-
-            let _ = slint::spawn_local({
-                let messages = messages.clone();
-
-                async move {
-                    let res = async_compat::Compat::new(llm_load()).await;
-
-                    if res.is_err() {
-                        let _ = slint::quit_event_loop();
-                    }
-
-                    let res = async_compat::Compat::new(llm_generate(text.to_string())).await;
-
-                    let text = match res {
-                        Ok(text) => text,
-                        Err(e) => {
-                            tracing::error!("Failed to generate msg. Reason: {e}");
-                            let _ = slint::quit_event_loop();
-                            return;
-                        }
-                    }
-                    .to_shared_string();
-
-                    messages.push(ChatMessage {
-                        is_user: false,
-                        text,
-                    });
-                }
-            })
-            .inspect_err(|e| tracing::error!("Failed to generate msg. Reason: {e}"));
-        }
-    });
+    app::runtime::setup(&ui, &settings)?;
+    app::storage::setup(&ui, &settings)?;
+    app::integrity::setup(&ui);
+    app::chat::setup(&ui)?;
+    app::personas::setup(&ui)?;
+    app::markdown::setup(&ui);
+    app::attachments::setup(&ui);
+    app::import::setup(&ui)?;
 
     Ok(ui)
 }
@@ -2,17 +2,28 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    setup_logger();
+    // Flushes file log on exit
+    let _log_guard = setup_logger();
 
     singularity_ui_lib::main()
 }
 
-fn setup_logger() {
+fn setup_logger() -> tracing_appender::non_blocking::WorkerGuard {
     use tracing_subscriber::layer::SubscriberExt;
     use tracing_subscriber::util::SubscriberInitExt;
 
+    let (file_writer, guard) = tracing_appender::non_blocking(tracing_appender::rolling::daily(
+        singularity_ui_lib::logs_dir(),
+        "singularity.log",
+    ));
+
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer())
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(file_writer),
+        )
         .with(
             tracing_subscriber::EnvFilter::from_default_env()
                 .add_directive("winit=warn".parse().unwrap())
@@ -21,4 +32,6 @@ fn setup_logger() {
                 .add_directive("reqwest=warn".parse().unwrap()),
         )
         .init();
+
+    guard
 }